@@ -1,3 +1,5 @@
+use crate::config::DefaultMode;
+use anyhow::{Result, bail};
 use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
@@ -20,6 +22,90 @@ pub struct Cli {
     #[arg(long, short)]
     pub worktree: bool,
 
+    /// Show staged and unstaged changes together (equivalent to git diff HEAD)
+    #[arg(long, conflicts_with_all = ["cached", "worktree", "patch", "targets"])]
+    pub head: bool,
+
+    /// Show what the current branch adds on top of its upstream (git diff @{upstream}...HEAD)
+    #[arg(long, conflicts_with_all = ["cached", "worktree", "head", "patch", "targets"])]
+    pub upstream: bool,
+
+    /// Treat targets strictly as git refs, never as files or directories
+    #[arg(long = "ref", global = true)]
+    pub force_ref: bool,
+
+    /// Read the diff from a patch file instead of running git
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["targets", "cached"])]
+    pub patch: Option<String>,
+
+    /// Only show changes under this path or pathspec (repeatable)
+    #[arg(long = "path", value_name = "PATHSPEC", global = true)]
+    pub paths: Vec<String>,
+
+    /// Pathspecs after `--` restrict the diff like `--path`
+    #[arg(last = true, value_name = "PATHSPEC")]
+    pub pathspec: Vec<String>,
+
+    /// Only show files with these kinds of changes, e.g. A (added), D or AM
+    /// (git diff --diff-filter)
+    #[arg(long, value_name = "FILTER", value_parser = parse_diff_filter, global = true)]
+    pub diff_filter: Option<String>,
+
+    /// Number of context lines to show around each change (git diff -U)
+    #[arg(long, short = 'U', value_name = "N")]
+    pub context: Option<u32>,
+
+    /// Merge hunks separated by up to N unchanged lines (git diff --inter-hunk-context)
+    #[arg(long, value_name = "N")]
+    pub inter_hunk_context: Option<u32>,
+
+    /// Include untracked files (status mode only)
+    #[arg(long, global = true)]
+    pub untracked: bool,
+
+    /// Ask before showing more than N files in the tree, offering to show only the first N
+    /// (the diff itself is still read in full)
+    #[arg(long, value_name = "N")]
+    pub max_files: Option<usize>,
+
+    /// Show the diffs read at startup instead of refetching each file from git
+    #[arg(long)]
+    pub instant: bool,
+
+    /// Refetch the diff every SECONDS (default 2) and show new changes as they appear
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "2"
+    )]
+    pub watch: Option<u64>,
+
+    /// Show git's raw diff next to the diff tool's output, to debug pager configuration
+    #[arg(long)]
+    pub debug_pager: bool,
+
+    /// Draw in the normal screen buffer, leaving the last frame in the scrollback
+    #[arg(long)]
+    pub no_alt_screen: bool,
+
+    /// Print the resolved diff tool command for the first file instead of starting the TUI
+    #[arg(long)]
+    pub print_diff_command: bool,
+
+    /// Write the diff of all files to a patch file instead of starting the TUI
+    #[arg(long, value_name = "FILE")]
+    pub export: Option<String>,
+
+    /// Print a JSON summary of changed files instead of starting the TUI
+    #[arg(long)]
+    pub json: bool,
+
+    /// Exit with status 1 if there are differences and 0 otherwise, without starting the TUI
+    #[arg(long)]
+    pub exit_code: bool,
+
     /// Configuration file path
     #[arg(long, value_name = "FILE")]
     pub config: Option<String>,
@@ -29,6 +115,20 @@ pub struct Cli {
     pub verbose: bool,
 }
 
+/// Validate a `--diff-filter` value: git's change letters, lowercase to exclude a kind
+fn parse_diff_filter(value: &str) -> Result<String> {
+    if value.is_empty() {
+        bail!("expected letters such as A, M, D or R");
+    }
+    if let Some(c) = value
+        .chars()
+        .find(|c| !"ACDMRTUXB".contains(c.to_ascii_uppercase()))
+    {
+        bail!("unknown change kind '{c}' (expected letters of ACDMRTUXB)");
+    }
+    Ok(value.to_string())
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     /// Compare git refs, files, or directories
@@ -43,11 +143,28 @@ pub enum Commands {
     },
     /// Show current git status with diffs
     Status,
+    /// Show files with merge conflicts, with their conflict markers
+    Conflicts,
+    /// List built-in themes or preview one
+    Themes {
+        /// Theme to preview with sample files and diff
+        #[arg(long, value_name = "NAME")]
+        preview: Option<String>,
+    },
     /// Generate shell completions
     Completions {
         #[arg(value_enum)]
         shell: clap_complete::Shell,
     },
+    /// Forget the checked files and hunks remembered for this repository
+    Clear {
+        /// Clear the check states of every repository
+        #[arg(long)]
+        all: bool,
+        /// Don't ask for confirmation
+        #[arg(long, short)]
+        force: bool,
+    },
 }
 
 impl Cli {
@@ -55,8 +172,21 @@ impl Cli {
         Cli::parse()
     }
 
+    /// Get the pathspecs from `--path` and after `--`
+    pub fn pathspecs(&self) -> Vec<String> {
+        self.paths.iter().chain(&self.pathspec).cloned().collect()
+    }
+
     /// Determine the operation mode based on arguments
-    pub fn get_operation_mode(&self) -> OperationMode {
+    ///
+    /// Targets are resolved with the following precedence:
+    /// - A single target is passed to `git diff` as-is; `--ref` marks it as a revision so a
+    ///   same-named path cannot make it ambiguous.
+    /// - Two targets are compared as git refs when git can resolve both, and as files or
+    ///   directories otherwise. `--ref` turns an unresolvable target into an error instead.
+    ///
+    /// `default_mode` is only used when there are no targets and no mode flags.
+    pub fn get_operation_mode(&self, default_mode: DefaultMode) -> OperationMode {
         if let Some(command) = &self.command {
             match command {
                 Commands::Diff {
@@ -65,38 +195,81 @@ impl Cli {
                     cached,
                 } => {
                     if *cached {
-                        OperationMode::GitCached
+                        match target2 {
+                            Some(_) => OperationMode::Invalid {
+                                reason: "--cached compares the index with at most one ref"
+                                    .to_string(),
+                            },
+                            None => OperationMode::GitCachedRef {
+                                target: target1.clone(),
+                            },
+                        }
                     } else if let Some(target2) = target2 {
                         // Two targets: could be refs, files, or directories
                         OperationMode::Compare {
                             target1: target1.clone(),
                             target2: target2.clone(),
+                            force_ref: self.force_ref,
                         }
                     } else {
                         // One target: compare with working directory or HEAD
                         OperationMode::GitDiff {
                             target: target1.clone(),
+                            force_ref: self.force_ref,
                         }
                     }
                 }
                 Commands::Status => OperationMode::GitStatus,
+                Commands::Conflicts => OperationMode::GitConflicts,
+                Commands::Themes { preview } => OperationMode::Themes {
+                    preview: preview.clone(),
+                },
                 Commands::Completions { shell } => OperationMode::Completions { shell: *shell },
+                Commands::Clear { all, force } => OperationMode::ClearChecks {
+                    all: *all,
+                    force: *force,
+                },
+            }
+        } else if let Some(path) = &self.patch {
+            OperationMode::PatchFile { path: path.clone() }
+        } else if self.head {
+            OperationMode::GitHead
+        } else if self.upstream {
+            // Three dots: only the commits on this branch, like a pull request
+            OperationMode::GitDiff {
+                target: "@{upstream}...HEAD".to_string(),
+                force_ref: true,
             }
         } else if self.cached {
-            OperationMode::GitCached
+            match self.targets.as_slice() {
+                [] => OperationMode::GitCached,
+                [target] => OperationMode::GitCachedRef {
+                    target: target.clone(),
+                },
+                _ => OperationMode::Invalid {
+                    reason: "--cached compares the index with at most one ref".to_string(),
+                },
+            }
         } else if self.targets.is_empty() {
-            // No arguments: show working directory changes
-            OperationMode::GitWorkingDirectory
+            // No arguments: show working directory changes unless configured otherwise
+            match default_mode {
+                _ if self.worktree => OperationMode::GitWorkingDirectory,
+                DefaultMode::WorkingDirectory => OperationMode::GitWorkingDirectory,
+                DefaultMode::Cached => OperationMode::GitCached,
+                DefaultMode::Status => OperationMode::GitStatus,
+            }
         } else if self.targets.len() == 1 {
             // One target: compare with working directory or HEAD
             OperationMode::GitDiff {
                 target: self.targets[0].clone(),
+                force_ref: self.force_ref,
             }
         } else if self.targets.len() == 2 {
             // Two targets: compare them
             OperationMode::Compare {
                 target1: self.targets[0].clone(),
                 target2: self.targets[1].clone(),
+                force_ref: self.force_ref,
             }
         } else {
             // Too many arguments
@@ -113,44 +286,143 @@ pub enum OperationMode {
     GitWorkingDirectory,
     /// Compare staged changes with HEAD
     GitCached,
+    /// Compare the working directory, including staged changes, with HEAD
+    GitHead,
+    /// Compare staged changes with the given ref
+    GitCachedRef { target: String },
     /// Compare target with working directory or HEAD
-    GitDiff { target: String },
+    ///
+    /// `force_ref` disambiguates the target as a revision when a same-named path exists.
+    GitDiff { target: String, force_ref: bool },
     /// Show git status with diffs
     GitStatus,
+    /// Show the contents of unmerged files during a merge or rebase
+    GitConflicts,
     /// Compare two targets (refs, files, or directories)
-    Compare { target1: String, target2: String },
+    ///
+    /// `force_ref` never falls back to comparing the targets as files or directories.
+    Compare {
+        target1: String,
+        target2: String,
+        force_ref: bool,
+    },
+    /// Show a diff read from a patch file
+    PatchFile { path: String },
+    /// List or preview themes
+    Themes { preview: Option<String> },
     /// Generate shell completions
     Completions { shell: clap_complete::Shell },
+    /// Remove persisted check states, of this repository or of all of them
+    ClearChecks { all: bool, force: bool },
     /// Invalid arguments
     Invalid { reason: String },
 }
 
 impl OperationMode {
+    /// Parse a command typed on the `:` command line into the comparison it switches to
+    ///
+    /// Supports `diff [REF | A..B | A...B | A B]`, `show REV`, `cached [REF]`, `head` and
+    /// `status`, with refs always treated as revisions.
+    pub fn from_command(input: &str) -> Result<Self> {
+        let mut words = input.split_whitespace();
+        let Some(command) = words.next() else {
+            bail!("Empty command");
+        };
+        let args: Vec<&str> = words.collect();
+
+        let mode = match (command, args.as_slice()) {
+            ("diff", []) => OperationMode::GitWorkingDirectory,
+            ("diff", [target]) => OperationMode::GitDiff {
+                target: target.to_string(),
+                force_ref: true,
+            },
+            ("diff", [target1, target2]) => OperationMode::Compare {
+                target1: target1.to_string(),
+                target2: target2.to_string(),
+                force_ref: true,
+            },
+            ("show", [revision]) => OperationMode::GitDiff {
+                target: format!("{revision}^!"),
+                force_ref: true,
+            },
+            ("cached", []) => OperationMode::GitCached,
+            ("cached", [target]) => OperationMode::GitCachedRef {
+                target: target.to_string(),
+            },
+            ("head", []) => OperationMode::GitHead,
+            ("status", []) => OperationMode::GitStatus,
+            ("diff" | "show" | "cached" | "head" | "status", _) => {
+                bail!("Wrong number of arguments for '{command}'")
+            }
+            _ => bail!("Unknown command '{command}' (try diff, show, cached, head or status)"),
+        };
+        Ok(mode)
+    }
+
     /// Check if this mode requires a git repository
     pub fn requires_git_repo(&self) -> bool {
         match self {
             OperationMode::GitWorkingDirectory
             | OperationMode::GitCached
+            | OperationMode::GitHead
+            | OperationMode::GitCachedRef { .. }
             | OperationMode::GitDiff { .. }
-            | OperationMode::GitStatus => true,
+            | OperationMode::GitStatus
+            | OperationMode::GitConflicts => true,
             OperationMode::Compare { .. }
+            | OperationMode::PatchFile { .. }
+            | OperationMode::Themes { .. }
             | OperationMode::Completions { .. }
+            | OperationMode::ClearChecks { .. }
             | OperationMode::Invalid { .. } => false,
         }
     }
 
+    /// Check if this mode runs git, inside a repository or not
+    ///
+    /// Comparing files or directories still needs git for `git diff --no-index`.
+    pub fn requires_git(&self) -> bool {
+        self.requires_git_repo() || matches!(self, OperationMode::Compare { .. })
+    }
+
+    /// Check if this mode diffs the working tree, so its output changes while editing
+    ///
+    /// A git diff target reads it when it is a single revision; ranges ("A..B", "A...B") and
+    /// single commits ("A^!") compare two revisions.
+    pub fn reads_working_tree(&self) -> bool {
+        match self {
+            OperationMode::GitDiff { target, .. } => {
+                !(target.contains("..") || target.ends_with("^!"))
+            }
+            _ => matches!(
+                self,
+                OperationMode::GitWorkingDirectory
+                    | OperationMode::GitHead
+                    | OperationMode::GitStatus
+                    | OperationMode::GitConflicts
+            ),
+        }
+    }
+
     /// Get a description of this operation mode
-    #[allow(dead_code)]
     pub fn description(&self) -> String {
         match self {
             OperationMode::GitWorkingDirectory => "Working directory changes".to_string(),
             OperationMode::GitCached => "Staged changes".to_string(),
-            OperationMode::GitDiff { target } => format!("Changes from {target}"),
+            OperationMode::GitHead => "Staged and unstaged changes".to_string(),
+            OperationMode::GitCachedRef { target } => format!("Staged changes against {target}"),
+            OperationMode::GitDiff { target, .. } => format!("Changes from {target}"),
             OperationMode::GitStatus => "Git status with diffs".to_string(),
-            OperationMode::Compare { target1, target2 } => {
+            OperationMode::GitConflicts => "Files with merge conflicts".to_string(),
+            OperationMode::Compare {
+                target1, target2, ..
+            } => {
                 format!("Comparing {target1} with {target2}")
             }
+            OperationMode::PatchFile { path } => format!("Patch file {path}"),
+            OperationMode::Themes { .. } => "Listing themes".to_string(),
             OperationMode::Completions { .. } => "Generating completions".to_string(),
+            OperationMode::ClearChecks { .. } => "Clearing check states".to_string(),
             OperationMode::Invalid { reason } => format!("Invalid: {reason}"),
         }
     }
@@ -159,6 +431,8 @@ impl OperationMode {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::git::GitExecutor;
+    use crate::git::test_repo::TestRepo;
 
     #[test]
     fn test_no_args_gives_working_directory() {
@@ -167,11 +441,30 @@ mod tests {
             targets: vec![],
             cached: false,
             worktree: false,
+            head: false,
+            upstream: false,
+            force_ref: false,
+            patch: None,
+            paths: vec![],
+            pathspec: vec![],
+            diff_filter: None,
+            context: None,
+            inter_hunk_context: None,
+            untracked: false,
+            max_files: None,
+            instant: false,
+            watch: None,
+            debug_pager: false,
+            no_alt_screen: false,
+            print_diff_command: false,
+            export: None,
+            json: false,
+            exit_code: false,
             config: None,
             verbose: false,
         };
 
-        match cli.get_operation_mode() {
+        match cli.get_operation_mode(DefaultMode::default()) {
             OperationMode::GitWorkingDirectory => (),
             _ => panic!("Expected GitWorkingDirectory mode"),
         }
@@ -184,16 +477,124 @@ mod tests {
             targets: vec![],
             cached: true,
             worktree: false,
+            head: false,
+            upstream: false,
+            force_ref: false,
+            patch: None,
+            paths: vec![],
+            pathspec: vec![],
+            diff_filter: None,
+            context: None,
+            inter_hunk_context: None,
+            untracked: false,
+            max_files: None,
+            instant: false,
+            watch: None,
+            debug_pager: false,
+            no_alt_screen: false,
+            print_diff_command: false,
+            export: None,
+            json: false,
+            exit_code: false,
             config: None,
             verbose: false,
         };
 
-        match cli.get_operation_mode() {
+        match cli.get_operation_mode(DefaultMode::default()) {
             OperationMode::GitCached => (),
             _ => panic!("Expected GitCached mode"),
         }
     }
 
+    #[test]
+    fn test_default_mode_only_without_arguments() {
+        let cli = Cli::parse_from(["ftdv"]);
+        assert!(matches!(
+            cli.get_operation_mode(DefaultMode::Cached),
+            OperationMode::GitCached
+        ));
+        assert!(matches!(
+            cli.get_operation_mode(DefaultMode::Status),
+            OperationMode::GitStatus
+        ));
+
+        // Targets and mode flags win over the configured default
+        let cli = Cli::parse_from(["ftdv", "main"]);
+        assert!(matches!(
+            cli.get_operation_mode(DefaultMode::Cached),
+            OperationMode::GitDiff { .. }
+        ));
+        let cli = Cli::parse_from(["ftdv", "--worktree"]);
+        assert!(matches!(
+            cli.get_operation_mode(DefaultMode::Cached),
+            OperationMode::GitWorkingDirectory
+        ));
+        let cli = Cli::parse_from(["ftdv", "--cached"]);
+        assert!(matches!(
+            cli.get_operation_mode(DefaultMode::Status),
+            OperationMode::GitCached
+        ));
+    }
+
+    #[test]
+    fn test_requires_git() {
+        let mode = |args: &[&str]| Cli::parse_from(args).get_operation_mode(DefaultMode::default());
+        assert!(mode(&["ftdv"]).requires_git());
+        assert!(mode(&["ftdv", "a.txt", "b.txt"]).requires_git());
+        assert!(!mode(&["ftdv", "a.txt", "b.txt"]).requires_git_repo());
+        assert!(!mode(&["ftdv", "--patch", "x.patch"]).requires_git());
+        assert!(!mode(&["ftdv", "themes"]).requires_git());
+    }
+
+    #[test]
+    fn test_head_flag() {
+        let cli = Cli::parse_from(["ftdv", "--head"]);
+        let mode = cli.get_operation_mode(DefaultMode::Cached);
+        assert!(matches!(mode, OperationMode::GitHead));
+        assert!(mode.requires_git_repo());
+        assert!(mode.reads_working_tree());
+
+        // HEAD is the only target
+        assert!(Cli::try_parse_from(["ftdv", "--head", "main"]).is_err());
+        assert!(Cli::try_parse_from(["ftdv", "--head", "--cached"]).is_err());
+    }
+
+    #[test]
+    fn test_upstream_flag() {
+        let cli = Cli::parse_from(["ftdv", "--upstream"]);
+        match cli.get_operation_mode(DefaultMode::Status) {
+            OperationMode::GitDiff { target, force_ref } => {
+                assert_eq!(target, "@{upstream}...HEAD");
+                assert!(force_ref);
+            }
+            mode => panic!("unexpected mode {mode:?}"),
+        }
+
+        assert!(Cli::try_parse_from(["ftdv", "--upstream", "main"]).is_err());
+        assert!(Cli::try_parse_from(["ftdv", "--upstream", "--head"]).is_err());
+    }
+
+    #[test]
+    fn test_cached_with_ref() {
+        let cli = Cli::parse_from(["ftdv", "--cached", "HEAD~2"]);
+        match cli.get_operation_mode(DefaultMode::default()) {
+            OperationMode::GitCachedRef { target } => assert_eq!(target, "HEAD~2"),
+            _ => panic!("Expected GitCachedRef mode"),
+        }
+
+        let cli = Cli::parse_from(["ftdv", "diff", "--cached", "main"]);
+        match cli.get_operation_mode(DefaultMode::default()) {
+            OperationMode::GitCachedRef { target } => assert_eq!(target, "main"),
+            _ => panic!("Expected GitCachedRef mode"),
+        }
+
+        let cli = Cli::parse_from(["ftdv", "--cached", "main", "feature"]);
+        assert!(matches!(
+            cli.get_operation_mode(DefaultMode::default()),
+            OperationMode::Invalid { .. }
+        ));
+    }
+
     #[test]
     fn test_single_target() {
         let cli = Cli {
@@ -201,12 +602,34 @@ mod tests {
             targets: vec!["branch1".to_string()],
             cached: false,
             worktree: false,
+            head: false,
+            upstream: false,
+            force_ref: false,
+            patch: None,
+            paths: vec![],
+            pathspec: vec![],
+            diff_filter: None,
+            context: None,
+            inter_hunk_context: None,
+            untracked: false,
+            max_files: None,
+            instant: false,
+            watch: None,
+            debug_pager: false,
+            no_alt_screen: false,
+            print_diff_command: false,
+            export: None,
+            json: false,
+            exit_code: false,
             config: None,
             verbose: false,
         };
 
-        match cli.get_operation_mode() {
-            OperationMode::GitDiff { target } => assert_eq!(target, "branch1"),
+        match cli.get_operation_mode(DefaultMode::default()) {
+            OperationMode::GitDiff { target, force_ref } => {
+                assert_eq!(target, "branch1");
+                assert!(!force_ref);
+            }
             _ => panic!("Expected GitDiff mode"),
         }
     }
@@ -218,16 +641,193 @@ mod tests {
             targets: vec!["branch1".to_string(), "branch2".to_string()],
             cached: false,
             worktree: false,
+            head: false,
+            upstream: false,
+            force_ref: false,
+            patch: None,
+            paths: vec![],
+            pathspec: vec![],
+            diff_filter: None,
+            context: None,
+            inter_hunk_context: None,
+            untracked: false,
+            max_files: None,
+            instant: false,
+            watch: None,
+            debug_pager: false,
+            no_alt_screen: false,
+            print_diff_command: false,
+            export: None,
+            json: false,
+            exit_code: false,
             config: None,
             verbose: false,
         };
 
-        match cli.get_operation_mode() {
-            OperationMode::Compare { target1, target2 } => {
+        match cli.get_operation_mode(DefaultMode::default()) {
+            OperationMode::Compare {
+                target1, target2, ..
+            } => {
                 assert_eq!(target1, "branch1");
                 assert_eq!(target2, "branch2");
             }
             _ => panic!("Expected Compare mode"),
         }
     }
+
+    #[test]
+    fn test_pathspec() {
+        let cli = Cli::parse_from(["ftdv", "--cached", "main", "--", "services/api/"]);
+        assert_eq!(cli.pathspecs(), ["services/api/"]);
+        match cli.get_operation_mode(DefaultMode::default()) {
+            OperationMode::GitCachedRef { target } => assert_eq!(target, "main"),
+            _ => panic!("Expected GitCachedRef mode"),
+        }
+
+        let cli = Cli::parse_from(["ftdv", "diff", "main", "feature", "--path", "docs"]);
+        assert_eq!(cli.pathspecs(), ["docs"]);
+    }
+
+    #[test]
+    fn test_diff_filter() {
+        assert_eq!(Cli::parse_from(["ftdv"]).diff_filter, None);
+        let cli = Cli::parse_from(["ftdv", "--diff-filter=AM", "main"]);
+        assert_eq!(cli.diff_filter.as_deref(), Some("AM"));
+        let cli = Cli::parse_from(["ftdv", "status", "--diff-filter", "d"]);
+        assert_eq!(cli.diff_filter.as_deref(), Some("d"));
+
+        assert!(Cli::try_parse_from(["ftdv", "--diff-filter=AQ"]).is_err());
+        assert!(Cli::try_parse_from(["ftdv", "--diff-filter="]).is_err());
+    }
+
+    #[test]
+    fn test_watch_interval() {
+        assert_eq!(Cli::parse_from(["ftdv"]).watch, None);
+        assert_eq!(Cli::parse_from(["ftdv", "--watch"]).watch, Some(2));
+        assert_eq!(Cli::parse_from(["ftdv", "--watch=5"]).watch, Some(5));
+
+        // A bare --watch never swallows a target
+        let cli = Cli::parse_from(["ftdv", "--watch", "main"]);
+        assert_eq!(cli.watch, Some(2));
+        assert!(
+            cli.get_operation_mode(DefaultMode::default())
+                .reads_working_tree()
+        );
+
+        // Comparisons between two revisions never change while editing
+        for target in ["main..feature", "main...feature", "HEAD^!"] {
+            let mode = OperationMode::GitDiff {
+                target: target.to_string(),
+                force_ref: true,
+            };
+            assert!(!mode.reads_working_tree(), "{target}");
+        }
+    }
+
+    #[test]
+    fn test_patch_file() {
+        let cli = Cli::parse_from(["ftdv", "--patch", "review.patch"]);
+
+        match cli.get_operation_mode(DefaultMode::default()) {
+            OperationMode::PatchFile { path } => assert_eq!(path, "review.patch"),
+            _ => panic!("Expected PatchFile mode"),
+        }
+        assert!(
+            !cli.get_operation_mode(DefaultMode::default())
+                .requires_git_repo()
+        );
+    }
+
+    #[test]
+    fn test_force_ref_with_ambiguous_target() {
+        // "src" exists as a directory in the crate root, but --ref forces it to be a ref
+        let cli = Cli::parse_from(["ftdv", "--ref", "src", "main"]);
+
+        match cli.get_operation_mode(DefaultMode::default()) {
+            OperationMode::Compare {
+                target1,
+                target2,
+                force_ref,
+            } => {
+                assert_eq!(target1, "src");
+                assert_eq!(target2, "main");
+                assert!(force_ref);
+            }
+            _ => panic!("Expected Compare mode"),
+        }
+
+        let cli = Cli::parse_from(["ftdv", "diff", "src", "--ref"]);
+        match cli.get_operation_mode(DefaultMode::default()) {
+            OperationMode::GitDiff { target, force_ref } => {
+                assert_eq!(target, "src");
+                assert!(force_ref);
+            }
+            _ => panic!("Expected GitDiff mode"),
+        }
+    }
+
+    #[test]
+    fn test_force_ref_resolves_ambiguous_target() {
+        let repo = TestRepo::new();
+        repo.write("notes", "notes\n");
+        repo.commit("initial");
+        repo.git(&["branch", "notes"]);
+        let _entered = repo.enter();
+
+        // A branch and a file share the name; the branch wins, with or without --ref
+        let executor = GitExecutor::new();
+        assert!(executor.is_git_ref("notes", true).unwrap());
+        assert!(executor.is_git_ref("notes", false).unwrap());
+
+        // A path alone is compared as a path, unless --ref insists on a ref
+        let path = repo.path().join("notes");
+        let path = path.to_string_lossy();
+        assert!(!executor.is_git_ref(&path, false).unwrap());
+        let error = executor.is_git_ref(&path, true).unwrap_err().to_string();
+        assert!(error.contains("is not a valid git ref"), "{error}");
+    }
+
+    #[test]
+    fn test_from_command() {
+        assert!(matches!(
+            OperationMode::from_command("diff").unwrap(),
+            OperationMode::GitWorkingDirectory
+        ));
+        match OperationMode::from_command("  diff main..feature ").unwrap() {
+            OperationMode::GitDiff { target, force_ref } => {
+                assert_eq!(target, "main..feature");
+                assert!(force_ref);
+            }
+            _ => panic!("Expected GitDiff mode"),
+        }
+        match OperationMode::from_command("diff main feature").unwrap() {
+            OperationMode::Compare {
+                target1,
+                target2,
+                force_ref,
+            } => {
+                assert_eq!((target1.as_str(), target2.as_str()), ("main", "feature"));
+                assert!(force_ref);
+            }
+            _ => panic!("Expected Compare mode"),
+        }
+        match OperationMode::from_command("show HEAD~3").unwrap() {
+            OperationMode::GitDiff { target, .. } => assert_eq!(target, "HEAD~3^!"),
+            _ => panic!("Expected GitDiff mode"),
+        }
+        match OperationMode::from_command("cached main").unwrap() {
+            OperationMode::GitCachedRef { target } => assert_eq!(target, "main"),
+            _ => panic!("Expected GitCachedRef mode"),
+        }
+
+        let error = OperationMode::from_command("show").unwrap_err();
+        assert_eq!(error.to_string(), "Wrong number of arguments for 'show'");
+        let error = OperationMode::from_command("frobnicate HEAD").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("Unknown command 'frobnicate'")
+        );
+        assert!(OperationMode::from_command("   ").is_err());
+    }
 }
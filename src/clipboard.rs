@@ -0,0 +1,95 @@
+use anyhow::Result;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Clipboard commands tried in order, as (program, args)
+const CLIPBOARD_COMMANDS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+/// Copy text to the system clipboard
+///
+/// Uses the first available platform clipboard command, and falls back to the OSC 52
+/// escape sequence so copying also works over SSH in terminals that support it.
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    for (program, args) in CLIPBOARD_COMMANDS {
+        if copy_with_command(program, args, text).is_ok() {
+            return Ok(());
+        }
+    }
+
+    copy_with_osc52(text)
+}
+
+fn copy_with_command(program: &str, args: &[&str], text: &str) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("{program} exited with {status}"))
+    }
+}
+
+fn copy_with_osc52(text: &str) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64_encode(text.as_bytes()))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn base64_encode(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+
+        output.push(ALPHABET[(n >> 18) as usize & 63] as char);
+        output.push(ALPHABET[(n >> 12) as usize & 63] as char);
+        output.push(if chunk.len() > 1 {
+            ALPHABET[(n >> 6) as usize & 63] as char
+        } else {
+            '='
+        });
+        output.push(if chunk.len() > 2 {
+            ALPHABET[n as usize & 63] as char
+        } else {
+            '='
+        });
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+    }
+}
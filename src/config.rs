@@ -1,8 +1,9 @@
 use crate::theme::Theme;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GitPagingConfig {
@@ -15,18 +16,37 @@ pub struct GitPagingConfig {
     pub external_diff_command: String,
 
     /// Color argument passed to git diff (always/never/auto)
-    #[serde(default = "default_color_arg")]
+    #[serde(
+        default = "default_color_arg",
+        rename = "colorArg",
+        alias = "color_arg"
+    )]
     pub color_arg: String,
 
     /// Use system-configured pager from git config
     #[serde(default)]
     pub use_config: bool,
+
+    /// Set TERM, COLUMNS, and LINES for diff tools instead of inheriting them
+    #[serde(default, rename = "overrideTerminalEnv")]
+    pub override_terminal_env: bool,
+
+    /// Environment variables passed to diff tools (empty inherits the whole environment)
+    #[serde(default, rename = "envPassthrough")]
+    pub env_passthrough: Vec<String>,
+
+    /// Show piped input that already has ANSI colors as-is instead of running the diff tool
+    #[serde(default, rename = "assumeColored")]
+    pub assume_colored: bool,
 }
 
 fn default_color_arg() -> String {
     "always".to_string()
 }
 
+/// Accepted values for `colorArg`
+pub const COLOR_ARGS: [&str; 3] = ["always", "never", "auto"];
+
 impl Default for GitPagingConfig {
     fn default() -> Self {
         Self {
@@ -34,11 +54,23 @@ impl Default for GitPagingConfig {
             external_diff_command: String::new(),
             color_arg: default_color_arg(),
             use_config: false,
+            override_terminal_env: false,
+            env_passthrough: Vec::new(),
+            assume_colored: false,
         }
     }
 }
 
 impl GitPagingConfig {
+    /// Resolve `colorArg` to the value passed to git, deciding `auto` from whether stdout is a tty
+    pub fn resolved_color_arg(&self) -> &str {
+        match self.color_arg.as_str() {
+            "auto" if std::io::IsTerminal::is_terminal(&std::io::stdout()) => "always",
+            "auto" => "never",
+            color_arg => color_arg,
+        }
+    }
+
     /// Check if external diff command is configured
     pub fn has_external_diff_command(&self) -> bool {
         !self.external_diff_command.trim().is_empty()
@@ -94,10 +126,280 @@ impl Default for DiffCommand {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+/// Operation mode used when ftdv is run without targets or mode flags
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DefaultMode {
+    #[default]
+    WorkingDirectory,
+    Cached,
+    Status,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GitConfig {
     #[serde(default)]
     pub paging: GitPagingConfig,
+
+    /// Mode for a bare `ftdv`: workingDirectory, cached, or status
+    #[serde(default, rename = "defaultMode")]
+    pub default_mode: DefaultMode,
+
+    /// Number of context lines passed to git diff as `-U<n>`
+    #[serde(
+        default,
+        rename = "contextLines",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub context_lines: Option<u32>,
+
+    /// Merge hunks separated by at most this many lines, as `--inter-hunk-context=<n>`
+    #[serde(
+        default,
+        rename = "interHunkContext",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub inter_hunk_context: Option<u32>,
+
+    /// Include untracked files in status mode
+    #[serde(default, rename = "showUntracked")]
+    pub show_untracked: bool,
+
+    /// Tool for `git difftool` (defaults to git's diff.tool setting)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difftool: Option<String>,
+
+    /// Show the diffs parsed at startup instead of refetching each file from git
+    #[serde(default)]
+    pub instant: bool,
+
+    /// Largest whole-tree diff read into memory, 0 for no limit
+    ///
+    /// Larger diffs only list files with their line counts, and each file is fetched when shown.
+    #[serde(default = "default_max_diff_bytes", rename = "maxDiffBytes")]
+    pub max_diff_bytes: u64,
+
+    /// Pathspecs restricting the diff, only set from the command line
+    #[serde(skip)]
+    pub pathspec: Vec<String>,
+
+    /// Swap the sides of a ref diff (git diff -R), only toggled at runtime
+    #[serde(skip)]
+    pub reverse: bool,
+
+    /// Change kinds to show (git diff --diff-filter), set from the command line or cycled at
+    /// runtime
+    #[serde(skip)]
+    pub diff_filter: Option<String>,
+}
+
+fn default_max_diff_bytes() -> u64 {
+    256 * 1024 * 1024
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            paging: GitPagingConfig::default(),
+            default_mode: DefaultMode::default(),
+            context_lines: None,
+            inter_hunk_context: None,
+            show_untracked: false,
+            difftool: None,
+            instant: false,
+            max_diff_bytes: default_max_diff_bytes(),
+            pathspec: Vec::new(),
+            reverse: false,
+            diff_filter: None,
+        }
+    }
+}
+
+/// Minimum and maximum sidebar width as a percentage of the terminal width
+pub const MIN_SIDEBAR_WIDTH: u16 = 10;
+pub const MAX_SIDEBAR_WIDTH: u16 = 60;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UiConfig {
+    /// Width of the file list pane as a percentage of the terminal width
+    #[serde(default = "default_sidebar_width", rename = "sidebarWidth")]
+    pub sidebar_width: u16,
+
+    /// How long the diff pane width must be stable before re-running the diff tool
+    #[serde(default = "default_resize_debounce_ms", rename = "resizeDebounceMs")]
+    pub resize_debounce_ms: u64,
+
+    /// How long a row flashes after its check mark is toggled, 0 to disable
+    #[serde(default = "default_check_flash_ms", rename = "checkFlashMs")]
+    pub check_flash_ms: u64,
+
+    /// Truncate the diff pane after this many lines until the full diff is requested
+    #[serde(
+        default,
+        rename = "maxDiffLines",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_diff_lines: Option<usize>,
+
+    /// Ask before opening diffs with more files than this, offering to show only the first ones
+    ///
+    /// The diff has been read by the time this is checked, so only building the tree is skipped.
+    #[serde(default, rename = "maxFiles", skip_serializing_if = "Option::is_none")]
+    pub max_files: Option<usize>,
+
+    /// Show a one-line bar with the most important keys at the bottom
+    #[serde(default = "default_footer")]
+    pub footer: bool,
+
+    /// Label files with their full path instead of their basename
+    ///
+    /// When unset, the tree shows basenames and the files-only list shows full paths.
+    #[serde(default, rename = "fullPaths", skip_serializing_if = "Option::is_none")]
+    pub full_paths: Option<bool>,
+
+    /// Show scrollbars on the diff pane when its content overflows
+    #[serde(default = "default_scrollbars")]
+    pub scrollbars: bool,
+
+    /// Keep the vertical scroll offset when switching files instead of returning to the top
+    #[serde(default, rename = "stickyScroll")]
+    pub sticky_scroll: bool,
+
+    /// Highlight trailing whitespace and mixed indentation on added lines (git's own diff only)
+    #[serde(default, rename = "highlightWhitespace")]
+    pub highlight_whitespace: bool,
+
+    /// Glob patterns of files to hide from the tree, e.g. "Cargo.lock" or "*.min.js"
+    ///
+    /// Patterns without a slash also match the file name in any directory.
+    #[serde(default, rename = "hidePatterns")]
+    pub hide_patterns: Vec<String>,
+
+    /// Show each file's number of hunks in the tree stats and the status line
+    #[serde(default, rename = "hunkCounts")]
+    pub hunk_counts: bool,
+
+    /// Show a dimmed file count next to expanded directories, like collapsed ones show stats
+    #[serde(default, rename = "expandedDirCounts")]
+    pub expanded_dir_counts: bool,
+
+    /// Start directories nested deeper than this many levels collapsed
+    #[serde(
+        default,
+        rename = "autoCollapseDepth",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub auto_collapse_depth: Option<usize>,
+
+    /// Template for the status line, e.g. "{path} | {stats} | {index} | {percent}"
+    #[serde(
+        default,
+        rename = "statusFormat",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub status_format: Option<String>,
+
+    /// Where directories go among their siblings in the file tree
+    #[serde(default, rename = "treeSort")]
+    pub tree_sort: TreeSort,
+}
+
+/// Order of files and directories within each directory of the file tree
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TreeSort {
+    #[default]
+    DirsFirst,
+    FilesFirst,
+    Interleaved,
+}
+
+fn default_sidebar_width() -> u16 {
+    20
+}
+
+fn default_resize_debounce_ms() -> u64 {
+    150
+}
+
+fn default_check_flash_ms() -> u64 {
+    150
+}
+
+fn default_footer() -> bool {
+    true
+}
+
+fn default_scrollbars() -> bool {
+    true
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            sidebar_width: default_sidebar_width(),
+            resize_debounce_ms: default_resize_debounce_ms(),
+            check_flash_ms: default_check_flash_ms(),
+            max_diff_lines: None,
+            max_files: None,
+            footer: default_footer(),
+            full_paths: None,
+            scrollbars: default_scrollbars(),
+            sticky_scroll: false,
+            hide_patterns: Vec::new(),
+            hunk_counts: false,
+            expanded_dir_counts: false,
+            highlight_whitespace: false,
+            auto_collapse_depth: None,
+            status_format: None,
+            tree_sort: TreeSort::default(),
+        }
+    }
+}
+
+impl UiConfig {
+    /// Get the sidebar width clamped so the diff pane never disappears
+    pub fn clamped_sidebar_width(&self) -> u16 {
+        self.sidebar_width
+            .clamp(MIN_SIDEBAR_WIDTH, MAX_SIDEBAR_WIDTH)
+    }
+}
+
+/// Icon overrides by file extension and by exact file name
+///
+/// Values are either the glyph itself or its codepoint, e.g. `U+E7A8`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct IconConfig {
+    #[serde(default)]
+    pub extensions: HashMap<String, String>,
+
+    #[serde(default)]
+    pub filenames: HashMap<String, String>,
+
+    /// Language of files whose name or extension is misleading, by file name or repository
+    /// path, e.g. `settings.txt: toml`
+    #[serde(default)]
+    pub languages: HashMap<String, String>,
+}
+
+/// How checked files are remembered between sessions
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckScope {
+    /// Keyed by the file's blob hashes, so a check is dropped when the file changes
+    #[default]
+    PerBlob,
+    /// Keyed by repository and path, so a check survives content changes
+    PerPath,
+    /// Check marks only last for the session
+    Off,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PersistenceConfig {
+    /// Scope of persisted check marks: perBlob, perPath, or off
+    #[serde(default, rename = "checkScope")]
+    pub check_scope: CheckScope,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -105,6 +407,15 @@ pub struct Config {
     #[serde(default)]
     pub git: GitConfig,
 
+    #[serde(default)]
+    pub ui: UiConfig,
+
+    #[serde(default)]
+    pub icons: IconConfig,
+
+    #[serde(default)]
+    pub persistence: PersistenceConfig,
+
     /// Legacy diff_command field for backward compatibility
     #[serde(skip_serializing_if = "Option::is_none")]
     pub diff_command: Option<DiffCommand>,
@@ -144,13 +455,20 @@ impl Config {
 
     /// Get display name for the current diff configuration
     pub fn get_diff_display_name(&self) -> String {
-        match self.get_diff_command_type() {
+        self.get_diff_command_type().display_name()
+    }
+}
+
+impl DiffCommandType {
+    /// Get a short name for the tool, e.g. "delta (pager)"
+    pub fn display_name(&self) -> String {
+        match self {
             DiffCommandType::GitDefault => "git diff".to_string(),
-            DiffCommandType::Pager(ref cmd) => {
+            DiffCommandType::Pager(cmd) => {
                 let tool_name = cmd.split_whitespace().next().unwrap_or("pager");
                 format!("{tool_name} (pager)")
             }
-            DiffCommandType::External(ref cmd) => {
+            DiffCommandType::External(cmd) => {
                 let tool_name = cmd.split_whitespace().next().unwrap_or("external");
                 format!("{tool_name} (external)")
             }
@@ -184,13 +502,29 @@ impl Config {
 
         let config: Config =
             serde_yaml::from_str(&contents).with_context(|| "Failed to parse config file")?;
+        config.validate()?;
 
         Ok(config)
     }
 
+    /// Check values that deserialize fine but are not meaningful
+    fn validate(&self) -> Result<()> {
+        let color_arg = &self.git.paging.color_arg;
+        if !COLOR_ARGS.contains(&color_arg.as_str()) {
+            return Err(anyhow::anyhow!(
+                "Invalid colorArg '{}': expected one of {}",
+                color_arg,
+                COLOR_ARGS.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
     pub fn save(&self) -> Result<()> {
-        let config_path = Self::config_path()?;
+        self.save_to(&Self::config_path()?)
+    }
 
+    fn save_to(&self, config_path: &Path) -> Result<()> {
         if let Some(parent) = config_path.parent() {
             fs::create_dir_all(parent)
                 .with_context(|| format!("Failed to create config directory: {parent:?}"))?;
@@ -198,23 +532,45 @@ impl Config {
 
         let yaml = serde_yaml::to_string(self).with_context(|| "Failed to serialize config")?;
 
-        fs::write(&config_path, yaml)
+        fs::write(config_path, yaml)
             .with_context(|| format!("Failed to write config file: {config_path:?}"))?;
 
         Ok(())
     }
 
     fn config_path() -> Result<PathBuf> {
-        let home_dir = dirs::home_dir().context("Failed to get home directory")?;
+        Ok(xdg_base_dir("XDG_CONFIG_HOME", ".config")?
+            .join("ftdv")
+            .join("config.yaml"))
+    }
+}
+
+/// Resolve an XDG base directory, falling back to the given directory under the home directory
+///
+/// The variables are honored on every platform rather than using `dirs::config_dir()`, which
+/// would move the files to `~/Library/Application Support` on macOS.
+pub fn xdg_base_dir(var: &str, home_fallback: &str) -> Result<PathBuf> {
+    resolve_xdg_base_dir(std::env::var_os(var), dirs::home_dir(), home_fallback)
+}
 
-        Ok(home_dir.join(".config").join("ftdv").join("config.yaml"))
+/// Resolve an XDG base directory from the variable's value and the home directory
+fn resolve_xdg_base_dir(
+    value: Option<std::ffi::OsString>,
+    home_dir: Option<PathBuf>,
+    home_fallback: &str,
+) -> Result<PathBuf> {
+    match value {
+        // The XDG spec says relative paths are invalid and should be ignored
+        Some(dir) if Path::new(&dir).is_absolute() => Ok(PathBuf::from(dir)),
+        _ => Ok(home_dir
+            .context("Failed to get home directory")?
+            .join(home_fallback)),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::env;
     use tempfile::TempDir;
 
     #[test]
@@ -242,6 +598,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_color_arg_validation() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+
+        fs::write(&config_path, "git:\n  paging:\n    colorArg: never\n").unwrap();
+        let config = Config::load_from_path(config_path.to_str().unwrap()).unwrap();
+        assert_eq!(config.git.paging.resolved_color_arg(), "never");
+
+        // The old snake_case key is still accepted
+        fs::write(&config_path, "git:\n  paging:\n    color_arg: auto\n").unwrap();
+        let config = Config::load_from_path(config_path.to_str().unwrap()).unwrap();
+        assert_eq!(config.git.paging.color_arg, "auto");
+
+        fs::write(&config_path, "git:\n  paging:\n    colorArg: sometimes\n").unwrap();
+        let err = Config::load_from_path(config_path.to_str().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("Invalid colorArg 'sometimes'"));
+    }
+
     #[test]
     fn test_pager_configuration() {
         let mut config = Config::default();
@@ -290,21 +665,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sidebar_width_clamped() {
+        let mut config = Config::default();
+        assert_eq!(config.ui.clamped_sidebar_width(), 20);
+
+        config.ui.sidebar_width = 0;
+        assert_eq!(config.ui.clamped_sidebar_width(), MIN_SIDEBAR_WIDTH);
+
+        config.ui.sidebar_width = 95;
+        assert_eq!(config.ui.clamped_sidebar_width(), MAX_SIDEBAR_WIDTH);
+
+        let deserialized: Config = serde_yaml::from_str("ui:\n  sidebarWidth: 35\n").unwrap();
+        assert_eq!(deserialized.ui.sidebar_width, 35);
+    }
+
     #[test]
     fn test_config_save_load() -> Result<()> {
         let temp_dir = TempDir::new()?;
-        let temp_home = temp_dir.path().to_path_buf();
-
-        // Override home directory for testing
-        unsafe {
-            env::set_var("HOME", &temp_home);
-        }
+        let config_path = temp_dir.path().join("ftdv").join("config.yaml");
 
         let mut config = Config::default();
         config.git.paging.pager = "delta --dark".to_string();
-        config.save()?;
+        config.save_to(&config_path)?;
+        assert!(config_path.exists());
 
-        let loaded_config = Config::load()?;
+        let loaded_config = Config::load_from_path_buf(&config_path)?;
 
         assert_eq!(config.git.paging.pager, loaded_config.git.paging.pager);
         assert_eq!(
@@ -314,4 +700,34 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_xdg_base_dir() -> Result<()> {
+        let home = PathBuf::from("/home/user");
+
+        // Without the variable, the directory is under the home directory
+        assert_eq!(
+            resolve_xdg_base_dir(None, Some(home.clone()), ".config")?,
+            home.join(".config")
+        );
+
+        // An absolute path redirects it
+        assert_eq!(
+            resolve_xdg_base_dir(Some("/xdg/config".into()), Some(home.clone()), ".config")?,
+            PathBuf::from("/xdg/config")
+        );
+
+        // Relative paths are ignored, as the XDG spec requires
+        assert_eq!(
+            resolve_xdg_base_dir(
+                Some("relative/config".into()),
+                Some(home.clone()),
+                ".config"
+            )?,
+            home.join(".config")
+        );
+
+        assert!(resolve_xdg_base_dir(None, None, ".config").is_err());
+        Ok(())
+    }
 }
@@ -1,51 +1,420 @@
 use crate::cli::OperationMode;
+use crate::parser::{ChangeKind, DiffParser, FileDiff, StatusGroup};
 use anyhow::{Context, Result, anyhow};
-use std::path::Path;
-use std::process::Command;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+#[cfg(test)]
+thread_local! {
+    /// Repository git runs in for the current test thread, see `test_repo::TestRepo::enter`
+    static TEST_REPO_DIR: std::cell::RefCell<Option<PathBuf>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Start a git command in the current directory
+///
+/// Tests run it in a fixture repository instead, since changing the process's working
+/// directory would affect every test running in parallel.
+pub fn git_command() -> Command {
+    #[allow(unused_mut)]
+    let mut command = Command::new("git");
+    #[cfg(test)]
+    TEST_REPO_DIR.with(|dir| {
+        if let Some(dir) = dir.borrow().as_ref() {
+            command.current_dir(dir);
+        }
+    });
+    command
+}
+
+/// Options that shape every diff invocation
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    /// Number of context lines around each change (git diff -U)
+    pub context_lines: Option<u32>,
+    /// Merge hunks separated by up to this many lines (git diff --inter-hunk-context)
+    pub inter_hunk_context: Option<u32>,
+    /// Value for git diff --color, or None to leave git's default (no color when piped)
+    pub color: Option<String>,
+    /// Pathspecs that restrict whole-tree diffs, e.g. `services/api/`
+    pub pathspec: Vec<String>,
+    /// Swap the two sides of the diff (git diff -R)
+    pub reverse: bool,
+    /// Give up on whole-tree diffs larger than this many bytes with `DiffTooLarge`
+    pub max_output_bytes: Option<usize>,
+    /// Change kinds whole-tree diffs are restricted to (git diff --diff-filter)
+    ///
+    /// Per-file diffs ignore it, so a file that is listed always shows its diff. Comparisons
+    /// of two directories outside git count as whole-tree diffs.
+    pub diff_filter: Option<String>,
+}
+
+/// Check if a `--diff-filter` value lets added files through
+///
+/// Uppercase letters select change kinds and lowercase ones exclude them, so a filter of only
+/// lowercase letters keeps everything it does not name.
+pub fn diff_filter_includes_added(filter: &str) -> bool {
+    if filter.chars().any(|c| c.is_ascii_uppercase()) {
+        filter.contains('A')
+    } else {
+        !filter.contains('a')
+    }
+}
+
+/// Describe a `--diff-filter` value for the UI, e.g. "added" for A
+pub fn describe_diff_filter(filter: &str) -> String {
+    match filter {
+        "A" => "added".to_string(),
+        "M" => "modified".to_string(),
+        "D" => "deleted".to_string(),
+        "R" => "renamed".to_string(),
+        "C" => "copied".to_string(),
+        filter => format!("--diff-filter={filter}"),
+    }
+}
+
+/// Error for a whole-tree diff exceeding `DiffOptions::max_output_bytes`
+///
+/// Callers fall back to `GitExecutor::get_summary_diffs` and fetch each file on demand.
+#[derive(Debug)]
+pub struct DiffTooLarge {
+    pub limit: usize,
+}
+
+impl fmt::Display for DiffTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Diff output exceeds {} bytes", self.limit)
+    }
+}
+
+impl std::error::Error for DiffTooLarge {}
+
+impl DiffOptions {
+    /// Get the extra arguments to pass to git diff
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(n) = self.context_lines {
+            args.push(format!("-U{n}"));
+        }
+        if let Some(n) = self.inter_hunk_context {
+            args.push(format!("--inter-hunk-context={n}"));
+        }
+        if let Some(color) = &self.color {
+            args.push(format!("--color={color}"));
+        }
+        if self.reverse {
+            args.push("-R".to_string());
+        }
+        args
+    }
+}
+
+/// Get the pair of paths to diff with `git diff --no-index` for one file of a path comparison
+///
+/// When the targets are directories, `filename` is the path from the diff header (relative to
+/// either target, without the leading `/` of absolute paths), and a file missing on one side
+/// is diffed against `/dev/null`.
+pub fn no_index_paths(target1: &str, target2: &str, filename: &str) -> (String, String) {
+    if !Path::new(target1).is_dir() {
+        return (target1.to_string(), target2.to_string());
+    }
+
+    let header_prefix = |target: &str| {
+        let target = target.trim_start_matches("./").trim_start_matches('/');
+        format!("{}/", target.trim_end_matches('/'))
+    };
+    let relative = [target1, target2]
+        .iter()
+        .find_map(|target| filename.strip_prefix(&header_prefix(target)))
+        .unwrap_or(filename);
+
+    let side = |target: &str| {
+        let path = Path::new(target).join(relative);
+        if path.exists() {
+            path.to_string_lossy().into_owned()
+        } else {
+            "/dev/null".to_string()
+        }
+    };
+    (side(target1), side(target2))
+}
 
 /// Git command executor for getting diff data
-pub struct GitExecutor;
+pub struct GitExecutor {
+    options: DiffOptions,
+}
 
 impl GitExecutor {
     pub fn new() -> Self {
-        Self
+        Self::with_options(DiffOptions::default())
+    }
+
+    pub fn with_options(options: DiffOptions) -> Self {
+        Self { options }
+    }
+
+    /// Get the top-level directory of the current repository
+    pub fn repo_root() -> Result<PathBuf> {
+        let output = git_command()
+            .args(["rev-parse", "--show-toplevel"])
+            .output()
+            .context("Failed to find repository root")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("Failed to find repository root: {}", stderr.trim()));
+        }
+
+        Ok(PathBuf::from(
+            String::from_utf8_lossy(&output.stdout).trim_end(),
+        ))
+    }
+
+    /// Get the upstream branch of the current branch, e.g. "origin/main"
+    pub fn upstream_branch() -> Result<String> {
+        Self::upstream_branch_in(Path::new("."))
+    }
+
+    fn upstream_branch_in(dir: &Path) -> Result<String> {
+        let rev_parse = |args: &[&str]| -> Result<Option<String>> {
+            let output = git_command()
+                .current_dir(dir)
+                .arg("rev-parse")
+                .args(args)
+                .output()
+                .context("Failed to execute git rev-parse")?;
+            Ok(output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string()))
+        };
+
+        if let Some(upstream) = rev_parse(&["--abbrev-ref", "--symbolic-full-name", "@{u}"])? {
+            return Ok(upstream);
+        }
+        match rev_parse(&["--abbrev-ref", "HEAD"])?.as_deref() {
+            Some("HEAD") | None => Err(anyhow!(
+                "HEAD is detached, so there is no upstream branch to compare with"
+            )),
+            Some(branch) => Err(anyhow!(
+                "Branch '{branch}' has no upstream branch; set one with \
+                 `git branch --set-upstream-to=<remote>/<branch>`"
+            )),
+        }
+    }
+
+    /// Get the (possibly abbreviated) object hashes that exist in the repository
+    pub fn known_objects(hashes: &[String]) -> Result<HashSet<String>> {
+        if hashes.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        let mut child = git_command()
+            .args(["cat-file", "--batch-check"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to run git cat-file")?;
+        // Write from another thread, since git stops reading once its output fills the pipe
+        let input = format!("{}\n", hashes.join("\n"));
+        let writer = child
+            .stdin
+            .take()
+            .map(|mut stdin| std::thread::spawn(move || stdin.write_all(input.as_bytes())));
+        let output = child.wait_with_output()?;
+        if let Some(writer) = writer {
+            writer
+                .join()
+                .map_err(|_| anyhow!("Failed to write to git cat-file"))??;
+        }
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("Failed to look up objects: {}", stderr.trim()));
+        }
+
+        // One line per requested hash, in order: "<sha> <type> <size>", or "<hash> missing"
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(hashes
+            .iter()
+            .zip(stdout.lines())
+            .filter(|(_, line)| !line.ends_with(" missing") && !line.ends_with(" ambiguous"))
+            .map(|(hash, _)| hash.clone())
+            .collect())
+    }
+
+    /// Check that git can be run, with a clear error when it is not installed
+    pub fn ensure_available() -> Result<()> {
+        Self::ensure_executable("git")
+    }
+
+    fn ensure_executable(program: &str) -> Result<()> {
+        match Command::new(program).arg("--version").output() {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(anyhow!(
+                "{program} executable not found on PATH; install {program} or add it to PATH"
+            )),
+            Err(e) => Err(anyhow!("Failed to run {program}: {e}")),
+        }
     }
 
     /// Check if we're in a git repository
     pub fn is_git_repo() -> bool {
-        Command::new("git")
+        git_command()
             .args(["rev-parse", "--git-dir"])
             .output()
             .map(|output| output.status.success())
             .unwrap_or(false)
     }
 
+    /// Check if a merge, rebase, cherry-pick or revert is stopped, possibly on conflicts
+    pub fn operation_in_progress() -> bool {
+        [
+            "MERGE_HEAD",
+            "REBASE_HEAD",
+            "CHERRY_PICK_HEAD",
+            "REVERT_HEAD",
+        ]
+        .iter()
+        .any(|name| {
+            // --git-path resolves the file inside the right git dir, also for worktrees
+            git_command()
+                .args(["rev-parse", "--git-path", name])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .is_some_and(|output| {
+                    Path::new(String::from_utf8_lossy(&output.stdout).trim_end()).exists()
+                })
+        })
+    }
+
+    /// List files with unresolved merge conflicts
+    pub fn get_conflicted_files(&self) -> Result<Vec<String>> {
+        let mut files = self.execute_git_name_only(&["diff", "--name-only", "--diff-filter=U"])?;
+        // Unmerged paths can be listed once per stage
+        files.dedup();
+        Ok(files)
+    }
+
+    /// Get the conflicted files with their working tree content, conflict markers included
+    pub fn get_conflict_diffs(&self) -> Result<Vec<FileDiff>> {
+        self.get_conflicted_files()?
+            .into_iter()
+            .map(|path| {
+                let content = self.read_conflicted_file(&path)?;
+                Ok(FileDiff {
+                    old_path: Some(format!("a/{path}")),
+                    new_path: Some(format!("b/{path}")),
+                    filename: path,
+                    content,
+                    added_lines: 0,
+                    removed_lines: 0,
+                    hunk_count: 0,
+                    diff_key: None,
+                    change_kind: ChangeKind::Modified,
+                    is_untracked: false,
+                    old_mode: None,
+                    new_mode: None,
+                    rename_from: None,
+                    similarity: None,
+                    status_group: None,
+                    submodule: None,
+                    load_error: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Read a conflicted file from the working tree; git lists it relative to the repository
+    fn read_conflicted_file(&self, path: &str) -> Result<String> {
+        let bytes = fs::read(Self::repo_root()?.join(path))
+            .with_context(|| format!("Failed to read {path}"))?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
     /// Get diff output based on operation mode
     pub fn get_diff(&self, mode: &OperationMode) -> Result<String> {
         match mode {
-            OperationMode::GitWorkingDirectory => self.execute_git_diff(&["diff"]),
-            OperationMode::GitCached => self.execute_git_diff(&["diff", "--cached"]),
-            OperationMode::GitDiff { target } => self.execute_git_diff(&["diff", target]),
+            OperationMode::GitWorkingDirectory => self.execute_git_diff_all(&["diff"]),
+            OperationMode::GitCached => self.execute_git_diff_all(&["diff", "--cached"]),
+            OperationMode::GitHead => self.execute_git_diff_all(&["diff", "HEAD", "--"]),
+            OperationMode::GitCachedRef { target } => {
+                self.execute_git_diff_all(&["diff", "--cached", target, "--"])
+            }
+            OperationMode::GitDiff { target, force_ref } => {
+                if *force_ref {
+                    // Trailing "--" tells git the target is a revision, not a path
+                    self.execute_git_diff_all(&["diff", target, "--"])
+                } else {
+                    self.execute_git_diff_all(&["diff", target])
+                }
+            }
             OperationMode::GitStatus => {
                 // For status, we might want to show multiple diffs
-                self.execute_git_diff(&["diff"])
+                self.execute_git_diff_all(&["diff"])
             }
-            OperationMode::Compare { target1, target2 } => {
+            OperationMode::Compare {
+                target1,
+                target2,
+                force_ref,
+            } => {
                 // Check if both targets are git refs
-                if self.is_git_ref(target1)? && self.is_git_ref(target2)? {
-                    self.execute_git_diff(&["diff", &format!("{target1}..{target2}")])
+                if self.is_git_ref(target1, *force_ref)? && self.is_git_ref(target2, *force_ref)? {
+                    self.execute_git_diff_all(&["diff", &format!("{target1}..{target2}")])
                 } else {
                     // Fall back to regular diff for files/directories
-                    self.execute_regular_diff(target1, target2)
+                    self.execute_regular_diff(target1, target2, true)
                 }
             }
-            OperationMode::Completions { .. } => {
-                Err(anyhow!("Completions mode should not call get_diff"))
-            }
+            OperationMode::GitConflicts
+            | OperationMode::PatchFile { .. }
+            | OperationMode::Themes { .. }
+            | OperationMode::Completions { .. }
+            | OperationMode::ClearChecks { .. } => Err(anyhow!(
+                "{} mode should not call get_diff",
+                mode.description()
+            )),
             OperationMode::Invalid { reason } => Err(anyhow!("Invalid operation mode: {}", reason)),
         }
     }
 
+    /// Get the changed files with their line counts but without content
+    ///
+    /// This is the fallback for diffs too large to read whole (see `DiffTooLarge`).
+    pub fn get_summary_diffs(&self, mode: &OperationMode) -> Result<Vec<FileDiff>> {
+        match mode {
+            OperationMode::GitWorkingDirectory => self.execute_git_summary(&["diff"]),
+            OperationMode::GitCached => self.execute_git_summary(&["diff", "--cached"]),
+            OperationMode::GitHead => self.execute_git_summary(&["diff", "HEAD", "--"]),
+            OperationMode::GitCachedRef { target } => {
+                self.execute_git_summary(&["diff", "--cached", target, "--"])
+            }
+            OperationMode::GitDiff { target, force_ref } => {
+                if *force_ref {
+                    self.execute_git_summary(&["diff", target, "--"])
+                } else {
+                    self.execute_git_summary(&["diff", target])
+                }
+            }
+            OperationMode::Compare {
+                target1,
+                target2,
+                force_ref,
+            } if self.is_git_ref(target1, *force_ref)?
+                && self.is_git_ref(target2, *force_ref)? =>
+            {
+                self.execute_git_summary(&["diff", &format!("{target1}..{target2}")])
+            }
+            _ => Err(anyhow!("{} mode cannot be summarized", mode.description())),
+        }
+    }
+
     /// Get list of files that have changes
     #[allow(dead_code)]
     pub fn get_changed_files(&self, mode: &OperationMode) -> Result<Vec<String>> {
@@ -56,12 +425,25 @@ impl GitExecutor {
             OperationMode::GitCached => {
                 self.execute_git_name_only(&["diff", "--cached", "--name-only"])
             }
-            OperationMode::GitDiff { target } => {
-                self.execute_git_name_only(&["diff", "--name-only", target])
+            OperationMode::GitHead => self.execute_git_name_only(&["diff", "--name-only", "HEAD"]),
+            OperationMode::GitCachedRef { target } => {
+                self.execute_git_name_only(&["diff", "--cached", "--name-only", target, "--"])
+            }
+            OperationMode::GitDiff { target, force_ref } => {
+                if *force_ref {
+                    self.execute_git_name_only(&["diff", "--name-only", target, "--"])
+                } else {
+                    self.execute_git_name_only(&["diff", "--name-only", target])
+                }
             }
             OperationMode::GitStatus => self.execute_git_name_only(&["diff", "--name-only"]),
-            OperationMode::Compare { target1, target2 } => {
-                if self.is_git_ref(target1)? && self.is_git_ref(target2)? {
+            OperationMode::GitConflicts => self.get_conflicted_files(),
+            OperationMode::Compare {
+                target1,
+                target2,
+                force_ref,
+            } => {
+                if self.is_git_ref(target1, *force_ref)? && self.is_git_ref(target2, *force_ref)? {
                     self.execute_git_name_only(&[
                         "diff",
                         "--name-only",
@@ -72,8 +454,12 @@ impl GitExecutor {
                     Ok(vec![target1.clone(), target2.clone()])
                 }
             }
-            OperationMode::Completions { .. } => Err(anyhow!(
-                "Completions mode should not call get_changed_files"
+            OperationMode::PatchFile { .. }
+            | OperationMode::Themes { .. }
+            | OperationMode::Completions { .. }
+            | OperationMode::ClearChecks { .. } => Err(anyhow!(
+                "{} mode should not call get_changed_files",
+                mode.description()
             )),
             OperationMode::Invalid { reason } => Err(anyhow!("Invalid operation mode: {}", reason)),
         }
@@ -81,39 +467,209 @@ impl GitExecutor {
 
     /// Get diff for a specific file
     pub fn get_file_diff(&self, mode: &OperationMode, file_path: &str) -> Result<String> {
+        self.get_paths_diff(mode, &[file_path])
+    }
+
+    /// Get the diff restricted to the given paths, e.g. both sides of a rename
+    pub fn get_paths_diff(&self, mode: &OperationMode, paths: &[&str]) -> Result<String> {
+        let diff = |args: &[&str]| {
+            let mut args = args.to_vec();
+            args.push("--");
+            args.extend(paths);
+            self.execute_git_diff(&args)
+        };
         match mode {
-            OperationMode::GitWorkingDirectory => self.execute_git_diff(&["diff", "--", file_path]),
-            OperationMode::GitCached => {
-                self.execute_git_diff(&["diff", "--cached", "--", file_path])
-            }
-            OperationMode::GitDiff { target } => {
-                self.execute_git_diff(&["diff", target, "--", file_path])
+            OperationMode::GitWorkingDirectory => diff(&["diff"]),
+            OperationMode::GitCached => diff(&["diff", "--cached"]),
+            OperationMode::GitHead => diff(&["diff", "HEAD"]),
+            OperationMode::GitCachedRef { target } => diff(&["diff", "--cached", target]),
+            OperationMode::GitDiff { target, .. } => diff(&["diff", target]),
+            OperationMode::GitStatus => diff(&["diff"]),
+            OperationMode::GitConflicts => {
+                self.read_conflicted_file(paths.first().context("Missing conflicted file path")?)
             }
-            OperationMode::GitStatus => self.execute_git_diff(&["diff", "--", file_path]),
-            OperationMode::Compare { target1, target2 } => {
-                if self.is_git_ref(target1)? && self.is_git_ref(target2)? {
-                    self.execute_git_diff(&[
-                        "diff",
-                        &format!("{target1}..{target2}"),
-                        "--",
-                        file_path,
-                    ])
+            OperationMode::Compare {
+                target1,
+                target2,
+                force_ref,
+            } => {
+                if self.is_git_ref(target1, *force_ref)? && self.is_git_ref(target2, *force_ref)? {
+                    diff(&["diff", &format!("{target1}..{target2}")])
                 } else {
                     // For file comparison, assume the file_path is one of the targets
-                    self.execute_regular_diff(target1, target2)
+                    self.execute_regular_diff(target1, target2, false)
                 }
             }
-            OperationMode::Completions { .. } => {
-                Err(anyhow!("Completions mode should not call get_file_diff"))
-            }
+            OperationMode::PatchFile { .. }
+            | OperationMode::Themes { .. }
+            | OperationMode::Completions { .. }
+            | OperationMode::ClearChecks { .. } => Err(anyhow!(
+                "{} mode should not call get_file_diff",
+                mode.description()
+            )),
             OperationMode::Invalid { reason } => Err(anyhow!("Invalid operation mode: {}", reason)),
         }
     }
 
-    /// Execute git diff command
-    fn execute_git_diff(&self, args: &[&str]) -> Result<String> {
-        let output = Command::new("git")
-            .args(args)
+    /// List untracked files, respecting .gitignore via --exclude-standard
+    pub fn get_untracked_files(&self) -> Result<Vec<String>> {
+        let mut command = git_command();
+        command.args([
+            "ls-files",
+            "--others",
+            "--exclude-standard",
+            "--full-name",
+            "-z",
+            "--",
+        ]);
+        if self.options.pathspec.is_empty() {
+            // ls-files only lists the current directory, while git diff covers the whole tree
+            command.arg(":/");
+        } else {
+            command.args(&self.options.pathspec);
+        }
+        let output = command.output().context("Failed to execute git ls-files")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("Git ls-files failed: {}", stderr));
+        }
+
+        // -z keeps paths unquoted and NUL-separated. --full-name makes them relative to the
+        // repository root like git diff's, even when ftdv runs from a subdirectory
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(|path| path.to_string())
+            .collect())
+    }
+
+    /// Get status mode diffs, grouped like `git status` into staged, unstaged and untracked files
+    pub fn get_status_diffs(&self, include_untracked: bool) -> Result<Vec<FileDiff>> {
+        let mut file_diffs = Vec::new();
+        for (group, args) in [
+            (StatusGroup::Staged, &["diff", "--cached"][..]),
+            (StatusGroup::Unstaged, &["diff"][..]),
+        ] {
+            let diffs = match self.execute_git_diff_all(args) {
+                Err(e) if e.is::<DiffTooLarge>() => self.execute_git_summary(args)?,
+                diff => DiffParser::parse(&diff?),
+            };
+            file_diffs.extend(diffs.into_iter().map(|mut file_diff| {
+                file_diff.status_group = Some(group);
+                file_diff
+            }));
+        }
+
+        // Untracked files count as added for the diff filter
+        let untracked_wanted = self
+            .options
+            .diff_filter
+            .as_deref()
+            .is_none_or(diff_filter_includes_added);
+        if include_untracked && untracked_wanted {
+            file_diffs.extend(
+                self.get_untracked_diffs()?
+                    .into_iter()
+                    .map(|mut file_diff| {
+                        file_diff.status_group = Some(StatusGroup::Untracked);
+                        file_diff
+                    }),
+            );
+        }
+        Ok(file_diffs)
+    }
+
+    /// Get untracked files as file diffs that show their whole content as added
+    pub fn get_untracked_diffs(&self) -> Result<Vec<FileDiff>> {
+        let files = self.get_untracked_files()?;
+        if files.is_empty() {
+            return Ok(Vec::new());
+        }
+        let root = Self::repo_root()?;
+        Ok(files
+            .iter()
+            .flat_map(|path| self.untracked_file_diffs(&root, path))
+            .collect())
+    }
+
+    /// Diff a single untracked file, or a placeholder carrying the error if it cannot be read
+    ///
+    /// `path` is relative to `root`, the repository root.
+    fn untracked_file_diffs(&self, root: &Path, path: &str) -> Vec<FileDiff> {
+        let mut file_diffs = match self.build_untracked_file_diff(root, path) {
+            Ok(diff) => DiffParser::parse(&diff),
+            Err(e) => vec![FileDiff {
+                filename: path.to_string(),
+                old_path: Some("/dev/null".to_string()),
+                new_path: Some(format!("b/{path}")),
+                change_kind: ChangeKind::Added,
+                load_error: Some(format!("{e:#}")),
+                ..Default::default()
+            }],
+        };
+        for file_diff in &mut file_diffs {
+            file_diff.is_untracked = true;
+        }
+        file_diffs
+    }
+
+    /// Synthesize a "new file" diff for an untracked file
+    fn build_untracked_file_diff(&self, root: &Path, path: &str) -> Result<String> {
+        let full_path = root.join(path);
+        let bytes = fs::read(&full_path).with_context(|| format!("Failed to read {path}"))?;
+
+        let mut diff = format!("diff --git a/{path} b/{path}\nnew file mode 100644\n");
+
+        // Include the blob hash so check states persist like tracked files
+        if let Some(hash) = self.hash_object(&full_path) {
+            let short_hash = &hash[..hash.len().min(7)];
+            diff.push_str(&format!("index 0000000..{short_hash}\n"));
+        }
+
+        match String::from_utf8(bytes) {
+            Ok(text) if text.is_empty() => {}
+            Ok(text) => {
+                // split_inclusive keeps a \r before each \n, like git diff does for CRLF files
+                let line_count = text.split_inclusive('\n').count();
+                diff.push_str(&format!("--- /dev/null\n+++ b/{path}\n"));
+                diff.push_str(&format!("@@ -0,0 +1,{line_count} @@\n"));
+                for line in text.split_inclusive('\n') {
+                    diff.push('+');
+                    diff.push_str(line);
+                }
+                if !text.ends_with('\n') {
+                    diff.push_str("\n\\ No newline at end of file\n");
+                }
+            }
+            Err(_) => diff.push_str(&format!("Binary files /dev/null and b/{path} differ\n")),
+        }
+
+        Ok(diff)
+    }
+
+    /// Get the blob hash git would assign to a file
+    fn hash_object(&self, path: &Path) -> Option<String> {
+        let output = git_command()
+            .args(["hash-object", "--"])
+            .arg(path)
+            .output()
+            .ok()?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Execute git diff command, inserting the configured diff options after the subcommand
+    fn execute_git_diff<S: AsRef<OsStr>>(&self, args: &[S]) -> Result<String> {
+        let (subcommand, rest) = args.split_first().context("Missing git subcommand")?;
+        let output = git_command()
+            .arg(subcommand)
+            .args(self.options.to_args())
+            .args(rest)
             .output()
             .context("Failed to execute git diff")?;
 
@@ -125,11 +681,98 @@ impl GitExecutor {
         String::from_utf8(output.stdout).context("Git diff output is not valid UTF-8")
     }
 
+    /// Run a whole-tree git diff, restricted to the configured pathspec
+    ///
+    /// Per-file diffs skip this, since git would list the union of the file and the pathspec.
+    /// Output beyond `max_output_bytes` fails with `DiffTooLarge` instead of being read.
+    fn execute_git_diff_all(&self, args: &[&str]) -> Result<String> {
+        let args = self.whole_tree_args(args);
+        match self.options.max_output_bytes {
+            Some(limit) => self.execute_git_diff_capped(&args, limit),
+            None => self.execute_git_diff(&args),
+        }
+    }
+
+    /// Execute git diff like `execute_git_diff`, but stop reading once the output passes `limit`
+    fn execute_git_diff_capped<S: AsRef<OsStr>>(&self, args: &[S], limit: usize) -> Result<String> {
+        let (subcommand, rest) = args.split_first().context("Missing git subcommand")?;
+        let mut child = git_command()
+            .arg(subcommand)
+            .args(self.options.to_args())
+            .args(rest)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to execute git diff")?;
+
+        // Drain stderr separately, so git cannot block on it while stdout is read
+        let mut stderr = child.stderr.take().context("Missing git diff stderr")?;
+        let stderr_reader = std::thread::spawn(move || {
+            let mut text = String::new();
+            let _ = stderr.read_to_string(&mut text);
+            text
+        });
+
+        // One byte past the limit tells a diff of exactly `limit` bytes from a larger one
+        let mut output = Vec::new();
+        child
+            .stdout
+            .take()
+            .context("Missing git diff stdout")?
+            .take(limit as u64 + 1)
+            .read_to_end(&mut output)
+            .context("Failed to read git diff output")?;
+        if output.len() > limit {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(DiffTooLarge { limit }.into());
+        }
+
+        let status = child.wait().context("Failed to wait for git diff")?;
+        if !status.success() {
+            let stderr = stderr_reader.join().unwrap_or_default();
+            return Err(anyhow!("Git diff failed: {}", stderr));
+        }
+
+        String::from_utf8(output).context("Git diff output is not valid UTF-8")
+    }
+
+    /// Run a whole-tree `git diff --raw --numstat` and parse it into summary file diffs
+    fn execute_git_summary(&self, args: &[&str]) -> Result<Vec<FileDiff>> {
+        let (subcommand, rest) = args.split_first().context("Missing git subcommand")?;
+        let mut args = vec![*subcommand, "--raw", "--numstat", "-z"];
+        args.extend(rest);
+        let output = self.execute_git_diff(&self.whole_tree_args(&args))?;
+        Ok(DiffParser::parse_summary(&output))
+    }
+
+    /// Add the configured diff filter after the subcommand and pathspec after a `--` separator
+    fn whole_tree_args(&self, args: &[&str]) -> Vec<String> {
+        let mut args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        if let Some(filter) = self.diff_filter_arg() {
+            args.insert(1.min(args.len()), filter);
+        }
+        if !self.options.pathspec.is_empty() {
+            if !args.iter().any(|arg| arg == "--") {
+                args.push("--".to_string());
+            }
+            args.extend(self.options.pathspec.iter().cloned());
+        }
+        args
+    }
+
+    /// Get the `--diff-filter=...` argument for whole-tree diffs, if a filter is set
+    fn diff_filter_arg(&self) -> Option<String> {
+        self.options
+            .diff_filter
+            .as_ref()
+            .map(|filter| format!("--diff-filter={filter}"))
+    }
+
     /// Execute git command to get file names only
-    #[allow(dead_code)]
     fn execute_git_name_only(&self, args: &[&str]) -> Result<Vec<String>> {
-        let output = Command::new("git")
-            .args(args)
+        let output = git_command()
+            .args(self.whole_tree_args(args))
             .output()
             .context("Failed to execute git diff --name-only")?;
 
@@ -147,41 +790,224 @@ impl GitExecutor {
             .collect())
     }
 
-    /// Execute regular diff command for non-git files
-    fn execute_regular_diff(&self, file1: &str, file2: &str) -> Result<String> {
-        let output = Command::new("diff")
-            .args(["-u", file1, file2])
+    /// Diff two files or directories with `git diff --no-index`, which works outside a repo
+    ///
+    /// Unlike the system `diff -u`, this produces git's format with file headers.
+    /// Only `whole_tree` comparisons apply the diff filter.
+    fn execute_regular_diff(&self, file1: &str, file2: &str, whole_tree: bool) -> Result<String> {
+        let output = git_command()
+            .args(["diff", "--no-index"])
+            .args(self.options.to_args())
+            .args(self.diff_filter_arg().filter(|_| whole_tree))
+            .args(["--", file1, file2])
             .output()
-            .context("Failed to execute diff")?;
+            .context("Failed to execute git diff --no-index")?;
 
-        // diff returns exit code 1 when files differ, which is normal
-        if output.status.code() == Some(2) {
+        // --no-index exits with 1 when the paths differ, which is normal, but also when a
+        // path cannot be read, in which case there is no diff
+        let differs = output.status.code() == Some(1) && !output.stdout.is_empty();
+        if !output.status.success() && !differs {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow!("Diff command failed: {}", stderr));
+            return Err(anyhow!("Git diff --no-index failed: {}", stderr));
         }
 
-        String::from_utf8(output.stdout).context("Diff output is not valid UTF-8")
+        String::from_utf8(output.stdout).context("Git diff output is not valid UTF-8")
+    }
+
+    /// Describe the revisions being viewed, e.g. "abc1234 Fix parser" or "main..feature"
+    ///
+    /// Returns None for modes that do not look at a specific commit or range.
+    pub fn revision_label(&self, mode: &OperationMode) -> Option<String> {
+        match mode {
+            OperationMode::GitDiff { target, .. } | OperationMode::GitCachedRef { target } => {
+                self.commit_summary(target)
+            }
+            OperationMode::Compare {
+                target1,
+                target2,
+                force_ref,
+            } => {
+                let both_refs = self.is_git_ref(target1, *force_ref).ok()?
+                    && self.is_git_ref(target2, *force_ref).ok()?;
+                both_refs.then(|| format!("{target1}..{target2}"))
+            }
+            _ => None,
+        }
     }
 
-    /// Check if a string is a valid git ref
-    fn is_git_ref(&self, ref_name: &str) -> Result<bool> {
-        // First check if it's a file or directory path
-        if Path::new(ref_name).exists() {
-            return Ok(false);
+    /// Get the contents of a file at a revision, with a path relative to the repository root
+    pub fn show_file_at(&self, rev: &str, path: &str) -> Result<String> {
+        let output = git_command()
+            .arg("show")
+            .arg(format!("{rev}:{path}"))
+            .output()
+            .context("Failed to execute git show")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("{}", stderr.trim()));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Get the one-line log of a checked-out submodule between two of its commits
+    pub fn submodule_log(dir: &Path, from: &str, to: &str) -> Result<Vec<String>> {
+        let output = git_command()
+            .arg("-C")
+            .arg(dir)
+            .args([
+                "log",
+                "--oneline",
+                "--no-color",
+                &format!("{from}..{to}"),
+                "--",
+            ])
+            .output()
+            .context("Failed to execute git log in submodule")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("{}", stderr.trim()));
         }
 
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Get the short hash and subject line of a commit
+    fn commit_summary(&self, rev: &str) -> Option<String> {
+        let output = git_command()
+            .args(["log", "-1", "--format=%h %s", rev, "--"])
+            .output()
+            .ok()?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        let summary = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (!summary.is_empty()).then_some(summary)
+    }
+
+    /// Check if a string is a valid git ref
+    ///
+    /// Precedence:
+    /// 1. Anything `git rev-parse` can resolve is a ref, even if a same-named path exists.
+    /// 2. Otherwise, an existing file, directory, or symlink (even a broken one) is a path.
+    /// 3. Otherwise the target is an error, since it is neither.
+    ///
+    /// With `force_ref`, step 2 is skipped so an unresolvable name reports a ref error.
+    pub fn is_git_ref(&self, ref_name: &str, force_ref: bool) -> Result<bool> {
         // Check if git can resolve it as a ref
-        let output = Command::new("git")
-            .args(["rev-parse", "--verify", ref_name])
+        let output = git_command()
+            .args(["rev-parse", "--verify", "--quiet", ref_name])
             .output()
             .context("Failed to check git ref")?;
 
-        Ok(output.status.success())
+        if output.status.success() {
+            return Ok(true);
+        }
+
+        if force_ref {
+            return Err(anyhow!("'{}' is not a valid git ref", ref_name));
+        }
+
+        // symlink_metadata does not follow links, so broken symlinks still count as paths
+        if fs::symlink_metadata(ref_name).is_ok() {
+            return Ok(false);
+        }
+
+        Err(anyhow!(
+            "'{}' is neither a git ref nor an existing path",
+            ref_name
+        ))
+    }
+}
+
+#[cfg(test)]
+pub mod test_repo {
+    use super::TEST_REPO_DIR;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    /// Temporary git repository with a known history, for tests that run git
+    pub struct TestRepo {
+        dir: tempfile::TempDir,
+    }
+
+    /// Keeps git commands of the current thread in a `TestRepo` until dropped
+    pub struct EnteredRepo {
+        previous: Option<PathBuf>,
+    }
+
+    impl TestRepo {
+        /// Create a repository on branch main without any commits
+        pub fn new() -> Self {
+            let repo = Self {
+                dir: tempfile::TempDir::new().unwrap(),
+            };
+            repo.git(&["init", "-q", "-b", "main"]);
+            repo
+        }
+
+        pub fn path(&self) -> &Path {
+            self.dir.path()
+        }
+
+        /// Write a file, creating its parent directories
+        pub fn write(&self, path: &str, contents: &str) {
+            let path = self.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        /// Stage every change and commit it
+        pub fn commit(&self, message: &str) {
+            self.git(&["add", "-A"]);
+            self.git(&["commit", "-q", "--allow-empty", "-m", message]);
+        }
+
+        /// Run git in the repository, returning its output
+        pub fn git(&self, args: &[&str]) -> String {
+            let output = Command::new("git")
+                .current_dir(self.path())
+                .args(["-c", "user.name=ftdv", "-c", "user.email=ftdv@example.com"])
+                .args(["-c", "commit.gpgsign=false"])
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "git {args:?} failed");
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        }
+
+        /// Run git commands of the current thread in the repository
+        pub fn enter(&self) -> EnteredRepo {
+            self.enter_dir("")
+        }
+
+        /// Run git commands of the current thread in a directory of the repository
+        pub fn enter_dir(&self, dir: &str) -> EnteredRepo {
+            let dir = self.path().join(dir);
+            EnteredRepo {
+                previous: TEST_REPO_DIR.with(|current| current.replace(Some(dir))),
+            }
+        }
+    }
+
+    impl Drop for EnteredRepo {
+        fn drop(&mut self) {
+            TEST_REPO_DIR.with(|current| *current.borrow_mut() = self.previous.take());
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::test_repo::TestRepo;
     use super::*;
 
     #[test]
@@ -190,6 +1016,411 @@ mod tests {
         // Just test that we can create it without panicking
     }
 
+    #[test]
+    fn test_diff_options_args() {
+        assert!(DiffOptions::default().to_args().is_empty());
+
+        let options = DiffOptions {
+            context_lines: Some(10),
+            inter_hunk_context: Some(4),
+            color: Some("never".to_string()),
+            pathspec: Vec::new(),
+            reverse: true,
+            max_output_bytes: None,
+            diff_filter: None,
+        };
+        assert_eq!(
+            options.to_args(),
+            vec![
+                "-U10".to_string(),
+                "--inter-hunk-context=4".to_string(),
+                "--color=never".to_string(),
+                "-R".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_regular_diff_uses_git_format() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let old = temp_dir.path().join("old.txt");
+        let new = temp_dir.path().join("new.txt");
+        fs::write(&old, "same\nbefore\n").unwrap();
+        fs::write(&new, "same\nafter\n").unwrap();
+
+        let executor = GitExecutor::new();
+        let diff = executor
+            .execute_regular_diff(&old.to_string_lossy(), &new.to_string_lossy(), false)
+            .unwrap();
+        let file_diffs = DiffParser::parse(&diff);
+        assert_eq!(file_diffs.len(), 1);
+        assert_eq!(file_diffs[0].added_lines, 1);
+        assert_eq!(file_diffs[0].removed_lines, 1);
+
+        // Identical files are not an error either
+        let diff = executor
+            .execute_regular_diff(&old.to_string_lossy(), &old.to_string_lossy(), false)
+            .unwrap();
+        assert!(diff.is_empty());
+        assert!(
+            executor
+                .execute_regular_diff("/no/such/ftdv/a", "/no/such/ftdv/b", false)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_no_index_paths() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let left = temp_dir.path().join("left");
+        let right = temp_dir.path().join("right");
+        fs::create_dir_all(left.join("src")).unwrap();
+        fs::create_dir_all(right.join("src")).unwrap();
+        fs::write(left.join("src/a.rs"), "a").unwrap();
+        fs::write(right.join("src/a.rs"), "b").unwrap();
+        fs::write(right.join("new.rs"), "new").unwrap();
+        let (left, right) = (left.to_string_lossy(), right.to_string_lossy());
+
+        // git drops the leading slash of absolute paths in its headers
+        let header_path = |path: &str| path.trim_start_matches('/').to_string();
+        assert_eq!(
+            no_index_paths(&left, &right, &header_path(&format!("{left}/src/a.rs"))),
+            (format!("{left}/src/a.rs"), format!("{right}/src/a.rs"))
+        );
+        assert_eq!(
+            no_index_paths(&left, &right, &header_path(&format!("{right}/new.rs"))),
+            ("/dev/null".to_string(), format!("{right}/new.rs"))
+        );
+
+        // Two files are diffed as they are
+        assert_eq!(
+            no_index_paths("a.txt", "b.txt", "a.txt"),
+            ("a.txt".to_string(), "b.txt".to_string())
+        );
+    }
+
+    #[test]
+    fn test_existing_path_is_not_a_ref() {
+        let executor = GitExecutor::new();
+        // Cargo runs tests from the crate root, so Cargo.toml always exists
+        assert!(!executor.is_git_ref("Cargo.toml", false).unwrap());
+        // Forcing a ref reports the unresolvable name instead of falling back to the path
+        assert!(executor.is_git_ref("Cargo.toml", true).is_err());
+    }
+
+    #[test]
+    fn test_unknown_target_is_an_error() {
+        let executor = GitExecutor::new();
+        assert!(
+            executor
+                .is_git_ref("no-such-ref-or-path-for-ftdv", false)
+                .is_err()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_broken_symlink_is_a_path() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let link = temp_dir.path().join("dangling");
+        std::os::unix::fs::symlink(temp_dir.path().join("missing"), &link).unwrap();
+
+        let executor = GitExecutor::new();
+        assert!(!executor.is_git_ref(&link.to_string_lossy(), false).unwrap());
+    }
+
+    #[test]
+    fn test_build_untracked_file_diff() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("new file.txt"), "first\nsecond").unwrap();
+
+        let diff = GitExecutor::new()
+            .build_untracked_file_diff(temp_dir.path(), "new file.txt")
+            .unwrap();
+        let file_diffs = DiffParser::parse(&diff);
+
+        assert_eq!(file_diffs.len(), 1);
+        assert_eq!(file_diffs[0].filename, "new file.txt");
+        assert_eq!(file_diffs[0].added_lines, 2);
+        assert_eq!(file_diffs[0].change_kind, crate::parser::ChangeKind::Added);
+        assert!(diff.ends_with("+second\n\\ No newline at end of file\n"));
+
+        // Carriage returns of CRLF files are kept, as git diff keeps them
+        fs::write(temp_dir.path().join("crlf.txt"), "first\r\nsecond\r\n").unwrap();
+        let diff = GitExecutor::new()
+            .build_untracked_file_diff(temp_dir.path(), "crlf.txt")
+            .unwrap();
+        assert!(diff.contains("@@ -0,0 +1,2 @@\n+first\r\n+second\r\n"));
+        assert!(!diff.contains("No newline"));
+    }
+
+    #[test]
+    fn test_untracked_files_from_subdirectory() {
+        let repo = TestRepo::new();
+        repo.write("tracked.txt", "tracked\n");
+        repo.commit("initial");
+        repo.write("top.txt", "top\n");
+        repo.write("sub/inner.txt", "inner\n");
+        let _entered = repo.enter_dir("sub");
+
+        // Paths are relative to the repository root, like the tracked files' paths
+        let executor = GitExecutor::new();
+        assert_eq!(
+            executor.get_untracked_files().unwrap(),
+            ["sub/inner.txt", "top.txt"]
+        );
+        let file_diffs = executor.get_untracked_diffs().unwrap();
+        assert_eq!(file_diffs.len(), 2);
+        assert!(
+            file_diffs
+                .iter()
+                .all(|file_diff| file_diff.load_error.is_none())
+        );
+        assert!(file_diffs[0].content.contains("+inner\n"));
+        assert!(file_diffs[1].content.contains("+top\n"));
+    }
+
+    #[test]
+    fn test_unreadable_untracked_file_is_kept() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file_diffs = GitExecutor::new().untracked_file_diffs(temp_dir.path(), "gone.txt");
+        assert_eq!(file_diffs.len(), 1);
+        assert_eq!(file_diffs[0].filename, "gone.txt");
+        assert!(file_diffs[0].is_untracked);
+        assert!(
+            file_diffs[0]
+                .load_error
+                .as_deref()
+                .unwrap()
+                .starts_with("Failed to read")
+        );
+    }
+
+    #[test]
+    fn test_repo_root() {
+        let root = GitExecutor::repo_root().unwrap();
+        assert!(root.is_absolute());
+        assert!(root.join("Cargo.toml").exists());
+    }
+
+    #[test]
+    fn test_show_file_at() {
+        let executor = GitExecutor::new();
+        let contents = executor.show_file_at("HEAD", "Cargo.toml").unwrap();
+        assert!(contents.contains("[package]"));
+
+        assert!(executor.show_file_at("HEAD", "no/such/file").is_err());
+    }
+
+    #[test]
+    fn test_upstream_branch() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .current_dir(temp_dir.path())
+                .args(["-c", "user.name=ftdv", "-c", "user.email=ftdv@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {args:?} failed");
+        };
+        git(&["init", "-q", "-b", "main"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "initial"]);
+
+        let error = GitExecutor::upstream_branch_in(temp_dir.path())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Branch 'main' has no upstream branch"));
+
+        git(&["checkout", "-q", "-b", "feature"]);
+        git(&["branch", "-q", "--set-upstream-to=main"]);
+        assert_eq!(
+            GitExecutor::upstream_branch_in(temp_dir.path()).unwrap(),
+            "main"
+        );
+
+        git(&["checkout", "-q", "--detach"]);
+        let error = GitExecutor::upstream_branch_in(temp_dir.path())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("HEAD is detached"));
+    }
+
+    #[test]
+    fn test_ensure_executable() {
+        assert!(GitExecutor::ensure_available().is_ok());
+
+        let error = GitExecutor::ensure_executable("ftdv-no-such-git")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("ftdv-no-such-git executable not found on PATH"));
+    }
+
+    #[test]
+    fn test_known_objects() {
+        let output = Command::new("git")
+            .args(["rev-parse", "HEAD:Cargo.toml"])
+            .output()
+            .unwrap();
+        let blob = String::from_utf8_lossy(&output.stdout)[..7].to_string();
+
+        let known =
+            GitExecutor::known_objects(&[blob.clone(), "0000000".to_string(), "f".repeat(40)])
+                .unwrap();
+        assert_eq!(known, HashSet::from([blob]));
+        assert!(GitExecutor::known_objects(&[]).unwrap().is_empty());
+
+        // More answers than fit in a pipe buffer
+        let missing: Vec<String> = (0..5000).map(|i| format!("{i:040x}")).collect();
+        assert!(GitExecutor::known_objects(&missing).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_large_diff_falls_back_to_summary() {
+        let repo = TestRepo::new();
+        repo.write("a.txt", "one\n");
+        repo.write("src/b.txt", "two\n");
+        repo.commit("initial");
+        repo.write("a.txt", "one\nmore\n");
+        repo.write("src/b.txt", "three\n");
+        repo.commit("change");
+        let _entered = repo.enter();
+
+        let range = OperationMode::Compare {
+            target1: "HEAD~1".to_string(),
+            target2: "HEAD".to_string(),
+            force_ref: true,
+        };
+        let executor = GitExecutor::with_options(DiffOptions {
+            color: Some("always".to_string()),
+            max_output_bytes: Some(16),
+            ..DiffOptions::default()
+        });
+
+        let error = executor.get_diff(&range).unwrap_err();
+        assert!(error.is::<DiffTooLarge>(), "{error}");
+
+        // The summary lists the same files, and each one can still be fetched on its own
+        let summaries = executor.get_summary_diffs(&range).unwrap();
+        let full = DiffParser::parse(&GitExecutor::new().get_diff(&range).unwrap());
+        assert_eq!(
+            summaries.iter().map(|d| &d.filename).collect::<Vec<_>>(),
+            full.iter().map(|d| &d.filename).collect::<Vec<_>>()
+        );
+        assert_eq!(summaries.len(), 2);
+        assert!(summaries.iter().all(FileDiff::is_summary_only));
+        let file_diff = executor
+            .get_file_diff(&range, &summaries[0].filename)
+            .unwrap();
+        assert!(file_diff.contains("diff --git"));
+    }
+
+    #[test]
+    fn test_pathspec_restricts_diff() {
+        let executor = GitExecutor::with_options(DiffOptions {
+            pathspec: vec!["README.md".to_string()],
+            ..DiffOptions::default()
+        });
+        assert_eq!(
+            executor.whole_tree_args(&["diff", "--cached"]),
+            ["diff", "--cached", "--", "README.md"]
+        );
+        assert_eq!(
+            executor.whole_tree_args(&["diff", "main", "--"]),
+            ["diff", "main", "--", "README.md"]
+        );
+
+        let repo = TestRepo::new();
+        repo.write("README.md", "one\n");
+        repo.write("src/lib.rs", "one\n");
+        repo.commit("initial");
+        repo.write("README.md", "two\n");
+        repo.write("src/lib.rs", "two\n");
+        repo.commit("change");
+        let _entered = repo.enter();
+
+        let range = OperationMode::Compare {
+            target1: "HEAD~1".to_string(),
+            target2: "HEAD".to_string(),
+            force_ref: true,
+        };
+        assert_eq!(executor.get_changed_files(&range).unwrap(), ["README.md"]);
+    }
+
+    #[test]
+    fn test_diff_filter() {
+        let executor = GitExecutor::with_options(DiffOptions {
+            diff_filter: Some("M".to_string()),
+            ..DiffOptions::default()
+        });
+        assert_eq!(
+            executor.whole_tree_args(&["diff", "main", "--"]),
+            ["diff", "--diff-filter=M", "main", "--"]
+        );
+
+        let repo = TestRepo::new();
+        repo.write("kept.txt", "one\n");
+        repo.write("removed.txt", "gone\n");
+        repo.commit("initial");
+        repo.write("kept.txt", "two\n");
+        repo.write("added.txt", "new\n");
+        std::fs::remove_file(repo.path().join("removed.txt")).unwrap();
+        repo.commit("change");
+        let _entered = repo.enter();
+
+        let range = OperationMode::Compare {
+            target1: "HEAD~1".to_string(),
+            target2: "HEAD".to_string(),
+            force_ref: true,
+        };
+        let file_diffs = DiffParser::parse(&executor.get_diff(&range).unwrap());
+        assert_eq!(file_diffs.len(), 1);
+        assert_eq!(file_diffs[0].filename, "kept.txt");
+        assert_eq!(file_diffs[0].change_kind, ChangeKind::Modified);
+
+        // A listed file's own diff is fetched without the filter
+        let file_diff = executor
+            .get_file_diff(&range, &file_diffs[0].filename)
+            .unwrap();
+        assert!(file_diff.contains("diff --git"));
+        assert_eq!(describe_diff_filter("D"), "deleted");
+        assert_eq!(describe_diff_filter("AM"), "--diff-filter=AM");
+
+        // Untracked files count as added: lowercase letters only exclude what they name
+        assert!(diff_filter_includes_added("AM"));
+        assert!(!diff_filter_includes_added("M"));
+        assert!(diff_filter_includes_added("d"));
+        assert!(!diff_filter_includes_added("ad"));
+        assert!(!diff_filter_includes_added("Ma"));
+    }
+
+    #[test]
+    fn test_revision_label() {
+        let executor = GitExecutor::new();
+
+        let range = OperationMode::Compare {
+            target1: "HEAD".to_string(),
+            target2: "HEAD".to_string(),
+            force_ref: false,
+        };
+        assert_eq!(
+            executor.revision_label(&range).as_deref(),
+            Some("HEAD..HEAD")
+        );
+
+        // A directory is not a commit, so there is nothing to describe
+        let path = OperationMode::GitDiff {
+            target: "src".to_string(),
+            force_ref: false,
+        };
+        assert_eq!(executor.revision_label(&path), None);
+        assert_eq!(
+            executor.revision_label(&OperationMode::GitWorkingDirectory),
+            None
+        );
+    }
+
     #[test]
     fn test_is_git_repo() {
         // This test will pass if run in a git repository
@@ -1,5 +1,153 @@
+use crate::config::IconConfig;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
 use std::path::Path;
 
+/// Icons from the config that take precedence over the built-in table
+#[derive(Debug, Clone, Default)]
+pub struct IconOverrides {
+    extensions: HashMap<String, char>,
+    filenames: HashMap<String, char>,
+    languages: HashMap<String, String>, // File name or path to the extension of its language
+}
+
+impl IconOverrides {
+    pub fn from_config(config: &IconConfig) -> Result<Self> {
+        let mut overrides = Self::default();
+        for (extension, glyph) in &config.extensions {
+            let icon = parse_glyph(glyph)
+                .with_context(|| format!("Invalid icon for extension '{extension}'"))?;
+            let extension = extension.trim_start_matches('.').to_lowercase();
+            overrides.extensions.insert(extension, icon);
+        }
+        for (filename, glyph) in &config.filenames {
+            let icon = parse_glyph(glyph)
+                .with_context(|| format!("Invalid icon for file '{filename}'"))?;
+            overrides.filenames.insert(filename.clone(), icon);
+        }
+        for (path, language) in &config.languages {
+            overrides
+                .languages
+                .insert(path.clone(), language_extension(language));
+        }
+        Ok(overrides)
+    }
+
+    /// Get icon for a file, preferring overrides for its name and then its extension
+    pub fn get_file_icon(&self, filename: &str) -> char {
+        self.get_path_icon(filename, || None)
+    }
+
+    /// Get icon for a file at a repository path
+    ///
+    /// A language configured for the path or its file name takes precedence over everything
+    /// else, and files without an extension are recognized by a shebang on their first line.
+    pub fn get_path_icon(&self, path: &str, first_line: impl FnOnce() -> Option<String>) -> char {
+        let filename = path.rsplit('/').next().unwrap_or(path);
+        if let Some(extension) = self
+            .languages
+            .get(path)
+            .or_else(|| self.languages.get(filename))
+        {
+            return self.extension_icon(extension);
+        }
+
+        if let Some(icon) = self.filenames.get(filename) {
+            return *icon;
+        }
+
+        let extension = Path::new(filename)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+        match extension {
+            Some(extension) => {
+                if let Some(icon) = self.extensions.get(&extension) {
+                    return *icon;
+                }
+            }
+            None => {
+                if let Some(extension) = first_line().as_deref().and_then(shebang_extension) {
+                    return self.extension_icon(extension);
+                }
+            }
+        }
+
+        get_file_icon(filename)
+    }
+
+    fn extension_icon(&self, extension: &str) -> char {
+        self.extensions
+            .get(extension)
+            .copied()
+            .unwrap_or_else(|| extension_icon(extension))
+    }
+}
+
+/// Get the file extension used for a language name such as `bash`, `python` or `toml`
+fn language_extension(language: &str) -> String {
+    let language = language.trim_start_matches('.').to_lowercase();
+    let extension = match language.as_str() {
+        "bash" | "shell" | "zsh" | "fish" => "sh",
+        "python" => "py",
+        "javascript" | "node" => "js",
+        "typescript" => "ts",
+        "rust" => "rs",
+        "ruby" => "rb",
+        "c++" => "cpp",
+        "markdown" => "md",
+        _ => return language,
+    };
+    extension.to_string()
+}
+
+/// Get the file extension of the language a script's shebang line runs it with
+///
+/// Handles both interpreter paths (`#!/bin/bash`) and `env` (`#!/usr/bin/env python3`).
+pub fn shebang_extension(line: &str) -> Option<&'static str> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        // Skip env's options and variable assignments, e.g. "env -S deno run"
+        program = words.find(|word| !word.starts_with('-') && !word.contains('='))?;
+    }
+
+    // Versioned interpreters such as python3.12 use the same icon
+    let interpreter = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    match interpreter {
+        "sh" | "bash" | "zsh" | "dash" | "ksh" | "fish" => Some("sh"),
+        "python" => Some("py"),
+        "node" | "deno" => Some("js"),
+        "ruby" => Some("rb"),
+        _ => None,
+    }
+}
+
+/// Parse a glyph given either literally or as a codepoint like `U+E7A8` or `0xe7a8`
+fn parse_glyph(value: &str) -> Result<char> {
+    let hex = value
+        .strip_prefix("U+")
+        .or_else(|| value.strip_prefix("u+"))
+        .or_else(|| value.strip_prefix("0x"));
+    if let Some(hex) = hex {
+        let codepoint = u32::from_str_radix(hex, 16)
+            .with_context(|| format!("'{value}' is not a hexadecimal codepoint"))?;
+        return char::from_u32(codepoint)
+            .ok_or_else(|| anyhow::anyhow!("'{value}' is not a valid codepoint"));
+    }
+
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(glyph), None) => Ok(glyph),
+        _ => Err(anyhow::anyhow!(
+            "'{value}' must be a single character or a codepoint like U+E7A8"
+        )),
+    }
+}
+
+/// Icon for submodules, whose diffs only move the commit they point to
+pub const SUBMODULE_ICON: char = '\u{e5fb}';
+
 /// Get icon for a file based on its name and extension  
 pub fn get_file_icon(filename: &str) -> char {
     // Check special filenames first
@@ -26,35 +174,7 @@ pub fn get_file_icon(filename: &str) -> char {
             // Check extension
             if let Some(extension) = Path::new(filename).extension() {
                 if let Some(ext_str) = extension.to_str() {
-                    match ext_str.to_lowercase().as_str() {
-                        // Programming languages
-                        "rs" => '\u{e7a8}',                                 //
-                        "py" | "pyc" | "pyo" | "pyw" => '\u{e73c}',         //
-                        "js" | "jsx" | "mjs" => '\u{e74e}',                 //
-                        "ts" | "tsx" => '\u{e628}',                         //
-                        "go" => '\u{e724}',                                 //
-                        "java" | "class" | "jar" => '\u{e738}',             //
-                        "c" | "h" => '\u{e61e}',                            //
-                        "cpp" | "cxx" | "cc" | "hpp" | "hxx" => '\u{e61d}', //
-                        "rb" => '\u{e739}',                                 //
-
-                        // Config
-                        "json" => '\u{e60b}',                 //
-                        "yaml" | "yml" => '\u{f481}',         //
-                        "toml" => '\u{e615}',                 //
-                        "ini" | "conf" | "cfg" => '\u{e615}', //
-
-                        // Documentation
-                        "md" | "markdown" => '\u{f48a}', //
-                        "txt" | "text" => '\u{f15c}',    //
-
-                        // Web
-                        "html" | "htm" => '\u{e736}',          //
-                        "css" | "scss" | "sass" => '\u{e749}', //
-
-                        // Default
-                        _ => '\u{f15b}', //
-                    }
+                    extension_icon(&ext_str.to_lowercase())
                 } else {
                     '\u{f15b}' // 
                 }
@@ -65,6 +185,40 @@ pub fn get_file_icon(filename: &str) -> char {
     }
 }
 
+/// Get icon for a lowercase file extension
+fn extension_icon(extension: &str) -> char {
+    match extension {
+        // Programming languages
+        "rs" => '\u{e7a8}',                                 //
+        "py" | "pyc" | "pyo" | "pyw" => '\u{e73c}',         //
+        "js" | "jsx" | "mjs" => '\u{e74e}',                 //
+        "ts" | "tsx" => '\u{e628}',                         //
+        "go" => '\u{e724}',                                 //
+        "java" | "class" | "jar" => '\u{e738}',             //
+        "c" | "h" => '\u{e61e}',                            //
+        "cpp" | "cxx" | "cc" | "hpp" | "hxx" => '\u{e61d}', //
+        "rb" => '\u{e739}',                                 //
+        "sh" | "bash" | "zsh" | "fish" => '\u{e795}',       //
+
+        // Config
+        "json" => '\u{e60b}',                 //
+        "yaml" | "yml" => '\u{f481}',         //
+        "toml" => '\u{e615}',                 //
+        "ini" | "conf" | "cfg" => '\u{e615}', //
+
+        // Documentation
+        "md" | "markdown" => '\u{f48a}', //
+        "txt" | "text" => '\u{f15c}',    //
+
+        // Web
+        "html" | "htm" => '\u{e736}',          //
+        "css" | "scss" | "sass" => '\u{e749}', //
+
+        // Default
+        _ => '\u{f15b}', //
+    }
+}
+
 /// Get icon for a directory
 pub fn get_directory_icon(expanded: bool) -> char {
     if expanded {
@@ -73,3 +227,76 @@ pub fn get_directory_icon(expanded: bool) -> char {
         '\u{f114}' //  Closed folder
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_icon_overrides() {
+        let mut config = IconConfig::default();
+        config
+            .extensions
+            .insert(".TF".to_string(), "U+E69A".to_string());
+        config.extensions.insert("rs".to_string(), "R".to_string());
+        config
+            .filenames
+            .insert("flake.nix".to_string(), "0xf313".to_string());
+        let overrides = IconOverrides::from_config(&config).unwrap();
+
+        assert_eq!(overrides.get_file_icon("main.tf"), '\u{e69a}');
+        assert_eq!(overrides.get_file_icon("lib.rs"), 'R');
+        assert_eq!(overrides.get_file_icon("flake.nix"), '\u{f313}');
+        // Special file names without an override keep their built-in icon
+        assert_eq!(overrides.get_file_icon("Cargo.toml"), '\u{e7a8}');
+        assert_eq!(overrides.get_file_icon("main.zig"), '\u{f15b}');
+
+        config
+            .extensions
+            .insert("zig".to_string(), "zig".to_string());
+        assert!(IconOverrides::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_languages_and_shebangs() {
+        let mut config = IconConfig::default();
+        config
+            .languages
+            .insert("settings.txt".to_string(), "TOML".to_string());
+        config
+            .languages
+            .insert("tools/run".to_string(), "python".to_string());
+        let overrides = IconOverrides::from_config(&config).unwrap();
+        let no_line = || None;
+        let line = |line: &str| {
+            let line = line.to_string();
+            move || Some(line)
+        };
+
+        assert_eq!(
+            overrides.get_path_icon("conf/settings.txt", no_line),
+            '\u{e615}'
+        );
+        assert_eq!(overrides.get_path_icon("tools/run", no_line), '\u{e73c}');
+        assert_eq!(overrides.get_path_icon("other/run", no_line), '\u{f15b}');
+
+        // Extensionless scripts are recognized by their shebang
+        assert_eq!(
+            overrides.get_path_icon("bin/deploy", line("#!/bin/bash")),
+            '\u{e795}'
+        );
+        assert_eq!(
+            overrides.get_path_icon("bin/serve", line("#!/usr/bin/env -S python3.12 -u")),
+            '\u{e73c}'
+        );
+        assert_eq!(
+            overrides.get_path_icon("bin/notes", line("plain text")),
+            '\u{f15b}'
+        );
+        // A file's extension wins over its shebang
+        assert_eq!(
+            overrides.get_path_icon("lib.rs", line("#!/bin/sh")),
+            '\u{e7a8}'
+        );
+    }
+}
@@ -0,0 +1,44 @@
+//! Diff parsing and file tree building used by the ftdv diff viewer
+//!
+//! The items re-exported at the crate root are the stable API:
+//!
+//! - [`DiffParser`] splits `git diff` output (or `jj diff` output) into [`FileDiff`]s
+//! - [`DiffFileKey`] identifies a file's diff by its blob hashes, e.g. for review state
+//! - [`FileTreeBuilder`] arranges file diffs into the rows of a file tree ([`FileTreeItem`]),
+//!   and [`FileTree`] keeps a built tree to flatten again as directories are collapsed
+//! - [`Config`] loads ftdv's YAML configuration
+//!
+//! The modules themselves are public so the `ftdv` binary can share them, but anything only
+//! reachable through a module path may change between releases.
+//!
+//! ```
+//! use ftdv::{DiffParser, FileTreeBuilder};
+//!
+//! let diff = "\
+//! diff --git a/src/main.rs b/src/main.rs
+//! --- a/src/main.rs
+//! +++ b/src/main.rs
+//! @@ -1 +1 @@
+//! -fn main() {}
+//! +fn main() { println!(\"hi\"); }
+//! ";
+//!
+//! let file_diffs = DiffParser::parse(diff);
+//! assert_eq!(file_diffs[0].filename, "src/main.rs");
+//! assert_eq!((file_diffs[0].added_lines, file_diffs[0].removed_lines), (1, 1));
+//!
+//! let tree = FileTreeBuilder::build_file_tree(&file_diffs);
+//! let names: Vec<&str> = tree.iter().map(|item| item.name.as_str()).collect();
+//! assert_eq!(names, ["src", "main.rs"]);
+//! assert!(tree[0].is_directory);
+//! ```
+
+pub mod config;
+pub mod icons;
+pub mod parser;
+pub mod theme;
+pub mod tree;
+
+pub use config::Config;
+pub use parser::{DiffFileKey, DiffParser, FileDiff};
+pub use tree::{FileTree, FileTreeBuilder, FileTreeItem};
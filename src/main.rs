@@ -1,28 +1,42 @@
 mod cli;
-mod config;
+mod clipboard;
 mod diff;
 mod git;
-mod icons;
-mod parser;
 mod persistence;
 mod render;
-mod theme;
-mod tree;
+
+// Parsing, tree building and configuration live in the library crate
+use ftdv::{config, icons, parser, theme, tree};
 
 use crate::cli::{Cli, OperationMode};
-use crate::config::{Config, DiffCommandType};
-use crate::git::GitExecutor;
-use crate::parser::{DiffFileKey, DiffParser, FileDiff};
-use crate::persistence::PersistenceManager;
-use crate::render::{render_diff_content, render_file_list, render_search_box, render_status_line};
+use crate::config::{
+    CheckScope, Config, DefaultMode, DiffCommandType, GitPagingConfig, MAX_SIDEBAR_WIDTH,
+    MIN_SIDEBAR_WIDTH,
+};
+use crate::git::{
+    DiffOptions, DiffTooLarge, GitExecutor, describe_diff_filter, git_command, no_index_paths,
+};
+use crate::icons::IconOverrides;
+use crate::parser::{DiffFileKey, DiffParser, FileDiff, FileDiffSummary, StatusGroup};
+use crate::persistence::{PersistenceManager, hunk_id, push_search_history};
+use crate::render::{
+    render_diff_content, render_error_dialog, render_file_list, render_footer, render_prompt,
+    render_search_box, render_status_line, render_theme_preview, render_too_small,
+};
 use crate::theme::Theme;
-use crate::tree::{FileTreeBuilder, FileTreeItem};
+use crate::tree::{FileTree, FileTreeBuilder, FileTreeItem};
 use anyhow::Result;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers, MouseButton,
+        MouseEventKind,
+    },
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use ratatui::{
     Frame, Terminal,
     backend::CrosstermBackend,
@@ -31,11 +45,72 @@ use ratatui::{
 };
 use std::io::{self, Read};
 use std::process::{Command, Stdio};
+use std::sync::mpsc;
 
 // Constants for external tool integration
 const DEFAULT_TERMINAL_HEIGHT: &str = "50";
 const DEFAULT_TERMINAL_TYPE: &str = "xterm-256color";
 
+// Size of the rendered theme preview
+const THEME_PREVIEW_WIDTH: u16 = 100;
+const THEME_PREVIEW_HEIGHT: u16 = 16;
+
+// Sample diff rendered by `ftdv themes --preview`
+const THEME_PREVIEW_DIFF: &str = "diff --git a/src/main.rs b/src/main.rs
+index 1111111..2222222 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,4 +1,5 @@
+ fn main() {
+-    println!(\"Hello\");
++    let name = \"ftdv\";
++    println!(\"Hello, {name}!\");
+ }
+diff --git a/src/ui/theme.rs b/src/ui/theme.rs
+new file mode 100644
+--- /dev/null
++++ b/src/ui/theme.rs
+@@ -0,0 +1,2 @@
++pub struct Theme;
++impl Theme {}
+diff --git a/README.md b/README.md
+--- a/README.md
++++ b/README.md
+@@ -1,2 +1 @@
+ # ftdv
+-Old description
+";
+
+// Variables always kept when diff tools get a restricted environment
+const ESSENTIAL_ENV_VARS: &[&str] = &["PATH", "HOME"];
+
+// Step used when resizing the sidebar at runtime (percentage points)
+const SIDEBAR_RESIZE_STEP: u16 = 5;
+
+// Step used when nudging the width template variables at runtime (columns)
+const WIDTH_OFFSET_STEP: i16 = 2;
+
+// Diff filters cycled with m: all files, then added, modified, deleted and renamed ones
+const DIFF_FILTER_CYCLE: [Option<&str>; 5] = [None, Some("A"), Some("M"), Some("D"), Some("R")];
+
+// Narrowest terminal the panes are laid out for, below which a notice is shown instead
+const MIN_UI_WIDTH: u16 = 20;
+// Shortest terminal the panes are laid out for, with room for a border and a few rows
+const MIN_UI_HEIGHT: u16 = 8;
+
+// How long to wait for a diff tool before showing the placeholder
+const DIFF_TOOL_GRACE_MS: u64 = 50;
+const DIFF_TOOL_PLACEHOLDER: &str = "Rendering…";
+
+// Upper bound for vim-style numeric count prefixes
+const MAX_COUNT_PREFIX: u16 = 999;
+
+// Fewest columns H and L scroll, even in a narrow diff pane
+const MIN_HORIZONTAL_PAGE: u16 = 20;
+
+// Context lines (git diff -U) that cover any realistic file, so one hunk shows all of it
+const WHOLE_FILE_CONTEXT: u32 = 1_000_000;
+
 // Template variable values for command substitution
 #[derive(Debug, Clone)]
 struct TemplateValues {
@@ -45,28 +120,188 @@ struct TemplateValues {
     diff_column_width: u16,
 }
 
+/// A diff tool invocation, prepared on the UI thread and run on a worker thread
+enum DiffToolJob {
+    /// No tool to run, the content is shown as-is
+    Ready(String),
+    /// A pager that reads the diff from stdin
+    Stdin {
+        cmd: Command,
+        name: String,
+        input: String,
+    },
+    /// git diff with an external diff tool configured
+    GitExternal(Command),
+}
+
+impl DiffToolJob {
+    fn run(self) -> Result<String> {
+        match self {
+            DiffToolJob::Ready(content) => Ok(content),
+            DiffToolJob::Stdin {
+                mut cmd,
+                name,
+                input,
+            } => run_command_with_stdin(&mut cmd, &name, &input),
+            DiffToolJob::GitExternal(mut cmd) => run_git_external_diff(&mut cmd),
+        }
+    }
+
+    /// Describe the command this job would run, for `--print-diff-command`
+    fn describe(&self) -> String {
+        let (cmd, input) = match self {
+            DiffToolJob::Ready(_) => {
+                return "No diff tool configured, git's diff output is shown as-is".to_string();
+            }
+            DiffToolJob::Stdin { cmd, .. } => (cmd, "the file's diff on stdin"),
+            DiffToolJob::GitExternal(cmd) => (cmd, "none, git runs the tool as diff.external"),
+        };
+
+        let mut lines = vec![format!("Command: {}", format_command_line(cmd))];
+        let env: Vec<String> = cmd
+            .get_envs()
+            .filter_map(|(key, value)| {
+                value
+                    .map(|value| format!("  {}={}", key.to_string_lossy(), value.to_string_lossy()))
+            })
+            .collect();
+        if env.is_empty() {
+            lines.push("Environment: inherited".to_string());
+        } else {
+            lines.push("Environment:".to_string());
+            lines.extend(env);
+        }
+        lines.push(format!("Input: {input}"));
+        lines.join("\n")
+    }
+}
+
+/// Content shown in the diff pane in place of the selected file's diff, until Esc
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Overlay {
+    /// The file's diff across a ref range
+    Range(String),
+    /// The file's contents at a revision
+    FileAtRevision { revision: String, path: String },
+}
+
+/// One-line prompt shown in place of the status line, capturing keys until Enter or Esc
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prompt {
+    /// Ref range to diff the selected file across (v)
+    Range,
+    /// Patch file to write every diff to (w)
+    Export,
+    /// Revision to show the selected file at (V)
+    Revision,
+    /// Command switching the comparison (:)
+    Command,
+}
+
+/// Pane that navigation keys (j/k, g/G, Ctrl-d/Ctrl-u) act on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    FileList,
+    Diff,
+}
+
+/// Checkbox state of a file or directory in the file list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CheckMark {
+    Checked,
+    Partial, // Some hunks of a file, or some files of a directory
+    Unchecked,
+}
+
+/// Diff tool output that is still being produced
+struct PendingDiff {
+    receiver: mpsc::Receiver<Result<String>>,
+    fallback: Option<String>, // Shown if the tool fails
+    for_resize: bool,         // Re-run at a new width, so the shown output is stale
+}
+
 struct App {
     should_quit: bool,
     config: Config,
     theme: Theme,
+    icon_overrides: IconOverrides, // Icons configured per extension or file name
     diff_output: String,
+    show_full_diff: bool, // Ignore maxDiffLines for the current file
     file_tree_items: Vec<FileTreeItem>,
+    file_only_items: Vec<FileTreeItem>, // Files without directory rows
+    files_only: bool,                   // Hide directory rows
+    full_paths: Option<bool>,           // Label files with full paths (None: per view)
+    sticky_scroll: bool,                // Keep the vertical scroll offset across files
+    highlight_whitespace: bool,         // Mark whitespace errors on added lines
     original_file_diffs: Vec<FileDiff>, // Store original file diffs
+    hide_patterns: Option<GlobSet>,     // Compiled ui.hidePatterns, if any are configured
+    hiding_files: bool,                 // Leave files matching hide_patterns out of the tree
+    hidden_count: usize,                // Files currently left out of the tree
+    file_tree: FileTree,                // Built from the file diffs, flattened on collapse
     selected_index: usize,
     vertical_scroll: u16,
     horizontal_scroll: u16,
     collapsed_directories: std::collections::HashSet<String>, // Track collapsed directories
     checked_files: std::collections::HashSet<String>,         // Track checked files by path
-    persistence_manager: PersistenceManager,                  // For saving/loading check states
-    git_executor: Option<GitExecutor>,                        // For getting individual file diffs
-    operation_mode: OperationMode,                            // Track how the app was invoked
+    checked_hunks: std::collections::HashMap<String, std::collections::HashSet<String>>, // By file
+    check_flash: Option<(String, std::time::Instant)>, // Row whose check mark was just toggled
+    error_dialog: Option<String>, // Error from the session, shown until a key is pressed
+    whole_file_context: std::collections::HashSet<String>, // Files shown with full context
+    override_diff_type: Option<DiffCommandType>, // Replaces the configured diff tool
+    persistence_manager: PersistenceManager, // For saving/loading check states
+    git_executor: Option<GitExecutor>, // For getting individual file diffs
+    diff_options: DiffOptions,    // Options applied to every git diff
+    operation_mode: OperationMode, // Track how the app was invoked
+    revision_label: Option<String>, // Commit or range being viewed, looked up once
+    compare_refs: bool, // Both targets of Compare mode are refs rather than paths, looked up once
+    piped_input: bool,  // Diff was read from stdin, so it cannot be reloaded
+    reversed: bool,     // The sides of the comparison were swapped with R
+    alt_screen: bool,   // Drawing in the alternate screen (off with --no-alt-screen)
     // Search functionality
     search_mode: bool,                           // Track if we're in search mode
     search_input_mode: bool,                     // Track if we're actively typing in search
     search_query: String,                        // Current search query
     filtered_file_tree_items: Vec<FileTreeItem>, // Filtered items for search
+    search_history: Vec<String>,                 // Recent queries, oldest first
+    search_history_index: Option<usize>,         // Position while recalling history
+    fuzzy_matcher: SkimMatcherV2,                // Scores paths against the search query
+    content_match_files: Vec<usize>, // Filtered items whose diffs contain the search query
+    search_match_line: Option<usize>, // Diff line of the match last jumped to with ( and )
+    // Prompts for ref ranges, revisions, patch exports and commands
+    prompt: Option<Prompt>,       // Prompt being typed in, if any
+    prompt_query: String,         // Text typed into the prompt
+    prompt_error: Option<String>, // Why the last command failed, shown in the prompt
+    overlay: Option<Overlay>,     // Shown in the diff pane in place of the selected diff
+    debug_pager: bool,            // Show git's raw diff next to the diff tool output
+    raw_diff_output: String,      // Diff passed to the diff tool, kept for debug_pager
+    submodule_log: Vec<String>,   // Commits the selected submodule moved across, if checked out
     // UI state
-    file_list_state: ListState, // For stateful file tree scrolling
+    file_list_state: ListState,        // For stateful file tree scrolling
+    file_list_viewport_height: u16,    // Inner height of the file list from the last render
+    focus: Pane,                       // Pane that navigation keys act on
+    diff_viewport_height: u16,         // Inner height of the diff pane from the last render
+    diff_row_count: usize,             // Rows the diff takes once wrapped, from the last render
+    diff_prefix_rows: usize,           // Rows of explanations above the diff, from the last render
+    diff_viewport_width: u16,          // Inner width of the diff pane from the last render
+    cached_line_widths: Vec<usize>, // Display width of each diff line, valid with cached_max_width
+    cached_max_width: Option<u16>,  // Longest diff line width, None until measured after a change
+    visible_lines: Option<Vec<usize>>, // Lines kept while hiding context, measured with the widths
+    changed_lines_only: bool,       // Hide context lines of git's own diff output
+    sidebar_width: u16,             // File list width as a percentage of the terminal
+    layout_width: u16,              // Terminal width from the last render
+    sidebar_border: u16,            // Column where the diff pane starts in the last render
+    dragging_sidebar: bool,         // Mouse is dragging the border between the panes
+    pending_count: Option<u16>,     // Vim-style numeric prefix typed before a motion
+    status_message: Option<String>, // One-shot confirmation shown in the status line
+    // Resize handling for width-dependent diff tools
+    last_refresh_width: u16, // Diff pane width the diff tool last ran with
+    pending_resize: Option<(u16, std::time::Instant)>, // New width and when it was first seen
+    pending_diff: Option<PendingDiff>, // Diff tool still running on a worker thread
+    width_offset: i16,       // Columns added to the width template variables, set with { and }
+    // Watch mode
+    watch_interval: Option<std::time::Duration>, // How often to refetch, None when not watching
+    last_watch_check: std::time::Instant,        // When the diff was last refetched for watching
+    diff_fingerprint: u64, // Hash of the fetched diffs, to skip rebuilding when nothing changed
 }
 
 impl App {
@@ -75,24 +310,58 @@ impl App {
         file_diffs: Vec<FileDiff>,
         operation_mode: OperationMode,
     ) -> Result<Self> {
-        let diff_output = if file_diffs.is_empty() {
+        let hide_patterns = build_hide_patterns(&config.ui.hide_patterns)?;
+        let shown_diffs = visible_file_diffs(&file_diffs, hide_patterns.as_ref());
+        let hidden_count = file_diffs.len() - shown_diffs.len();
+
+        let diff_output = if shown_diffs.is_empty() {
             String::from("No diff content available")
         } else {
-            file_diffs[0].content.clone()
+            shown_diffs[0].content.clone()
         };
-
-        let file_tree_items = FileTreeBuilder::build_file_tree(&file_diffs);
+        let diff_output = config
+            .ui
+            .max_diff_lines
+            .and_then(|max_lines| truncate_diff_lines(&diff_output, max_lines))
+            .unwrap_or(diff_output);
+
+        let diff_fingerprint = diff_fingerprint(&file_diffs);
+        let file_tree = FileTree::build_sorted(shown_diffs.iter().copied(), config.ui.tree_sort);
+        let collapsed_directories = match config.ui.auto_collapse_depth {
+            Some(depth) => FileTreeBuilder::directory_paths(shown_diffs.iter().copied())
+                .into_iter()
+                .filter(|path| path.split('/').count() > depth)
+                .collect(),
+            None => std::collections::HashSet::new(),
+        };
+        let file_tree_items = file_tree.flatten(&collapsed_directories);
         let theme = config.theme.clone();
+        let icon_overrides = IconOverrides::from_config(&config.icons)?;
+        let sidebar_width = config.ui.clamped_sidebar_width();
+        let full_paths = config.ui.full_paths;
+        let sticky_scroll = config.ui.sticky_scroll;
+        let highlight_whitespace = config.ui.highlight_whitespace;
 
-        // Initialize persistence manager
-        let persistence_manager = PersistenceManager::new()?;
+        // Initialize persistence manager; only per-path check states need the repository
+        let check_scope = config.persistence.check_scope;
+        let repo_dir = (check_scope == CheckScope::PerPath).then(check_states_repo_dir);
+        let persistence_manager = PersistenceManager::new(check_scope, repo_dir.as_deref())?;
 
         // Initialize git executor if needed for interactive file viewing
-        let git_executor = if operation_mode.requires_git_repo() {
-            Some(GitExecutor::new())
+        let mut diff_options = diff_options_from_config(&config);
+        if let DiffCommandType::GitDefault = config.get_diff_command_type() {
+            // Without a diff tool, refetched diffs are displayed directly, so honor colorArg
+            diff_options.color = Some(config.git.paging.resolved_color_arg().to_string());
+        }
+        // Instant mode always shows the diffs parsed at startup, so it never refetches
+        let git_executor = if operation_mode.requires_git_repo() && !config.git.instant {
+            Some(GitExecutor::with_options(diff_options.clone()))
         } else {
             None
         };
+        let revision_label = revision_label_for(&operation_mode, &diff_options);
+        // Targets that are neither were already reported when the diffs were fetched
+        let compare_refs = compare_targets_are_refs(&operation_mode).unwrap_or(false);
 
         // Load existing check states
         let diff_keys: Vec<DiffFileKey> = file_diffs
@@ -103,69 +372,250 @@ impl App {
         let checked_files = persistence_manager
             .load_checked_files(&diff_keys)
             .unwrap_or_else(|_| std::collections::HashSet::new());
+        let checked_hunks = persistence_manager
+            .load_checked_hunks(&diff_keys)
+            .unwrap_or_default();
+        let search_history = persistence_manager
+            .load_search_history()
+            .unwrap_or_default();
 
         Ok(Self {
             should_quit: false,
             config,
             theme,
+            icon_overrides,
             diff_output,
+            show_full_diff: false,
             file_tree_items: file_tree_items.clone(),
+            file_only_items: file_tree.file_list(),
+            file_tree,
+            files_only: false,
+            full_paths,
+            sticky_scroll,
+            highlight_whitespace,
             original_file_diffs: file_diffs,
+            hide_patterns,
+            hiding_files: true,
+            hidden_count,
             selected_index: 0,
             vertical_scroll: 0,
             horizontal_scroll: 0,
-            collapsed_directories: std::collections::HashSet::new(),
+            collapsed_directories,
             checked_files,
+            checked_hunks,
+            check_flash: None,
+            error_dialog: None,
+            whole_file_context: std::collections::HashSet::new(),
+            override_diff_type: None,
             persistence_manager,
             git_executor,
+            diff_options,
             operation_mode,
+            revision_label,
+            compare_refs,
+            piped_input: false,
+            reversed: false,
+            alt_screen: true,
             search_mode: false,
             search_input_mode: false,
             search_query: String::new(),
             filtered_file_tree_items: file_tree_items,
+            search_history,
+            search_history_index: None,
+            fuzzy_matcher: SkimMatcherV2::default().ignore_case(),
+            content_match_files: Vec::new(),
+            search_match_line: None,
+            prompt: None,
+            prompt_query: String::new(),
+            prompt_error: None,
+            overlay: None,
+            debug_pager: false,
+            raw_diff_output: String::new(),
+            submodule_log: Vec::new(),
             file_list_state: {
                 let mut state = ListState::default();
                 state.select(Some(0));
                 state
             },
+            file_list_viewport_height: 0,
+            focus: Pane::FileList,
+            diff_viewport_height: 0,
+            diff_row_count: 0,
+            diff_prefix_rows: 0,
+            diff_viewport_width: 0,
+            cached_line_widths: Vec::new(),
+            cached_max_width: None,
+            visible_lines: None,
+            changed_lines_only: false,
+            sidebar_width,
+            layout_width: 0,
+            sidebar_border: 0,
+            dragging_sidebar: false,
+            pending_count: None,
+            status_message: None,
+            last_refresh_width: 0,
+            pending_resize: None,
+            pending_diff: None,
+            width_offset: 0,
+            watch_interval: None,
+            last_watch_check: std::time::Instant::now(),
+            diff_fingerprint,
         })
     }
 
-    fn select_next(&mut self) {
+    fn select_next(&mut self, count: u16) {
         let current_items = self.get_current_file_tree_items();
         if !current_items.is_empty() && self.selected_index < current_items.len() - 1 {
-            self.selected_index += 1;
+            self.selected_index =
+                (self.selected_index + count as usize).min(current_items.len() - 1);
             self.file_list_state.select(Some(self.selected_index));
             self.update_diff_content();
         }
     }
 
-    fn select_previous(&mut self) {
+    /// Get the number of items to move by when paging through the file list
+    fn list_page_size(&self) -> u16 {
+        self.file_list_viewport_height.max(1)
+    }
+
+    fn select_previous(&mut self, count: u16) {
         if self.selected_index > 0 {
-            self.selected_index -= 1;
+            self.selected_index = self.selected_index.saturating_sub(count as usize);
+            self.file_list_state.select(Some(self.selected_index));
+            self.update_diff_content();
+        }
+    }
+
+    /// Append a digit to the pending count prefix, returning false if it is not part of a count
+    fn push_count_digit(&mut self, digit: char) -> bool {
+        let Some(value) = digit.to_digit(10) else {
+            return false;
+        };
+        // A leading zero is not a count (matches vim)
+        if value == 0 && self.pending_count.is_none() {
+            return false;
+        }
+
+        let count = self.pending_count.unwrap_or(0);
+        self.pending_count = Some((count * 10 + value as u16).min(MAX_COUNT_PREFIX));
+        true
+    }
+
+    /// Take the pending count prefix, resetting it
+    fn take_count(&mut self) -> Option<u16> {
+        self.pending_count.take()
+    }
+
+    /// Jump to the item at the given 1-based position
+    fn jump_to_position(&mut self, position: u16) {
+        let current_items = self.get_current_file_tree_items();
+        if !current_items.is_empty() {
+            self.selected_index = (position as usize).clamp(1, current_items.len()) - 1;
             self.file_list_state.select(Some(self.selected_index));
             self.update_diff_content();
         }
     }
 
+    /// Switch navigation keys between the file list and the diff pane
+    fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            Pane::FileList => Pane::Diff,
+            Pane::Diff => Pane::FileList,
+        };
+    }
+
+    /// Move down in the focused pane: to a later file, or further down the diff
+    fn move_down(&mut self, count: u16) {
+        match self.focus {
+            Pane::FileList => self.select_next(count),
+            Pane::Diff => self.scroll_down(count),
+        }
+    }
+
+    /// Move up in the focused pane: to an earlier file, or further up the diff
+    fn move_up(&mut self, count: u16) {
+        match self.focus {
+            Pane::FileList => self.select_previous(count),
+            Pane::Diff => self.scroll_up(count),
+        }
+    }
+
+    /// Move down a screenful of the focused pane
+    fn page_down(&mut self, count: u16) {
+        match self.focus {
+            Pane::FileList => self.select_next(self.list_page_size().saturating_mul(count)),
+            Pane::Diff => self.scroll_down(self.diff_viewport_height.max(1).saturating_mul(count)),
+        }
+    }
+
+    /// Move up a screenful of the focused pane
+    fn page_up(&mut self, count: u16) {
+        match self.focus {
+            Pane::FileList => self.select_previous(self.list_page_size().saturating_mul(count)),
+            Pane::Diff => self.scroll_up(self.diff_viewport_height.max(1).saturating_mul(count)),
+        }
+    }
+
+    /// Jump to the top of the focused pane, or with a count to that item or diff line
+    fn jump_to_start(&mut self, count: Option<u16>) {
+        match (self.focus, count) {
+            (Pane::FileList, Some(position)) => self.jump_to_position(position),
+            (Pane::FileList, None) => self.jump_to_top(),
+            (Pane::Diff, _) => self.scroll_to_line(count.unwrap_or(1).saturating_sub(1)),
+        }
+    }
+
+    /// Jump to the bottom of the focused pane, or with a count to that item or diff line
+    fn jump_to_end(&mut self, count: Option<u16>) {
+        match (self.focus, count) {
+            (Pane::FileList, Some(position)) => self.jump_to_position(position),
+            (Pane::FileList, None) => self.jump_to_bottom(),
+            (Pane::Diff, Some(line)) => self.scroll_to_line(line.saturating_sub(1)),
+            // Clamped to the last page when rendering
+            (Pane::Diff, None) => self.vertical_scroll = u16::MAX,
+        }
+    }
+
+    /// Scroll the diff pane so a line is at the top, even if lines above it wrap
+    fn scroll_to_line(&mut self, line: u16) {
+        let row = self.row_of_line(line as usize);
+        self.vertical_scroll = row.min(u16::MAX as usize) as u16;
+    }
+
     fn update_diff_content(&mut self) {
+        self.show_full_diff = false;
+        self.cached_max_width = None;
+        self.load_selected_diff();
+    }
+
+    /// Replace the diff pane content, invalidating the cached line widths
+    fn set_diff_output(&mut self, output: String) {
+        self.diff_output = output;
+        self.cached_max_width = None;
+        self.search_match_line = None;
+    }
+
+    /// Load the full diff for the current file, ignoring maxDiffLines
+    fn load_full_diff(&mut self) {
+        if self.show_full_diff || self.overlay.is_some() {
+            return;
+        }
+        self.show_full_diff = true;
+        self.load_selected_diff();
+    }
+
+    fn load_selected_diff(&mut self) {
+        self.overlay = None;
+        self.pending_diff = None;
+        self.submodule_log.clear();
         let current_items = self.get_current_file_tree_items();
         if let Some(tree_item) = current_items.get(self.selected_index) {
             if let Some(file_diff) = &tree_item.file_diff {
-                // Try to get individual file diff if we have a git executor
-                if let Some(ref git_executor) = self.git_executor {
-                    match git_executor.get_file_diff(&self.operation_mode, &tree_item.full_path) {
-                        Ok(fresh_diff) => {
-                            self.diff_output = fresh_diff;
-                        }
-                        Err(_) => {
-                            // Fallback to stored diff content
-                            self.diff_output = file_diff.content.clone();
-                        }
-                    }
-                } else {
-                    // Use stored diff content
-                    self.diff_output = file_diff.content.clone();
+                let submodule_log = self.load_submodule_log(file_diff);
+                self.set_diff_output(self.fetch_file_diff(&tree_item.full_path, file_diff));
+                self.submodule_log = submodule_log;
+                if self.debug_pager {
+                    self.raw_diff_output = self.diff_output.clone();
                 }
 
                 // Apply external diff tool if configured
@@ -175,92 +625,525 @@ impl App {
                 } else {
                     self.apply_external_diff_tool();
                 }
+                self.apply_diff_line_limit();
 
-                // Reset scroll position when switching files
-                self.vertical_scroll = 0;
+                // Reset scroll position when switching files, unless it is sticky.
+                // A sticky offset is clamped to the new file's length on the next render.
+                if !self.sticky_scroll {
+                    self.vertical_scroll = 0;
+                }
                 self.horizontal_scroll = 0;
             } else {
                 // Directory selected - show directory info
-                self.diff_output = format!("Directory: {}", tree_item.full_path);
+                self.set_diff_output(format!("Directory: {}", tree_item.full_path));
                 self.vertical_scroll = 0;
                 self.horizontal_scroll = 0;
             }
         }
     }
 
+    fn enter_prompt(&mut self, prompt: Prompt) {
+        self.prompt = Some(prompt);
+        self.prompt_query.clear();
+        self.prompt_error = None;
+    }
+
+    fn cancel_prompt(&mut self) {
+        self.prompt = None;
+        self.prompt_query.clear();
+        self.prompt_error = None;
+    }
+
+    /// Act on the text typed into the open prompt
+    fn confirm_prompt(&mut self) {
+        match self.prompt {
+            Some(Prompt::Range) => self.confirm_range_input(),
+            Some(Prompt::Export) => self.confirm_export_input(),
+            Some(Prompt::Revision) => self.confirm_revision_input(),
+            Some(Prompt::Command) => self.confirm_command_input(),
+            None => {}
+        }
+    }
+
+    /// Write every file's diff to the typed path as a patch that `--patch` can reopen
+    fn confirm_export_input(&mut self) {
+        self.prompt = None;
+        let path = self.prompt_query.trim().to_string();
+        if path.is_empty() {
+            return;
+        }
+
+        let result = load_summarized_diffs(
+            &self.original_file_diffs,
+            &self.operation_mode,
+            &self.diff_options,
+        )
+        .and_then(|file_diffs| write_patch(&path, &file_diffs));
+        self.status_message = Some(match result {
+            Ok(()) => format!("Wrote {} files to {path}", self.original_file_diffs.len()),
+            Err(e) => format!("Failed to write patch: {e}"),
+        });
+    }
+
+    /// Show the selected file's diff for the typed ref range as a temporary overlay
+    fn confirm_range_input(&mut self) {
+        self.prompt = None;
+        let range = self.prompt_query.trim().to_string();
+        if range.is_empty() {
+            return;
+        }
+
+        let current_items = self.get_current_file_tree_items();
+        let Some(tree_item) = current_items.get(self.selected_index) else {
+            return;
+        };
+        if tree_item.is_directory {
+            return;
+        }
+
+        // git diff takes "A..B", "A...B" (from their merge base) and a single ref (against
+        // the worktree) as they are
+        let mode = OperationMode::GitDiff {
+            target: range.clone(),
+            force_ref: true,
+        };
+
+        let git_executor = GitExecutor::with_options(self.diff_options.clone());
+        self.set_diff_output(
+            match git_executor.get_file_diff(&mode, &tree_item.full_path) {
+                Ok(diff) if diff.is_empty() => format!("No changes to this file in {range}"),
+                Ok(diff) => diff,
+                Err(e) => format!("Failed to diff {range}: {e}"),
+            },
+        );
+        self.pending_diff = None;
+        self.overlay = Some(Overlay::Range(range));
+        self.vertical_scroll = 0;
+        self.horizontal_scroll = 0;
+    }
+
+    /// Switch to the comparison typed on the command line and fetch its diffs
+    ///
+    /// Invalid commands and failed fetches keep the prompt open with the error, and the
+    /// current comparison stays as it was.
+    fn confirm_command_input(&mut self) {
+        let command = self.prompt_query.trim().to_string();
+        if command.is_empty() {
+            self.cancel_prompt();
+            return;
+        }
+        if self.piped_input {
+            self.prompt_error = Some("Cannot switch comparisons for a diff read from stdin".into());
+            return;
+        }
+        let mode = match OperationMode::from_command(&command) {
+            Ok(mode) => mode,
+            Err(e) => {
+                self.prompt_error = Some(e.to_string());
+                return;
+            }
+        };
+
+        // A new comparison starts out unreversed
+        let previous_mode = std::mem::replace(&mut self.operation_mode, mode);
+        let previous_compare_refs = self.compare_refs;
+        let previous_reverse = (self.reversed, self.config.git.reverse);
+        self.reversed = false;
+        self.config.git.reverse = false;
+        self.diff_options.reverse = false;
+
+        let refetched = compare_targets_are_refs(&self.operation_mode).and_then(|refs| {
+            self.compare_refs = refs;
+            self.refetch()
+        });
+        if let Err(e) = refetched {
+            self.operation_mode = previous_mode;
+            self.compare_refs = previous_compare_refs;
+            (self.reversed, self.config.git.reverse) = previous_reverse;
+            self.diff_options.reverse = self.config.git.reverse;
+            self.prompt_error = Some(format!("{e:#}"));
+            return;
+        }
+
+        self.rebuild_git_executor();
+        self.revision_label = revision_label_for(&self.operation_mode, &self.diff_options);
+        self.overlay = None;
+        self.pending_diff = None;
+        self.cancel_prompt();
+        self.status_message = Some(self.operation_mode.description());
+    }
+
+    /// Show the selected file's contents at the typed revision as a temporary overlay
+    fn confirm_revision_input(&mut self) {
+        self.prompt = None;
+        let revision = self.prompt_query.trim().to_string();
+        if revision.is_empty() {
+            return;
+        }
+
+        let current_items = self.get_current_file_tree_items();
+        let Some(tree_item) = current_items.get(self.selected_index) else {
+            return;
+        };
+        if tree_item.is_directory {
+            return;
+        }
+        let path = tree_item.full_path.clone();
+
+        let git_executor = GitExecutor::new();
+        self.set_diff_output(match git_executor.show_file_at(&revision, &path) {
+            Ok(contents) => contents,
+            Err(e) => format!("Failed to show {path} at {revision}: {e}"),
+        });
+        self.pending_diff = None;
+        self.overlay = Some(Overlay::FileAtRevision { revision, path });
+        self.vertical_scroll = 0;
+        self.horizontal_scroll = 0;
+    }
+
+    /// Leave the ref range or file overlay and show the normal diff again
+    fn close_overlay(&mut self) {
+        self.update_diff_content();
+    }
+
+    /// Recreate the git executor for the current mode so it picks up changed diff options
+    ///
+    /// Instant mode and modes without a repository keep using the stored diffs.
+    fn rebuild_git_executor(&mut self) {
+        self.git_executor = if self.operation_mode.requires_git_repo() && !self.config.git.instant {
+            Some(GitExecutor::with_options(self.diff_options.clone()))
+        } else {
+            None
+        };
+    }
+
+    /// Get the diff for a single file, refetching it from git when possible
+    fn fetch_file_diff(&self, file_path: &str, file_diff: &FileDiff) -> String {
+        if let Some(error) = &file_diff.load_error {
+            return error.clone();
+        }
+
+        // Staged files in status mode are diffed against HEAD instead of the worktree
+        let mode = match file_diff.status_group {
+            Some(StatusGroup::Staged) => &OperationMode::GitCached,
+            _ => &self.operation_mode,
+        };
+
+        // Files of a diff too large to read whole are fetched now, even in instant mode
+        if file_diff.is_summary_only() {
+            return fetch_summarized_diff(&self.operation_mode, &self.diff_options, file_diff)
+                .unwrap_or_else(|e| format!("Failed to load the diff of {file_path}: {e}"));
+        }
+
+        // Untracked files have no git diff to refetch, and piped input may not come from git.
+        // A rename spans two paths, so refetching the new one alone would show an added file
+        if file_diff.is_untracked || self.piped_input || file_diff.rename_from.is_some() {
+            return file_diff.content.clone();
+        }
+
+        // Try to get individual file diff if we have a git executor
+        if let Some(ref git_executor) = self.git_executor {
+            let result = if self.whole_file_context.contains(file_path) {
+                GitExecutor::with_options(DiffOptions {
+                    context_lines: Some(WHOLE_FILE_CONTEXT),
+                    ..self.diff_options.clone()
+                })
+                .get_file_diff(mode, file_path)
+            } else {
+                git_executor.get_file_diff(mode, file_path)
+            };
+            match result {
+                Ok(fresh_diff) => fresh_diff,
+                // Fallback to stored diff content
+                Err(_) => file_diff.content.clone(),
+            }
+        } else {
+            // Use stored diff content
+            file_diff.content.clone()
+        }
+    }
+
     fn apply_external_diff_tool(&mut self) {
         self.apply_external_diff_tool_with_width(None);
     }
 
+    /// Truncate the diff output to maxDiffLines unless the full diff was requested
+    fn apply_diff_line_limit(&mut self) {
+        if self.show_full_diff {
+            return;
+        }
+        if let Some(max_lines) = self.config.ui.max_diff_lines {
+            if let Some(truncated) = truncate_diff_lines(&self.diff_output, max_lines) {
+                self.set_diff_output(truncated);
+            }
+        }
+    }
+
+    /// Get the commits a submodule change moved across, if the submodule is checked out
+    fn load_submodule_log(&self, file_diff: &FileDiff) -> Vec<String> {
+        let Some(submodule) = &file_diff.submodule else {
+            return Vec::new();
+        };
+        let (Some(old), Some(new)) = (&submodule.old_commit, &submodule.new_commit) else {
+            return Vec::new();
+        };
+        let Ok(base_dir) = self.path_base_dir() else {
+            return Vec::new();
+        };
+        let submodule_dir = base_dir.join(&file_diff.filename);
+        if !submodule_dir.join(".git").exists() {
+            return Vec::new();
+        }
+        let commit = |hash: &str| hash.trim_end_matches("-dirty").to_string();
+        GitExecutor::submodule_log(&submodule_dir, &commit(old), &commit(new)).unwrap_or_default()
+    }
+
+    /// Get the lines shown above the diff to explain what changed, e.g. for a pure rename
+    ///
+    /// They end with a blank line separating them from the diff, unless the diff shows
+    /// nothing at all, in which case they replace it.
+    fn diff_prefix_lines(&self) -> Vec<String> {
+        let Some(summary) = self.no_text_changes_summary() else {
+            return Vec::new();
+        };
+        // A checked-out submodule also lists the commits its pointer moved across
+        let mut lines = vec![summary];
+        lines.extend(
+            self.submodule_log
+                .iter()
+                .map(|commit| format!("  {commit}")),
+        );
+        if !self.diff_output_is_blank() {
+            lines.push(String::new());
+        }
+        lines
+    }
+
+    /// Check if the diff output has no visible text, such as git's empty diff for a rename
+    fn diff_output_is_blank(&self) -> bool {
+        strip_ansi_escapes::strip_str(&self.diff_output)
+            .trim()
+            .is_empty()
+    }
+
+    /// Explain why the selected file's diff has nothing to show, e.g. for a pure rename
+    fn no_text_changes_summary(&self) -> Option<String> {
+        if self.overlay.is_some() || self.shows_file_contents() {
+            return None;
+        }
+        self.get_current_file_tree_items()
+            .get(self.selected_index)?
+            .file_diff
+            .as_ref()?
+            .no_text_changes_summary()
+    }
+
+    /// Check if the diff pane shows file contents rather than a diff
+    ///
+    /// Diff tools cannot render plain file contents, so they are skipped for these.
+    fn shows_file_contents(&self) -> bool {
+        match &self.overlay {
+            Some(Overlay::FileAtRevision { .. }) => true,
+            Some(Overlay::Range(_)) => false,
+            None => matches!(self.operation_mode, OperationMode::GitConflicts),
+        }
+    }
+
     fn apply_external_diff_tool_with_width(&mut self, width: Option<u16>) {
+        if self.shows_file_contents() || self.input_is_preformatted() {
+            return;
+        }
         // Check if we should use a diff tool (pager or external)
-        match self.config.get_diff_command_type() {
+        match self.diff_command_type() {
             DiffCommandType::GitDefault => {
                 // No processing needed
             }
             DiffCommandType::Pager(_) | DiffCommandType::External(_) => {
-                match self.execute_external_diff_tool_with_width(&self.diff_output, width) {
-                    Ok(processed_output) => {
-                        self.diff_output = processed_output;
-                    }
-                    Err(e) => {
-                        // Log error but continue with original output
-                        eprintln!("Warning: Failed to process with diff tool: {e}");
+                // On failure the original output is shown instead
+                let original_output = self.diff_output.clone();
+                match self.prepare_diff_tool_with_width(&self.diff_output, width) {
+                    Ok(job) => {
+                        self.spawn_diff_tool(job, Some(original_output));
+                        if self.pending_diff.is_some() {
+                            self.set_diff_output(DIFF_TOOL_PLACEHOLDER.to_string());
+                        }
                     }
+                    // Keep showing the original output
+                    Err(e) => self.report_diff_tool_error(&e),
+                }
+            }
+        }
+    }
+
+    /// Check if the diff pane is split into git's raw diff and the diff tool's output
+    fn shows_debug_split(&self) -> bool {
+        self.debug_pager
+            && self.overlay.is_none()
+            && !self.shows_file_contents()
+            && !self.input_is_preformatted()
+            && !matches!(self.diff_command_type(), DiffCommandType::GitDefault)
+    }
+
+    /// Check if piped input was already colored by a diff tool, and should be shown as-is
+    fn input_is_preformatted(&self) -> bool {
+        self.piped_input
+            && self.config.git.paging.assume_colored
+            && self.contains_ansi_codes(&self.diff_output)
+    }
+
+    /// Run a diff tool job on a worker thread
+    ///
+    /// Fast tools finish within a short grace period and are applied right away, so the
+    /// placeholder only appears for slow ones. `fallback` is shown if the tool fails.
+    fn spawn_diff_tool(&mut self, job: DiffToolJob, fallback: Option<String>) {
+        // A newer job supersedes any pending one; its result is dropped when it arrives
+        self.pending_diff = None;
+
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(job.run());
+        });
+
+        match receiver.recv_timeout(std::time::Duration::from_millis(DIFF_TOOL_GRACE_MS)) {
+            Ok(result) => self.finish_diff_tool(result, fallback),
+            Err(_) => {
+                self.pending_diff = Some(PendingDiff {
+                    receiver,
+                    fallback,
+                    for_resize: false,
+                })
+            }
+        }
+    }
+
+    /// Check if the diff is shown at an old width while waiting for a resize to settle or for
+    /// the diff tool to re-render it
+    fn is_resizing(&self) -> bool {
+        self.pending_resize.is_some()
+            || self
+                .pending_diff
+                .as_ref()
+                .is_some_and(|pending| pending.for_resize)
+    }
+
+    /// Apply the result of a pending diff tool job if it has finished
+    fn poll_diff_tool(&mut self) {
+        let Some(pending) = &self.pending_diff else {
+            return;
+        };
+
+        match pending.receiver.try_recv() {
+            Ok(result) => {
+                let fallback = self
+                    .pending_diff
+                    .take()
+                    .and_then(|pending| pending.fallback);
+                self.finish_diff_tool(result, fallback);
+            }
+            Err(mpsc::TryRecvError::Empty) => {}
+            Err(mpsc::TryRecvError::Disconnected) => self.pending_diff = None,
+        }
+    }
+
+    fn finish_diff_tool(&mut self, result: Result<String>, fallback: Option<String>) {
+        match result {
+            Ok(processed_output) => self.set_diff_output(processed_output),
+            Err(e) => {
+                self.report_diff_tool_error(&e);
+                match fallback {
+                    Some(fallback) => self.set_diff_output(fallback),
+                    None => return,
                 }
             }
         }
+        self.apply_diff_line_limit();
+    }
+
+    /// Describe how the diff tool would be run for the selected file
+    fn describe_diff_command(&self, area_width: u16, terminal_width: u16) -> Result<String> {
+        let tree_item = self
+            .get_current_file_tree_items()
+            .get(self.selected_index)
+            .filter(|tree_item| !tree_item.is_directory)
+            .ok_or_else(|| anyhow::anyhow!("No file selected"))?;
+        let file_diff = tree_item
+            .file_diff
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No diff for {}", tree_item.full_path))?;
+
+        let base_diff = self.fetch_file_diff(&tree_item.full_path, file_diff);
+        let job = self.prepare_diff_tool_with_area_width(&base_diff, area_width, terminal_width)?;
+        Ok(format!(
+            "File: {}\nDiff tool: {}\n{}",
+            tree_item.full_path,
+            self.diff_command_type().display_name(),
+            job.describe()
+        ))
+    }
+
+    /// Show a diff tool failure in the status line, since stderr is hidden behind the TUI
+    fn report_diff_tool_error(&mut self, error: &anyhow::Error) {
+        // Tool errors usually end with the tool's stderr, which may span several lines
+        let message = error.to_string();
+        let details: Vec<&str> = message
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        self.status_message = Some(format!(
+            "⚠ {} failed: {}",
+            self.diff_command_type().display_name(),
+            details.join(" ")
+        ));
     }
 
     #[allow(dead_code)]
     fn execute_external_diff_tool(&self, diff_content: &str) -> Result<String> {
-        self.execute_external_diff_tool_with_width(diff_content, None)
+        self.prepare_diff_tool_with_width(diff_content, None)?.run()
     }
 
-    fn execute_external_diff_tool_with_width(
+    fn prepare_diff_tool_with_width(
         &self,
         diff_content: &str,
         width: Option<u16>,
-    ) -> Result<String> {
-        let diff_command_type = self.config.get_diff_command_type();
+    ) -> Result<DiffToolJob> {
+        let diff_command_type = self.diff_command_type();
 
         match diff_command_type {
             DiffCommandType::GitDefault => {
-                Ok(diff_content.to_string()) // No processing needed
+                Ok(DiffToolJob::Ready(diff_content.to_string())) // No processing needed
             }
             DiffCommandType::Pager(ref cmd) => {
                 // Use stdin-based approach for pagers (delta, bat, ydiff, etc.)
-                self.execute_pager_with_stdin_legacy(cmd, diff_content, width)
+                self.prepare_pager_with_stdin_legacy(cmd, diff_content, width)
             }
             DiffCommandType::External(ref cmd) => {
                 // Use Git's external diff mechanism for external diff tools like difftastic
                 if let Some(w) = width {
-                    self.execute_external_diff_via_git(cmd, w.saturating_sub(2), w)
+                    self.prepare_external_diff_via_git(cmd, w.saturating_sub(2), w)
                 } else {
                     // Fallback with default widths
                     if let Ok((terminal_width, _)) = crossterm::terminal::size() {
-                        self.execute_external_diff_via_git(
+                        self.prepare_external_diff_via_git(
                             cmd,
                             terminal_width.saturating_sub(2),
                             terminal_width,
                         )
                     } else {
-                        self.execute_external_diff_via_git(cmd, 78, 80)
+                        self.prepare_external_diff_via_git(cmd, 78, 80)
                     }
                 }
             }
         }
     }
 
-    /// Common helper to execute external command with stdin input
-    fn execute_command_with_stdin(
+    /// Common helper to build an external command that reads the diff from stdin
+    fn prepare_command_with_stdin(
         &self,
         command_str: &str,
         input: &str,
         env_vars: &[(&str, String)],
-    ) -> Result<String> {
-        use std::io::Write;
-
+    ) -> Result<DiffToolJob> {
         // Parse command and arguments
         let parts: Vec<&str> = command_str.split_whitespace().collect();
         if parts.is_empty() {
@@ -276,49 +1159,22 @@ impl App {
         }
 
         // Set environment variables
-        for (key, value) in env_vars {
-            cmd.env(key, value);
-        }
-
-        cmd.stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        let mut child = cmd
-            .spawn()
-            .map_err(|e| anyhow::anyhow!("Failed to spawn {}: {}", command_name, e))?;
+        configure_child_env(&mut cmd, &self.config.git.paging, env_vars);
 
-        // Write input
-        if let Some(stdin) = child.stdin.take() {
-            let mut writer = std::io::BufWriter::new(stdin);
-            writer
-                .write_all(input.as_bytes())
-                .map_err(|e| anyhow::anyhow!("Failed to write to command: {}", e))?;
-            writer
-                .flush()
-                .map_err(|e| anyhow::anyhow!("Failed to flush command input: {}", e))?;
-        }
-
-        let output = child
-            .wait_with_output()
-            .map_err(|e| anyhow::anyhow!("Failed to read from command: {}", e))?;
-
-        if output.status.success() {
-            String::from_utf8(output.stdout)
-                .map_err(|e| anyhow::anyhow!("Command output is not valid UTF-8: {}", e))
-        } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(anyhow::anyhow!("Command failed: {}", stderr))
-        }
+        Ok(DiffToolJob::Stdin {
+            cmd,
+            name: command_name.to_string(),
+            input: input.to_string(),
+        })
     }
 
     /// Legacy pager execution for backward compatibility with existing tools
-    fn execute_pager_with_stdin_legacy(
+    fn prepare_pager_with_stdin_legacy(
         &self,
         command_str: &str,
         diff_content: &str,
         width: Option<u16>,
-    ) -> Result<String> {
+    ) -> Result<DiffToolJob> {
         // Apply template variable substitution
         let final_command_str = if let Some(w) = width {
             let content_width = w.saturating_sub(2);
@@ -337,40 +1193,40 @@ impl App {
             env_vars.push(("COLUMNS", w.to_string()));
         }
 
-        self.execute_command_with_stdin(&final_command_str, diff_content, &env_vars)
+        self.prepare_command_with_stdin(&final_command_str, diff_content, &env_vars)
     }
 
-    fn execute_external_diff_tool_with_area_width(
+    fn prepare_diff_tool_with_area_width(
         &self,
         diff_content: &str,
         area_width: u16,
         terminal_width: u16,
-    ) -> Result<String> {
-        let diff_command_type = self.config.get_diff_command_type();
+    ) -> Result<DiffToolJob> {
+        let diff_command_type = self.diff_command_type();
 
         match diff_command_type {
             DiffCommandType::GitDefault => {
-                Ok(diff_content.to_string()) // No processing needed
+                Ok(DiffToolJob::Ready(diff_content.to_string())) // No processing needed
             }
             DiffCommandType::Pager(ref cmd) => {
                 // Use stdin-based approach for pagers
-                self.execute_pager_with_stdin(cmd, diff_content, area_width, terminal_width)
+                self.prepare_pager_with_stdin(cmd, diff_content, area_width, terminal_width)
             }
             DiffCommandType::External(ref cmd) => {
                 // Use Git's external diff mechanism for external diff tools like difftastic
-                self.execute_external_diff_via_git(cmd, area_width, terminal_width)
+                self.prepare_external_diff_via_git(cmd, area_width, terminal_width)
             }
         }
     }
 
-    /// Execute pager commands via stdin (delta, bat, ydiff, etc.)
-    fn execute_pager_with_stdin(
+    /// Prepare pager commands via stdin (delta, bat, ydiff, etc.)
+    fn prepare_pager_with_stdin(
         &self,
         command_str: &str,
         diff_content: &str,
         area_width: u16,
         terminal_width: u16,
-    ) -> Result<String> {
+    ) -> Result<DiffToolJob> {
         // Apply template variable substitution with both area and terminal width
         let final_command_str = self.resolve_template_variables_with_area_width(
             command_str,
@@ -385,7 +1241,7 @@ impl App {
             ("LINES", DEFAULT_TERMINAL_HEIGHT.to_string()),
         ];
 
-        self.execute_command_with_stdin(&final_command_str, diff_content, &env_vars)
+        self.prepare_command_with_stdin(&final_command_str, diff_content, &env_vars)
     }
 
     /// Setup essential environment variables for Git external diff tools
@@ -396,20 +1252,21 @@ impl App {
         terminal_width: u16,
     ) {
         // Essential terminal environment only
-        cmd.env("TERM", DEFAULT_TERMINAL_TYPE);
-        cmd.env("COLUMNS", terminal_width.to_string());
-        cmd.env("LINES", DEFAULT_TERMINAL_HEIGHT);
+        let env_vars = [
+            ("TERM", DEFAULT_TERMINAL_TYPE.to_string()),
+            ("COLUMNS", terminal_width.to_string()),
+            ("LINES", DEFAULT_TERMINAL_HEIGHT.to_string()),
+        ];
+        configure_child_env(cmd, &self.config.git.paging, &env_vars);
     }
 
-    /// Execute external diff tools via Git's external diff mechanism
-    fn execute_external_diff_via_git(
+    /// Prepare external diff tools via Git's external diff mechanism
+    fn prepare_external_diff_via_git(
         &self,
         command_str: &str,
         area_width: u16,
         terminal_width: u16,
-    ) -> Result<String> {
-        use std::process::{Command, Stdio};
-
+    ) -> Result<DiffToolJob> {
         // Apply template variable substitution
         let final_command_str = self.resolve_template_variables_with_area_width(
             command_str,
@@ -419,47 +1276,63 @@ impl App {
 
         // Get current file path if available
         let current_items = self.get_current_file_tree_items();
-        let file_path = if let Some(tree_item) = current_items.get(self.selected_index) {
-            if !tree_item.is_directory {
-                Some(&tree_item.full_path)
-            } else {
-                None
-            }
-        } else {
-            None
-        };
+        let tree_item = current_items
+            .get(self.selected_index)
+            .filter(|tree_item| !tree_item.is_directory);
+        let file_path = tree_item.map(|tree_item| &tree_item.full_path);
+        let is_staged =
+            tree_item.is_some_and(|tree_item| tree_item.status_group == Some(StatusGroup::Staged));
 
         if file_path.is_none() {
             return Err(anyhow::anyhow!("No file selected for external diff"));
         }
 
         // Build git command using external diff mechanism (like lazygit)
-        let mut cmd = Command::new("git");
+        // diff.noprefix is left to the user's git config, as for every other git diff we run
+        let mut cmd = git_command();
         let external_diff_config = format!("diff.external={final_command_str}");
 
-        cmd.args([
-            "-c",
-            &external_diff_config,
-            "-c",
-            "diff.noprefix=false",
-            "diff",
-            "--ext-diff",
-            "--color=always",
-        ]);
+        cmd.args(["-c", &external_diff_config, "diff", "--ext-diff"]);
+        cmd.arg(format!(
+            "--color={}",
+            self.config.git.paging.resolved_color_arg()
+        ));
+        cmd.args(self.diff_options.to_args());
 
         // Add operation mode specific arguments
         match &self.operation_mode {
             OperationMode::GitWorkingDirectory => {
                 // Compare working directory with index
             }
+            OperationMode::GitStatus => {
+                if is_staged {
+                    cmd.arg("--cached");
+                }
+            }
             OperationMode::GitCached => {
                 cmd.arg("--cached");
             }
-            OperationMode::Compare { target1, target2 } => {
+            OperationMode::GitHead => {
+                cmd.arg("HEAD");
+            }
+            OperationMode::GitCachedRef { target } => {
+                cmd.arg("--cached");
+                cmd.arg(target);
+            }
+            OperationMode::Compare {
+                target1, target2, ..
+            } => {
+                if !self.compare_refs {
+                    // Paths are compared with --no-index, which also works outside a repo
+                    let (path1, path2) = no_index_paths(target1, target2, file_path.unwrap());
+                    cmd.args(["--no-index", "--", &path1, &path2]);
+                    self.setup_git_external_diff_env(&mut cmd, area_width, terminal_width);
+                    return Ok(DiffToolJob::GitExternal(cmd));
+                }
                 cmd.arg(target1);
                 cmd.arg(target2);
             }
-            OperationMode::GitDiff { target } => {
+            OperationMode::GitDiff { target, .. } => {
                 cmd.arg(target);
             }
             _ => {
@@ -476,28 +1349,18 @@ impl App {
         // Set environment variables for git and child processes
         self.setup_git_external_diff_env(&mut cmd, area_width, terminal_width);
 
-        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
-
-        let output = cmd
-            .output()
-            .map_err(|e| anyhow::anyhow!("Failed to execute git with external diff: {}", e))?;
-
-        if output.status.success() {
-            String::from_utf8(output.stdout)
-                .map_err(|e| anyhow::anyhow!("Git external diff output is not valid UTF-8: {}", e))
-        } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(anyhow::anyhow!("Git external diff failed: {}", stderr))
-        }
+        Ok(DiffToolJob::GitExternal(cmd))
     }
 
     fn scroll_up(&mut self, amount: u16) {
         self.vertical_scroll = self.vertical_scroll.saturating_sub(amount);
+        self.search_match_line = None;
         // No need to clamp here - it will be clamped in render
     }
 
     fn scroll_down(&mut self, amount: u16) {
         self.vertical_scroll = self.vertical_scroll.saturating_add(amount);
+        self.search_match_line = None;
         // No need to clamp here - it will be clamped in render
     }
 
@@ -508,71 +1371,617 @@ impl App {
 
     fn scroll_right(&mut self, amount: u16) {
         self.horizontal_scroll = self.horizontal_scroll.saturating_add(amount);
-        // No need to clamp here - it will be clamped in render
+        // Stop at the longest line right away so a huge count cannot overshoot it;
+        // render clamps again once the content has been measured
+        if let Some(max_width) = self.cached_max_width {
+            let max_horizontal_scroll = max_width.saturating_sub(self.diff_viewport_width);
+            self.horizontal_scroll = self.horizontal_scroll.min(max_horizontal_scroll);
+        }
     }
 
-    fn jump_to_top(&mut self) {
-        self.selected_index = 0;
-        self.file_list_state.select(Some(self.selected_index));
-        self.update_diff_content();
+    /// Columns scrolled by H and L: half the diff pane, so minified lines stay navigable
+    fn horizontal_page_size(&self) -> u16 {
+        (self.diff_viewport_width / 2).max(MIN_HORIZONTAL_PAGE)
     }
 
-    fn jump_to_bottom(&mut self) {
-        let current_items = self.get_current_file_tree_items();
-        if !current_items.is_empty() {
-            self.selected_index = current_items.len() - 1;
-            self.file_list_state.select(Some(self.selected_index));
-            self.update_diff_content();
-        }
+    /// Copy the hunk at the top of the diff pane as a markdown suggestion block
+    fn copy_hunk_suggestion(&mut self) {
+        let plain_output =
+            String::from_utf8_lossy(&strip_ansi_escapes::strip(&self.diff_output)).into_owned();
+        let Some(hunk) = DiffParser::hunk_at_line(&plain_output, self.top_diff_line()) else {
+            self.status_message = Some("No hunk under cursor".to_string());
+            return;
+        };
+
+        let block = DiffParser::suggestion_block(&hunk);
+        self.status_message = Some(match clipboard::copy_to_clipboard(&block) {
+            Ok(()) => "Copied suggestion to clipboard".to_string(),
+            Err(e) => format!("Failed to copy suggestion: {e}"),
+        });
     }
 
-    fn toggle_file_checked(&mut self) {
-        let current_items = if self.search_mode {
-            &self.filtered_file_tree_items
+    /// Copy the line at the top of the diff pane (or its whole hunk on a header) for a review
+    fn copy_review_comment(&mut self) {
+        let Some(path) = self
+            .get_current_file_tree_items()
+            .get(self.selected_index)
+            .filter(|item| !item.is_directory)
+            .map(|item| item.repo_path().to_string())
+        else {
+            self.status_message = Some("Select a file to quote".to_string());
+            return;
+        };
+
+        let plain_output =
+            String::from_utf8_lossy(&strip_ansi_escapes::strip(&self.diff_output)).into_owned();
+        let Some(comment) = DiffParser::review_comment(&plain_output, &path, self.top_diff_line())
+        else {
+            self.status_message = Some("No diff line under cursor".to_string());
+            return;
+        };
+
+        let location = comment.lines().next().unwrap_or_default().to_string();
+        self.status_message = Some(match clipboard::copy_to_clipboard(&comment) {
+            Ok(()) => format!("Copied {location} to clipboard"),
+            Err(e) => format!("Failed to copy review comment: {e}"),
+        });
+    }
+
+    /// Copy the selected item's path, relative to the repository or as an absolute path
+    fn copy_selected_path(&mut self, absolute: bool) {
+        let Some(relative_path) = self
+            .get_current_file_tree_items()
+            .get(self.selected_index)
+            .map(|item| item.repo_path().to_string())
+            .filter(|path| !path.is_empty())
+        else {
+            return;
+        };
+
+        let path = if absolute {
+            match self.path_base_dir() {
+                Ok(base_dir) => base_dir.join(&relative_path).to_string_lossy().into_owned(),
+                Err(e) => {
+                    self.status_message = Some(format!("Failed to resolve path: {e}"));
+                    return;
+                }
+            }
         } else {
-            &self.file_tree_items
+            relative_path
         };
 
-        if let Some(tree_item) = current_items.get(self.selected_index) {
-            // Only toggle check state for files, not directories
-            if !tree_item.is_directory {
-                let file_path = tree_item.full_path.clone();
-                let was_checked = self.checked_files.contains(&file_path);
+        let form = if absolute { "absolute" } else { "relative" };
+        self.status_message = Some(match clipboard::copy_to_clipboard(&path) {
+            Ok(()) => format!("Copied {form} path: {path}"),
+            Err(e) => format!("Failed to copy path: {e}"),
+        });
+    }
 
-                if was_checked {
-                    self.checked_files.remove(&file_path);
-                } else {
-                    self.checked_files.insert(file_path.clone());
+    /// Build the `git difftool` invocation for the selected file
+    fn difftool_command(&self) -> Result<Command> {
+        let item = self
+            .get_current_file_tree_items()
+            .get(self.selected_index)
+            .filter(|item| !item.is_directory)
+            .ok_or_else(|| anyhow::anyhow!("Select a file to open in the difftool"))?;
+        let path = item.full_path.clone();
+        let is_staged = item.status_group == Some(StatusGroup::Staged);
+
+        let mut cmd = git_command();
+        cmd.args(["difftool", "--no-prompt"]);
+        if let Some(tool) = &self.config.git.difftool {
+            cmd.arg(format!("--tool={tool}"));
+        }
+
+        match &self.operation_mode {
+            OperationMode::GitWorkingDirectory => {}
+            OperationMode::GitStatus => {
+                if is_staged {
+                    cmd.arg("--cached");
                 }
+            }
+            OperationMode::GitCached => {
+                cmd.arg("--cached");
+            }
+            OperationMode::GitHead => {
+                cmd.arg("HEAD");
+            }
+            OperationMode::GitCachedRef { target } => {
+                cmd.args(["--cached", target]);
+            }
+            OperationMode::GitDiff { target, .. } => {
+                cmd.arg(target);
+            }
+            OperationMode::Compare {
+                target1, target2, ..
+            } if self.revision_label.is_some() => {
+                cmd.args([target1, target2]);
+            }
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "The difftool is only available when diffing git revisions"
+                ));
+            }
+        }
 
-                // Save to persistence if we have a diff key
-                if let Some(file_diff) = tree_item.file_diff.as_ref() {
-                    if let Some(diff_key) = &file_diff.diff_key {
-                        let is_now_checked = !was_checked;
-                        if let Err(e) = self
-                            .persistence_manager
-                            .save_check_state(diff_key, is_now_checked)
-                        {
-                            eprintln!("Warning: Failed to save check state: {e}");
-                        }
-                    }
+        cmd.arg("--").arg(path);
+        Ok(cmd)
+    }
+
+    /// Get the directory that file paths in the diff are relative to
+    fn path_base_dir(&self) -> Result<std::path::PathBuf> {
+        let repo_relative = match &self.operation_mode {
+            OperationMode::PatchFile { .. } => false,
+            // Only ref comparisons have repository paths; file comparisons use the given paths
+            OperationMode::Compare { .. } => self.revision_label.is_some(),
+            _ => true,
+        };
+
+        if repo_relative {
+            if let Ok(root) = GitExecutor::repo_root() {
+                return Ok(root);
+            }
+        }
+        Ok(std::env::current_dir()?)
+    }
+
+    fn grow_sidebar(&mut self) {
+        self.sidebar_width = (self.sidebar_width + SIDEBAR_RESIZE_STEP).min(MAX_SIDEBAR_WIDTH);
+    }
+
+    fn shrink_sidebar(&mut self) {
+        self.sidebar_width = self
+            .sidebar_width
+            .saturating_sub(SIDEBAR_RESIZE_STEP)
+            .max(MIN_SIDEBAR_WIDTH);
+    }
+
+    /// Resize the sidebar by dragging the border between the file list and the diff pane
+    fn handle_sidebar_drag(&mut self, kind: MouseEventKind, column: u16) {
+        match kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                // Both panes draw a border here: the file list's right and the diff's left
+                self.dragging_sidebar = self.sidebar_border > 0
+                    && (column == self.sidebar_border || column + 1 == self.sidebar_border);
+            }
+            MouseEventKind::Drag(MouseButton::Left)
+                if self.dragging_sidebar && self.layout_width > 0 =>
+            {
+                let percentage =
+                    (column as u32 * 100 + self.layout_width as u32 / 2) / self.layout_width as u32;
+                self.sidebar_width =
+                    (percentage as u16).clamp(MIN_SIDEBAR_WIDTH, MAX_SIDEBAR_WIDTH);
+            }
+            MouseEventKind::Up(MouseButton::Left) => self.dragging_sidebar = false,
+            _ => {}
+        }
+    }
+
+    fn jump_to_top(&mut self) {
+        self.selected_index = 0;
+        self.file_list_state.select(Some(self.selected_index));
+        self.update_diff_content();
+    }
+
+    fn jump_to_bottom(&mut self) {
+        let current_items = self.get_current_file_tree_items();
+        if !current_items.is_empty() {
+            self.selected_index = current_items.len() - 1;
+            self.file_list_state.select(Some(self.selected_index));
+            self.update_diff_content();
+        }
+    }
+
+    /// Toggle the check state of the selected file, or of every file under a directory
+    ///
+    /// A directory checks all its files unless they are all checked already, in which case
+    /// it unchecks them.
+    fn toggle_file_checked(&mut self) {
+        let current_items = if self.search_mode {
+            &self.filtered_file_tree_items
+        } else {
+            &self.file_tree_items
+        };
+        let Some(tree_item) = current_items.get(self.selected_index) else {
+            return;
+        };
+        if self.config.ui.check_flash_ms > 0 {
+            self.check_flash = Some((tree_item.full_path.clone(), std::time::Instant::now()));
+        }
+
+        let files: Vec<(String, Option<DiffFileKey>)> = if tree_item.is_directory {
+            self.file_tree
+                .files_under(&tree_item.full_path)
+                .into_iter()
+                .map(|(path, file_diff)| (path.to_string(), file_diff.diff_key.clone()))
+                .collect()
+        } else {
+            let diff_key = tree_item
+                .file_diff
+                .as_ref()
+                .and_then(|file_diff| file_diff.diff_key.clone());
+            vec![(tree_item.full_path.clone(), diff_key)]
+        };
+        let check = !files
+            .iter()
+            .all(|(path, _)| self.checked_files.contains(path));
+
+        for (file_path, diff_key) in files {
+            if check {
+                self.checked_files.insert(file_path.clone());
+            } else {
+                self.checked_files.remove(&file_path);
+            }
+            // The whole file is now either reviewed or not
+            self.checked_hunks.remove(&file_path);
+
+            // Save to persistence if we have a diff key
+            if let Some(diff_key) = &diff_key {
+                if let Err(e) = self.persistence_manager.save_check_state(diff_key, check) {
+                    self.status_message = Some(format!("Failed to save check state: {e}"));
                 }
             }
         }
     }
 
+    /// Show an error in a dialog over the UI until the next key press
+    fn show_error(&mut self, error: &anyhow::Error) {
+        self.error_dialog = Some(format!("{error:#}"));
+    }
+
+    /// Check if a row is still flashing after its check mark was toggled
+    fn is_check_flashing(&self, path: &str) -> bool {
+        let duration = std::time::Duration::from_millis(self.config.ui.check_flash_ms);
+        self.check_flash
+            .as_ref()
+            .is_some_and(|(flashed, since)| flashed == path && since.elapsed() < duration)
+    }
+
+    /// Get the checkbox state of a file, or the aggregate state of a directory's files
+    fn check_mark(&self, tree_item: &FileTreeItem) -> CheckMark {
+        let files: Vec<&str> = if tree_item.is_directory {
+            self.file_tree
+                .files_under(&tree_item.full_path)
+                .into_iter()
+                .map(|(path, _)| path)
+                .collect()
+        } else {
+            vec![tree_item.full_path.as_str()]
+        };
+
+        let checked = files
+            .iter()
+            .filter(|path| self.checked_files.contains(**path))
+            .count();
+        if !files.is_empty() && checked == files.len() {
+            CheckMark::Checked
+        } else if checked > 0 || files.iter().any(|path| self.is_partially_checked(path)) {
+            CheckMark::Partial
+        } else {
+            CheckMark::Unchecked
+        }
+    }
+
+    /// Check whether some, but not all, hunks of a file are checked
+    fn is_partially_checked(&self, file_path: &str) -> bool {
+        !self.checked_files.contains(file_path)
+            && self
+                .checked_hunks
+                .get(file_path)
+                .is_some_and(|hunks| !hunks.is_empty())
+    }
+
+    /// Check whether the hunk with the given header is checked in a file
+    fn is_hunk_checked(&self, file_path: &str, header: &str) -> bool {
+        self.checked_files.contains(file_path)
+            || self
+                .checked_hunks
+                .get(file_path)
+                .is_some_and(|hunks| hunks.contains(&hunk_id(header)))
+    }
+
+    /// Toggle the check state of the hunk at the top of the diff pane
+    ///
+    /// Checking the last unchecked hunk checks the whole file, and unchecking a hunk of a
+    /// checked file leaves the file partially checked.
+    fn toggle_hunk_checked(&mut self) {
+        if self.overlay.is_some() {
+            return;
+        }
+        let Some(tree_item) = self
+            .get_current_file_tree_items()
+            .get(self.selected_index)
+            .filter(|tree_item| !tree_item.is_directory)
+        else {
+            return;
+        };
+        let file_path = tree_item.full_path.clone();
+        let diff_key = tree_item
+            .file_diff
+            .as_ref()
+            .and_then(|file_diff| file_diff.diff_key.clone());
+
+        let plain_output = strip_ansi_escapes::strip_str(&self.diff_output);
+        let Some(header) = DiffParser::hunk_header_at_line(&plain_output, self.top_diff_line())
+        else {
+            self.status_message = Some("No hunk under cursor".to_string());
+            return;
+        };
+        let toggled = hunk_id(header);
+        let all_hunks: Vec<String> = DiffParser::hunk_headers(&plain_output)
+            .into_iter()
+            .map(hunk_id)
+            .collect();
+
+        let mut hunks = if self.checked_files.contains(&file_path) {
+            all_hunks.iter().cloned().collect()
+        } else {
+            self.checked_hunks
+                .get(&file_path)
+                .cloned()
+                .unwrap_or_default()
+        };
+        if !hunks.remove(&toggled) {
+            hunks.insert(toggled);
+        }
+
+        let checked_count = all_hunks.iter().filter(|id| hunks.contains(*id)).count();
+        let file_checked = checked_count == all_hunks.len();
+        let saved = if file_checked {
+            self.checked_files.insert(file_path.clone());
+            self.checked_hunks.remove(&file_path);
+            diff_key.map(|key| self.persistence_manager.save_check_state(&key, true))
+        } else {
+            self.checked_files.remove(&file_path);
+            let saved =
+                diff_key.map(|key| self.persistence_manager.save_checked_hunks(&key, &hunks));
+            self.checked_hunks.insert(file_path, hunks);
+            saved
+        };
+
+        self.status_message = Some(match saved {
+            Some(Err(e)) => format!("Failed to save check state: {e}"),
+            _ => format!("{checked_count}/{} hunks checked", all_hunks.len()),
+        });
+    }
+
     fn get_current_file_tree_items(&self) -> &Vec<FileTreeItem> {
         if self.search_mode {
             &self.filtered_file_tree_items
+        } else {
+            self.get_browse_items()
+        }
+    }
+
+    /// Get the items shown outside search mode: the tree, or only its files
+    fn get_browse_items(&self) -> &Vec<FileTreeItem> {
+        if self.files_only {
+            &self.file_only_items
         } else {
             &self.file_tree_items
         }
     }
 
+    /// Check whether file labels are full paths rather than basenames
+    ///
+    /// Unless set explicitly, only the files-only list shows full paths.
+    fn shows_full_paths(&self) -> bool {
+        self.full_paths.unwrap_or(self.files_only)
+    }
+
+    /// Switch file labels between full paths and basenames
+    fn toggle_full_paths(&mut self) {
+        self.full_paths = Some(!self.shows_full_paths());
+    }
+
+    /// Toggle keeping the vertical scroll offset when switching files
+    fn toggle_sticky_scroll(&mut self) {
+        self.sticky_scroll = !self.sticky_scroll;
+        self.status_message = Some(format!(
+            "Sticky scroll {}",
+            if self.sticky_scroll { "on" } else { "off" }
+        ));
+    }
+
+    /// Toggle showing the selected file's whole content around its changes
+    fn toggle_whole_file_context(&mut self) {
+        let Some(item) = self
+            .get_current_file_tree_items()
+            .get(self.selected_index)
+            .filter(|item| !item.is_directory)
+        else {
+            return;
+        };
+        let untracked = item
+            .file_diff
+            .as_ref()
+            .is_some_and(|file_diff| file_diff.is_untracked);
+        if self.piped_input
+            || self.git_executor.is_none()
+            || untracked
+            || self.shows_file_contents()
+        {
+            self.status_message =
+                Some("Whole-file context needs a diff refetched from git".to_string());
+            return;
+        }
+
+        let path = item.full_path.clone();
+        let expanded = if self.whole_file_context.remove(&path) {
+            false
+        } else {
+            self.whole_file_context.insert(path);
+            true
+        };
+        self.update_diff_content();
+        self.status_message = Some(
+            if expanded {
+                "Showing the whole file"
+            } else {
+                "Showing normal context"
+            }
+            .to_string(),
+        );
+    }
+
+    /// Check if the selected file is diffed with the whole file as context
+    fn shows_whole_file_context(&self) -> bool {
+        self.overlay.is_none()
+            && self
+                .get_current_file_tree_items()
+                .get(self.selected_index)
+                .is_some_and(|item| self.whole_file_context.contains(&item.full_path))
+    }
+
+    /// Check if context lines are hidden from the diff pane
+    ///
+    /// Diff tools reformat their output, so only git's own diff lines can be filtered.
+    fn filters_changed_lines(&self) -> bool {
+        self.changed_lines_only
+            && matches!(self.diff_command_type(), DiffCommandType::GitDefault)
+            && !self.shows_file_contents()
+    }
+
+    /// Toggle hiding context lines, showing only added and removed lines and hunk headers
+    fn toggle_changed_lines_only(&mut self) {
+        self.changed_lines_only = !self.changed_lines_only;
+        // The filtered lines are measured along with the line widths
+        self.cached_max_width = None;
+        self.vertical_scroll = 0;
+        self.status_message = Some(
+            if !matches!(self.diff_command_type(), DiffCommandType::GitDefault) {
+                "Changed-lines-only mode only applies to git's own diff output"
+            } else if self.changed_lines_only {
+                "Showing changed lines only"
+            } else {
+                "Showing the full diff"
+            }
+            .to_string(),
+        );
+    }
+
+    /// Get the index in the diff output of the line at the top of the diff pane
+    fn top_diff_line(&self) -> usize {
+        let line = self.line_at_row(self.vertical_scroll as usize);
+        match &self.visible_lines {
+            Some(visible) if self.filters_changed_lines() => {
+                visible.get(line).copied().unwrap_or(line)
+            }
+            _ => line,
+        }
+    }
+
+    /// Get the index among the measured lines of the line shown on a row of the diff pane
+    ///
+    /// Long lines wrap onto several rows, so rows and lines only match for short lines.
+    /// Until `clamp_scroll` has measured the content, each line is taken to fill one row.
+    fn line_at_row(&self, row: usize) -> usize {
+        let row = row.saturating_sub(self.diff_prefix_rows);
+        if self.cached_max_width.is_none() {
+            return row;
+        }
+        let mut rows = 0;
+        for (line, &width) in self.cached_line_widths.iter().enumerate() {
+            rows += wrapped_row_count(width, self.diff_viewport_width);
+            if rows > row {
+                return line;
+            }
+        }
+        self.cached_line_widths.len() + (row - rows)
+    }
+
+    /// Get the diff pane row a measured line starts on, the inverse of `line_at_row`
+    fn row_of_line(&self, line: usize) -> usize {
+        if self.cached_max_width.is_none() {
+            return self.diff_prefix_rows + line;
+        }
+        let measured = line.min(self.cached_line_widths.len());
+        let rows: usize = self.cached_line_widths[..measured]
+            .iter()
+            .map(|&width| wrapped_row_count(width, self.diff_viewport_width))
+            .sum();
+        self.diff_prefix_rows + rows + (line - measured)
+    }
+
+    /// Get the diff tool in use, honoring a session override of the configured one
+    fn diff_command_type(&self) -> DiffCommandType {
+        self.override_diff_type
+            .clone()
+            .unwrap_or_else(|| self.config.get_diff_command_type())
+    }
+
+    /// Switch between the configured diff tool and git's own output for this session
+    fn toggle_raw_diff(&mut self) {
+        if matches!(
+            self.config.get_diff_command_type(),
+            DiffCommandType::GitDefault
+        ) {
+            self.status_message = Some("No diff tool configured".to_string());
+            return;
+        }
+
+        self.override_diff_type = match self.override_diff_type {
+            Some(_) => None,
+            None => Some(DiffCommandType::GitDefault),
+        };
+
+        // Git's output is displayed directly without a tool, so color it like at startup
+        let raw = self.override_diff_type.is_some();
+        self.diff_options.color =
+            raw.then(|| self.config.git.paging.resolved_color_arg().to_string());
+        self.rebuild_git_executor();
+
+        self.update_diff_content();
+        self.status_message = Some(format!(
+            "Showing {}",
+            self.diff_command_type().display_name()
+        ));
+    }
+
+    /// Toggle highlighting whitespace errors on added lines
+    fn toggle_highlight_whitespace(&mut self) {
+        self.highlight_whitespace = !self.highlight_whitespace;
+        self.status_message = Some(
+            if !matches!(self.diff_command_type(), DiffCommandType::GitDefault) {
+                "Whitespace highlighting only applies to git's own diff output".to_string()
+            } else if self.highlight_whitespace {
+                "Whitespace highlighting on".to_string()
+            } else {
+                "Whitespace highlighting off".to_string()
+            },
+        );
+    }
+
+    /// Toggle hiding directory rows, keeping the selected file selected
+    fn toggle_files_only(&mut self) {
+        let selected_path = self
+            .get_current_file_tree_items()
+            .get(self.selected_index)
+            .map(FileTreeItem::tree_path);
+
+        self.files_only = !self.files_only;
+        if self.search_mode {
+            self.update_search_filter();
+        }
+
+        let current_items = self.get_current_file_tree_items();
+        let index = selected_path
+            .and_then(|path| {
+                current_items
+                    .iter()
+                    .position(|item| item.tree_path() == path)
+            })
+            .unwrap_or(0);
+        self.selected_index = index;
+        self.file_list_state.select(Some(index));
+        self.update_diff_content();
+    }
+
     fn enter_search_mode(&mut self) {
         if self.search_mode {
             // Already in search mode, clear query and start fresh input
             self.search_query.clear();
+            self.search_history_index = None;
             self.search_input_mode = true;
             self.selected_index = 0;
             self.file_list_state.select(Some(self.selected_index));
@@ -582,6 +1991,7 @@ impl App {
             self.search_mode = true;
             self.search_input_mode = true;
             self.search_query.clear();
+            self.search_history_index = None;
             self.selected_index = 0;
             self.file_list_state.select(Some(self.selected_index));
             self.update_search_filter();
@@ -599,13 +2009,23 @@ impl App {
 
     fn confirm_search(&mut self) {
         self.search_input_mode = false;
+        self.search_history_index = None;
         // Keep search_mode = true to show filtered results
         // But allow navigation with hjkl
+
+        if !self.search_query.is_empty() {
+            push_search_history(&mut self.search_history, &self.search_query);
+            // History is a convenience, so a failed write is not worth interrupting for
+            let _ = self
+                .persistence_manager
+                .save_search_history(&self.search_history);
+        }
     }
 
     fn add_search_char(&mut self, c: char) {
         if self.search_input_mode {
             self.search_query.push(c);
+            self.search_history_index = None;
             self.update_search_filter();
         }
     }
@@ -613,22 +2033,73 @@ impl App {
     fn remove_search_char(&mut self) {
         if self.search_input_mode && !self.search_query.is_empty() {
             self.search_query.pop();
+            self.search_history_index = None;
             self.update_search_filter();
         }
     }
 
+    /// Replace the query with the previous (older) history entry
+    fn recall_previous_search(&mut self) {
+        if self.search_history.is_empty() {
+            return;
+        }
+
+        let index = match self.search_history_index {
+            None => self.search_history.len() - 1,
+            Some(index) => index.saturating_sub(1),
+        };
+        self.search_history_index = Some(index);
+        self.search_query = self.search_history[index].clone();
+        self.update_search_filter();
+    }
+
+    /// Replace the query with the next (newer) history entry, or clear it past the newest
+    fn recall_next_search(&mut self) {
+        let Some(index) = self.search_history_index else {
+            return;
+        };
+
+        if index + 1 < self.search_history.len() {
+            self.search_history_index = Some(index + 1);
+            self.search_query = self.search_history[index + 1].clone();
+        } else {
+            self.search_history_index = None;
+            self.search_query.clear();
+        }
+        self.update_search_filter();
+    }
+
     fn update_search_filter(&mut self) {
         if self.search_query.is_empty() {
-            self.filtered_file_tree_items = self.file_tree_items.clone();
+            self.filtered_file_tree_items = self.get_browse_items().clone();
         } else {
-            // Simple fuzzy matching - each character in query should appear in order
-            self.filtered_file_tree_items = self
-                .file_tree_items
+            // Fuzzy match every path and rank the best matches first
+            let mut scored: Vec<(i64, &FileTreeItem)> = self
+                .get_browse_items()
                 .iter()
-                .filter(|item| self.fuzzy_match(&item.full_path, &self.search_query))
-                .cloned()
+                .filter_map(|item| {
+                    self.fuzzy_matcher
+                        .fuzzy_match(&item.full_path, &self.search_query)
+                        .map(|score| (score, item))
+                })
                 .collect();
+            // Stable sort keeps tree order among equal scores
+            scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+            self.filtered_file_tree_items =
+                scored.into_iter().map(|(_, item)| item.clone()).collect();
         }
+        self.content_match_files = self
+            .filtered_file_tree_items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| {
+                item.file_diff.as_ref().is_some_and(|file_diff| {
+                    !DiffParser::search_match_lines(&file_diff.content, &self.search_query)
+                        .is_empty()
+                })
+            })
+            .map(|(index, _)| index)
+            .collect();
 
         // Reset selection and update diff content
         self.selected_index = 0;
@@ -636,12 +2107,135 @@ impl App {
         self.update_diff_content();
     }
 
-    fn fuzzy_match(&self, text: &str, pattern: &str) -> bool {
-        // Simple substring matching like diffnav
-        text.to_lowercase().contains(&pattern.to_lowercase())
+    /// Get the search query while a search filters the file list
+    fn active_search_query(&self) -> Option<&str> {
+        Some(self.search_query.as_str()).filter(|query| self.search_mode && !query.is_empty())
+    }
+
+    /// Get the lines of the diff pane that contain the search query
+    fn search_match_lines(&self) -> Vec<usize> {
+        self.active_search_query()
+            .map(|query| DiffParser::search_match_lines(&self.diff_output, query))
+            .unwrap_or_default()
+    }
+
+    /// Select the next file (or previous, going back) of the filtered set whose diff contains
+    /// the search query, and scroll to its first match
+    fn jump_to_search_match_file(&mut self, forward: bool, count: u16) {
+        let Some(query) = self.active_search_query() else {
+            self.status_message = Some("Search with / to jump between matches".to_string());
+            return;
+        };
+        let selected = self.selected_index;
+        let candidates = self.content_match_files.iter().copied();
+        let target = if forward {
+            candidates
+                .filter(|&index| index > selected)
+                .nth(count as usize - 1)
+        } else {
+            candidates
+                .rev()
+                .filter(|&index| index < selected)
+                .nth(count as usize - 1)
+        };
+        let Some(index) = target else {
+            self.status_message = Some(format!(
+                "No {} file contains '{query}'",
+                if forward { "later" } else { "earlier" }
+            ));
+            return;
+        };
+
+        self.selected_index = index;
+        self.file_list_state.select(Some(index));
+        self.update_diff_content();
+        if let Some(&line) = self.search_match_lines().first() {
+            self.scroll_to_search_match(line);
+        }
+    }
+
+    /// Scroll to the next match (or previous, going back) of the search query in this file
+    fn jump_to_search_match_line(&mut self, forward: bool, count: u16) {
+        if self.active_search_query().is_none() {
+            self.status_message = Some("Search with / to jump between matches".to_string());
+            return;
+        }
+        let matches = self.search_match_lines();
+        let current = self.search_match_line;
+        let top = self.top_diff_line();
+        let target = if forward {
+            matches
+                .iter()
+                .filter(|&&line| current.map_or(line >= top, |current| line > current))
+                .nth(count as usize - 1)
+        } else {
+            matches
+                .iter()
+                .rev()
+                .filter(|&&line| line < current.unwrap_or(top))
+                .nth(count as usize - 1)
+        };
+        match target {
+            Some(&line) => self.scroll_to_search_match(line),
+            None => {
+                self.status_message = Some(format!(
+                    "No {} match in this file",
+                    if forward { "later" } else { "earlier" }
+                ))
+            }
+        }
+    }
+
+    /// Scroll so a matching line of the diff output is at the top, and remember it
+    fn scroll_to_search_match(&mut self, line: usize) {
+        // Lines hidden by changed-lines-only mode are not measured, so count the shown ones
+        let measured = match &self.visible_lines {
+            Some(visible) if self.filters_changed_lines() => {
+                visible.partition_point(|&shown| shown < line)
+            }
+            _ => line,
+        };
+        self.scroll_to_line(measured.min(u16::MAX as usize) as u16);
+        self.search_match_line = Some(line);
+    }
+
+    /// Describe where the selected file and the top of the diff are among the search matches,
+    /// e.g. "file 2/5 with matches, line 3/8 in this file"
+    fn search_match_summary(&self) -> Option<String> {
+        let query = self.active_search_query()?;
+        if self.content_match_files.is_empty() {
+            return Some(format!("No file contains '{query}'"));
+        }
+        let files = self.content_match_files.len();
+        let Some(file) = self
+            .content_match_files
+            .iter()
+            .position(|&index| index == self.selected_index)
+        else {
+            return Some(format!("{files} files with matches"));
+        };
+
+        let matches = self.search_match_lines();
+        let current = self
+            .search_match_line
+            .unwrap_or_else(|| self.top_diff_line());
+        let line = matches
+            .iter()
+            .position(|&line| line >= current)
+            .unwrap_or(matches.len().saturating_sub(1));
+        Some(format!(
+            "file {}/{files} with matches, line {}/{} in this file",
+            file + 1,
+            (line + 1).min(matches.len()),
+            matches.len()
+        ))
     }
 
     fn toggle_directory(&mut self) {
+        // Directory rows are hidden, so there is nothing to toggle
+        if self.files_only {
+            return;
+        }
         if let Some(tree_item) = self.file_tree_items.get(self.selected_index) {
             if tree_item.is_directory {
                 let path = tree_item.full_path.clone();
@@ -656,51 +2250,300 @@ impl App {
         }
     }
 
-    fn rebuild_file_tree(&mut self) {
-        // Use original file diffs instead of extracting from current items
-        self.file_tree_items = FileTreeBuilder::build_file_tree_with_collapsed(
-            &self.original_file_diffs,
-            &self.collapsed_directories,
-        );
+    fn collapse_all_directories(&mut self) {
+        self.collapsed_directories = FileTreeBuilder::directory_paths(&self.original_file_diffs);
+        self.rebuild_file_tree_preserving_selection();
+    }
 
-        // Adjust selected index if needed
-        if self.selected_index >= self.file_tree_items.len() {
-            self.selected_index = self.file_tree_items.len().saturating_sub(1);
-            self.file_list_state.select(Some(self.selected_index));
+    fn expand_all_directories(&mut self) {
+        self.collapsed_directories.clear();
+        self.rebuild_file_tree_preserving_selection();
+    }
+
+    fn toggle_all_directories(&mut self) {
+        if self.collapsed_directories.is_empty() {
+            self.collapse_all_directories();
+        } else {
+            self.expand_all_directories();
         }
     }
 
-    /// Refresh diff output with specific width for side-by-side display
-    fn refresh_diff_with_width(&mut self, width: u16) {
-        // Re-execute diff tool with the new width for proper side-by-side alignment
-        match self.config.get_diff_command_type() {
-            DiffCommandType::GitDefault => {
-                // No processing needed for default git diff
-            }
-            DiffCommandType::Pager(_) | DiffCommandType::External(_) => {
-                let current_items = self.get_current_file_tree_items();
-                if let Some(tree_item) = current_items.get(self.selected_index) {
-                    if let Some(file_diff) = &tree_item.file_diff {
-                        // Get fresh diff content for the current file
-                        let base_diff = if let Some(ref git_executor) = self.git_executor {
-                            match git_executor
-                                .get_file_diff(&self.operation_mode, &tree_item.full_path)
-                            {
-                                Ok(fresh_diff) => fresh_diff,
-                                Err(_) => file_diff.content.clone(),
-                            }
-                        } else {
-                            file_diff.content.clone()
-                        };
+    /// Rebuild the tree, keeping the selected item or its nearest visible ancestor selected
+    fn rebuild_file_tree_preserving_selection(&mut self) {
+        // The selection points into the file-only list, which collapsing does not change
+        if self.files_only {
+            self.rebuild_file_tree();
+            return;
+        }
 
-                        // Apply diff tool with width
-                        match self.execute_external_diff_tool_with_width(&base_diff, Some(width)) {
-                            Ok(processed_output) => {
-                                self.diff_output = processed_output;
-                            }
-                            Err(e) => {
-                                eprintln!("Warning: Failed to refresh diff with width: {e}");
-                            }
+        let selected_path = self
+            .file_tree_items
+            .get(self.selected_index)
+            .map(FileTreeItem::tree_path);
+
+        self.rebuild_file_tree();
+
+        if let Some(path) = selected_path {
+            let mut candidate = Some(path.as_str());
+            while let Some(current) = candidate {
+                if let Some(index) = self
+                    .file_tree_items
+                    .iter()
+                    .position(|item| item.tree_path() == current)
+                {
+                    self.selected_index = index;
+                    self.file_list_state.select(Some(index));
+                    if current != path {
+                        self.update_diff_content();
+                    }
+                    return;
+                }
+                candidate = current.rsplit_once('/').map(|(parent, _)| parent);
+            }
+        }
+    }
+
+    /// Re-run the diff for the current operation mode and show the fresh results
+    fn reload(&mut self) {
+        if self.piped_input {
+            self.status_message = Some("Cannot reload a diff read from stdin".to_string());
+            return;
+        }
+
+        self.status_message = Some(match self.refetch() {
+            Ok(count) => format!("Reloaded {count} files"),
+            Err(e) => format!("Reload failed: {e}"),
+        });
+    }
+
+    /// Refetch the diff in watch mode once the interval has passed, keeping the view in place
+    ///
+    /// The tree is only rebuilt when the fetched diffs differ from the ones shown.
+    fn poll_watch(&mut self) {
+        let Some(interval) = self.watch_interval else {
+            return;
+        };
+        if self.last_watch_check.elapsed() < interval {
+            return;
+        }
+        self.last_watch_check = std::time::Instant::now();
+
+        // Leave prompts and the ref range overlay alone until they are closed
+        if self.search_input_mode || self.prompt.is_some() || self.overlay.is_some() {
+            return;
+        }
+        let Ok(mut file_diffs) = get_diffs_from_git(&self.operation_mode, &self.config) else {
+            return;
+        };
+        // Keep showing only the first files if that was chosen at startup
+        if let Some(max_files) = self.config.ui.max_files {
+            file_diffs.truncate(max_files);
+        }
+        if diff_fingerprint(&file_diffs) == self.diff_fingerprint {
+            return;
+        }
+
+        let selected_path = |app: &App| {
+            app.get_current_file_tree_items()
+                .get(app.selected_index)
+                .map(FileTreeItem::tree_path)
+        };
+        let previous_path = selected_path(self);
+        let (vertical_scroll, horizontal_scroll) = (self.vertical_scroll, self.horizontal_scroll);
+        self.replace_file_diffs(file_diffs);
+
+        // Stay at the same spot in a file that is still selected; render clamps the offsets
+        if selected_path(self) == previous_path {
+            self.vertical_scroll = vertical_scroll;
+            self.horizontal_scroll = horizontal_scroll;
+        }
+    }
+
+    /// Fetch the diffs for the current operation mode again, returning the file count
+    fn refetch(&mut self) -> Result<usize> {
+        let file_diffs = match &self.operation_mode {
+            OperationMode::PatchFile { path } => read_patch_file(path)?,
+            mode => get_diffs_from_git(mode, &self.config)?,
+        };
+
+        let count = file_diffs.len();
+        self.replace_file_diffs(file_diffs);
+        Ok(count)
+    }
+
+    /// Swap the sides of a two-target comparison or ref diff, then refetch
+    ///
+    /// Compare mode swaps its targets, while a ref diff against the working tree is reversed
+    /// with `git diff -R`.
+    fn reverse_comparison(&mut self) {
+        if self.piped_input {
+            self.status_message = Some("Cannot reverse a diff read from stdin".to_string());
+            return;
+        }
+
+        match &mut self.operation_mode {
+            OperationMode::Compare {
+                target1, target2, ..
+            } => std::mem::swap(target1, target2),
+            OperationMode::GitDiff { .. } | OperationMode::GitHead => {
+                self.config.git.reverse = !self.config.git.reverse;
+                self.diff_options.reverse = self.config.git.reverse;
+                self.rebuild_git_executor();
+            }
+            _ => {
+                self.status_message =
+                    Some("Only comparisons of two targets or a ref can be reversed".to_string());
+                return;
+            }
+        }
+        self.reversed = !self.reversed;
+        self.revision_label = revision_label_for(&self.operation_mode, &self.diff_options);
+
+        self.status_message = Some(match self.refetch() {
+            Ok(_) if self.reversed => "Reversed the comparison".to_string(),
+            Ok(_) => "Restored the comparison direction".to_string(),
+            Err(e) => format!("Reverse failed: {e}"),
+        });
+    }
+
+    /// Toggle leaving files that match `ui.hidePatterns` out of the tree
+    fn toggle_hidden_files(&mut self) {
+        if self.hide_patterns.is_none() {
+            self.status_message = Some("No ui.hidePatterns configured".to_string());
+            return;
+        }
+
+        self.hiding_files = !self.hiding_files;
+        let file_diffs = std::mem::take(&mut self.original_file_diffs);
+        self.replace_file_diffs(file_diffs);
+        self.status_message = Some(if self.hiding_files {
+            format!(
+                "Hiding {} files matching ui.hidePatterns",
+                self.hidden_count
+            )
+        } else {
+            "Showing files matching ui.hidePatterns".to_string()
+        });
+    }
+
+    /// Cycle the diff filter between all files and only added, modified, deleted or renamed
+    /// files, then refetch
+    ///
+    /// A custom `--diff-filter` from the command line goes back to showing all files.
+    fn cycle_diff_filter(&mut self) {
+        if self.piped_input {
+            self.status_message = Some("Cannot filter a diff read from stdin".to_string());
+            return;
+        }
+
+        let position = DIFF_FILTER_CYCLE
+            .iter()
+            .position(|filter| *filter == self.config.git.diff_filter.as_deref());
+        let next = match position {
+            Some(index) => DIFF_FILTER_CYCLE[(index + 1) % DIFF_FILTER_CYCLE.len()],
+            None => None,
+        };
+        self.config.git.diff_filter = next.map(str::to_string);
+        self.diff_options.diff_filter = self.config.git.diff_filter.clone();
+        self.rebuild_git_executor();
+
+        self.status_message = Some(match self.refetch() {
+            Ok(count) => match next {
+                Some(filter) => format!("Showing {count} {} files", describe_diff_filter(filter)),
+                None => format!("Showing all {count} files"),
+            },
+            Err(e) => format!("Filter failed: {e}"),
+        });
+    }
+
+    /// Swap in new file diffs, keeping the selected file and collapsed directories
+    fn replace_file_diffs(&mut self, file_diffs: Vec<FileDiff>) {
+        let selected_path = self
+            .get_current_file_tree_items()
+            .get(self.selected_index)
+            .map(FileTreeItem::tree_path);
+        self.diff_fingerprint = diff_fingerprint(&file_diffs);
+
+        let diff_keys: Vec<DiffFileKey> = file_diffs
+            .iter()
+            .filter_map(|fd| fd.diff_key.clone())
+            .collect();
+        self.checked_files = self
+            .persistence_manager
+            .load_checked_files(&diff_keys)
+            .unwrap_or_default();
+        self.checked_hunks = self
+            .persistence_manager
+            .load_checked_hunks(&diff_keys)
+            .unwrap_or_default();
+
+        self.original_file_diffs = file_diffs;
+        let shown_diffs = visible_file_diffs(
+            &self.original_file_diffs,
+            self.hide_patterns.as_ref().filter(|_| self.hiding_files),
+        );
+        self.hidden_count = self.original_file_diffs.len() - shown_diffs.len();
+        self.file_tree = FileTree::build_sorted(shown_diffs, self.config.ui.tree_sort);
+        self.file_only_items = self.file_tree.file_list();
+        self.rebuild_file_tree();
+        if self.search_mode {
+            self.update_search_filter();
+        }
+
+        let current_items = self.get_current_file_tree_items();
+        if current_items.is_empty() {
+            self.overlay = None;
+            self.pending_diff = None;
+            self.set_diff_output(String::from("No differences found."));
+            self.vertical_scroll = 0;
+            self.horizontal_scroll = 0;
+            return;
+        }
+
+        let index = selected_path
+            .and_then(|path| {
+                current_items
+                    .iter()
+                    .position(|item| item.tree_path() == path)
+            })
+            .unwrap_or(self.selected_index.min(current_items.len() - 1));
+        self.selected_index = index;
+        self.file_list_state.select(Some(index));
+        self.update_diff_content();
+    }
+
+    fn rebuild_file_tree(&mut self) {
+        // Only re-flatten; the tree is built once per set of file diffs
+        self.file_tree_items = self.file_tree.flatten(&self.collapsed_directories);
+
+        // Adjust selected index if needed
+        if self.selected_index >= self.file_tree_items.len() {
+            self.selected_index = self.file_tree_items.len().saturating_sub(1);
+            self.file_list_state.select(Some(self.selected_index));
+        }
+    }
+
+    /// Refresh diff output with specific width for side-by-side display
+    fn refresh_diff_with_width(&mut self, width: u16) {
+        if self.shows_file_contents() {
+            return;
+        }
+        // Re-execute diff tool with the new width for proper side-by-side alignment
+        match self.diff_command_type() {
+            DiffCommandType::GitDefault => {
+                // No processing needed for default git diff
+            }
+            DiffCommandType::Pager(_) | DiffCommandType::External(_) => {
+                let current_items = self.get_current_file_tree_items();
+                if let Some(tree_item) = current_items.get(self.selected_index) {
+                    if let Some(file_diff) = &tree_item.file_diff {
+                        // Get fresh diff content for the current file
+                        let base_diff = self.fetch_file_diff(&tree_item.full_path, file_diff);
+
+                        // Apply diff tool with width, keeping the current output until it is done
+                        match self.prepare_diff_tool_with_width(&base_diff, Some(width)) {
+                            Ok(job) => self.spawn_diff_tool(job, None),
+                            Err(e) => self.report_diff_tool_error(&e),
                         }
                     }
                 }
@@ -710,7 +2553,10 @@ impl App {
 
     /// Refresh diff output with area width and terminal width for better template calculations
     fn refresh_diff_with_area_width(&mut self, area_width: u16, terminal_width: u16) {
-        match self.config.get_diff_command_type() {
+        if self.shows_file_contents() {
+            return;
+        }
+        match self.diff_command_type() {
             DiffCommandType::GitDefault => {
                 // No processing needed for default git diff
             }
@@ -719,29 +2565,22 @@ impl App {
                 if let Some(tree_item) = current_items.get(self.selected_index) {
                     if let Some(file_diff) = &tree_item.file_diff {
                         // Get fresh diff content for the current file
-                        let base_diff = if let Some(ref git_executor) = self.git_executor {
-                            match git_executor
-                                .get_file_diff(&self.operation_mode, &tree_item.full_path)
-                            {
-                                Ok(fresh_diff) => fresh_diff,
-                                Err(_) => file_diff.content.clone(),
-                            }
-                        } else {
-                            file_diff.content.clone()
-                        };
+                        let base_diff = self.fetch_file_diff(&tree_item.full_path, file_diff);
 
                         // Execute diff tool with area width for optimal template variable usage
-                        match self.execute_external_diff_tool_with_area_width(
+                        match self.prepare_diff_tool_with_area_width(
                             &base_diff,
                             area_width,
                             terminal_width,
                         ) {
-                            Ok(processed_output) => {
-                                self.diff_output = processed_output;
-                            }
-                            Err(e) => {
-                                eprintln!("Warning: Failed to refresh diff with area width: {e}");
+                            Ok(job) => {
+                                self.spawn_diff_tool(job, None);
+                                // Keep the old output until the new one arrives, but say so
+                                if let Some(pending) = &mut self.pending_diff {
+                                    pending.for_resize = true;
+                                }
                             }
+                            Err(e) => self.report_diff_tool_error(&e),
                         }
                     }
                 }
@@ -751,20 +2590,61 @@ impl App {
 
     /// Clamp scroll values to valid ranges based on content and viewport size
     fn clamp_scroll(&mut self, viewport_height: u16, viewport_width: u16) {
-        // Calculate content dimensions
-        let content_height = self.diff_output.lines().count() as u16;
-
-        // Calculate the maximum display width, accounting for ANSI escape sequences
-        let max_line_width = self
-            .diff_output
-            .lines()
-            .map(|line| self.calculate_display_width(line))
-            .max()
-            .unwrap_or(0) as u16;
-
         // Account for borders (subtract 2 for top and bottom borders)
         let available_height = viewport_height.saturating_sub(2);
         let available_width = viewport_width.saturating_sub(2);
+        self.diff_viewport_height = available_height;
+        self.diff_viewport_width = available_width;
+
+        // Measuring lines (accounting for ANSI escape sequences) is linear in the content,
+        // which is too slow to repeat every frame for minified files, so it is cached until
+        // the content changes
+        let max_line_width = match self.cached_max_width {
+            Some(max_width) => max_width,
+            None => {
+                self.visible_lines = self.filters_changed_lines().then(|| {
+                    DiffParser::changed_line_indices(&strip_ansi_escapes::strip_str(
+                        &self.diff_output,
+                    ))
+                });
+                let lines: Vec<&str> = self.diff_output.lines().collect();
+                let widths: Vec<usize> = match &self.visible_lines {
+                    Some(visible) => visible
+                        .iter()
+                        .filter_map(|&i| lines.get(i))
+                        .map(|line| self.calculate_display_width(line))
+                        .collect(),
+                    None => lines
+                        .iter()
+                        .map(|line| self.calculate_display_width(line))
+                        .collect(),
+                };
+                let max_width = widths.iter().copied().max().unwrap_or(0);
+                self.cached_line_widths = widths;
+                let max_width = max_width.min(u16::MAX as usize) as u16;
+                self.cached_max_width = Some(max_width);
+                max_width
+            }
+        };
+
+        // The diff pane wraps, so a line wider than the pane takes several rows
+        let mut content_rows: usize = self
+            .cached_line_widths
+            .iter()
+            .map(|&width| wrapped_row_count(width, available_width))
+            .sum();
+        // Explanations are added above the diff when rendering, or replace an empty one
+        let prefix_lines = self.diff_prefix_lines();
+        self.diff_prefix_rows = prefix_lines
+            .iter()
+            .map(|line| wrapped_row_count(self.calculate_display_width(line), available_width))
+            .sum();
+        if !prefix_lines.is_empty() && self.diff_output_is_blank() {
+            content_rows = 0;
+        }
+        content_rows += self.diff_prefix_rows;
+        self.diff_row_count = content_rows;
+        let content_height = content_rows.min(u16::MAX as usize) as u16;
 
         // Vertical scroll limit: can't scroll beyond content
         let max_vertical_scroll = content_height.saturating_sub(available_height);
@@ -772,11 +2652,49 @@ impl App {
         // Horizontal scroll limit: can't scroll beyond the longest line
         let max_horizontal_scroll = max_line_width.saturating_sub(available_width);
 
+        // The placeholder shown while a diff tool runs would lose a sticky scroll offset
+        if self.pending_diff.is_some() {
+            return;
+        }
+
         // Clamp the scroll values
         self.vertical_scroll = self.vertical_scroll.min(max_vertical_scroll);
         self.horizontal_scroll = self.horizontal_scroll.min(max_horizontal_scroll);
     }
 
+    /// Get the scroll position as a percentage (0-100), or None if everything fits on screen
+    fn scroll_percentage(&self) -> Option<u16> {
+        let content_height = self.diff_row_count;
+        let max_vertical_scroll = content_height.saturating_sub(self.diff_viewport_height as usize);
+
+        if max_vertical_scroll == 0 {
+            return None;
+        }
+
+        let percentage = (self.vertical_scroll as usize * 100 / max_vertical_scroll).min(100);
+        Some(percentage as u16)
+    }
+
+    /// Get the 1-based position of the selected file among all visible files
+    fn selected_file_position(&self) -> Option<(usize, usize)> {
+        let current_items = self.get_current_file_tree_items();
+        let selected = current_items.get(self.selected_index)?;
+        if selected.is_directory {
+            return None;
+        }
+
+        let total = current_items
+            .iter()
+            .filter(|item| !item.is_directory)
+            .count();
+        let position = current_items[..=self.selected_index]
+            .iter()
+            .filter(|item| !item.is_directory)
+            .count();
+
+        Some((position, total))
+    }
+
     /// Calculate the display width of a line, excluding ANSI escape sequences
     fn calculate_display_width(&self, line: &str) -> usize {
         // Use strip_ansi_escapes to remove ANSI sequences, then calculate width
@@ -818,14 +2736,23 @@ impl App {
         let column_width = (terminal_width / 2).saturating_sub(6);
         let diff_column_width = (diff_area_width / 2).saturating_sub(6);
 
+        // The runtime offset applies to every width, never going below one column
+        let offset = |width: u16| width.saturating_add_signed(self.width_offset).max(1);
         TemplateValues {
-            width: terminal_width,
-            column_width,
-            diff_area_width,
-            diff_column_width,
+            width: offset(terminal_width),
+            column_width: offset(column_width),
+            diff_area_width: offset(diff_area_width),
+            diff_column_width: offset(diff_column_width),
         }
     }
 
+    /// Nudge the width template variables and re-run the diff tool with them
+    fn adjust_width_offset(&mut self, delta: i16) {
+        self.width_offset = self.width_offset.saturating_add(delta);
+        // Refresh on the next render as if the pane had been drawn for the first time
+        self.last_refresh_width = 0;
+    }
+
     /// Apply template variable substitutions to command string
     fn apply_template_substitutions(&self, command_str: &str, values: &TemplateValues) -> String {
         let mut result = command_str.to_string();
@@ -849,9 +2776,16 @@ impl App {
         result
     }
 
+    /// Get the width of the diff pane in a terminal of the given width
+    ///
+    /// Computed in u32, since the percentage product overflows u16 on very wide terminals.
+    fn diff_pane_width(&self, terminal_width: u16) -> u16 {
+        (u32::from(terminal_width) * u32::from(100 - self.sidebar_width) / 100) as u16
+    }
+
     /// Resolve template variables in command string (lazygit style)
     fn resolve_template_variables(&self, command_str: &str, width: u16) -> String {
-        let area_width = (width * 80 / 100).saturating_sub(2); // 80% minus borders
+        let area_width = self.diff_pane_width(width).saturating_sub(2); // Diff pane minus borders
         let values = self.calculate_template_values(area_width, width);
         self.apply_template_substitutions(command_str, &values)
     }
@@ -871,32 +2805,70 @@ impl App {
 fn main() -> Result<()> {
     // Parse command line arguments
     let cli = Cli::parse_args();
-    let operation_mode = cli.get_operation_mode();
 
-    // Handle special modes first
-    match &operation_mode {
-        OperationMode::Completions { shell } => {
-            generate_completions(*shell);
-            return Ok(());
-        }
-        OperationMode::Invalid { reason } => {
-            eprintln!("Error: {reason}");
-            std::process::exit(1);
-        }
-        _ => {}
+    // Completions never depend on the configuration
+    if let OperationMode::Completions { shell } = cli.get_operation_mode(DefaultMode::default()) {
+        generate_completions(shell);
+        return Ok(());
     }
 
     // Load configuration
-    let config = if let Some(config_path) = cli.config {
-        Config::load_from_path(&config_path)?
+    let mut config = if let Some(config_path) = &cli.config {
+        Config::load_from_path(config_path)?
     } else {
         Config::load()?
     };
 
-    // Check if we need a git repository
+    // The configured default mode applies when no targets or mode flags are given
+    let operation_mode = cli.get_operation_mode(config.git.default_mode);
+    if let OperationMode::Invalid { reason } = &operation_mode {
+        eprintln!("Error: {reason}");
+        std::process::exit(1);
+    }
+
+    // Command line flags take precedence over the config file
+    if let Some(context) = cli.context {
+        config.git.context_lines = Some(context);
+    }
+    if cli.inter_hunk_context.is_some() {
+        config.git.inter_hunk_context = cli.inter_hunk_context;
+    }
+    if cli.untracked {
+        config.git.show_untracked = true;
+    }
+    if cli.instant {
+        config.git.instant = true;
+    }
+    config.git.diff_filter = cli.diff_filter.clone();
+    if cli.max_files.is_some() {
+        config.ui.max_files = cli.max_files;
+    }
+    config.git.pathspec = cli.pathspecs();
+
+    if let OperationMode::Themes { preview } = &operation_mode {
+        return run_themes_command(&config, preview.as_deref());
+    }
+    if let OperationMode::ClearChecks { all, force } = operation_mode {
+        return run_clear_command(&config, all, force);
+    }
+
+    // Check if we need git, and a git repository
+    if operation_mode.requires_git() {
+        GitExecutor::ensure_available()?;
+    }
     if operation_mode.requires_git_repo() && !GitExecutor::is_git_repo() {
         return Err(anyhow::anyhow!("Not in a git repository"));
     }
+    // Name the upstream branch in the title, and explain why there is none
+    let operation_mode = if cli.upstream {
+        OperationMode::GitDiff {
+            target: format!("{}...HEAD", GitExecutor::upstream_branch()?),
+            force_ref: true,
+        }
+    } else {
+        operation_mode
+    };
+    let operation_mode = resolve_path_target(operation_mode, &mut config)?;
 
     // Get diff data based on operation mode
     let is_stdin_terminal = io::IsTerminal::is_terminal(&io::stdin());
@@ -905,54 +2877,325 @@ fn main() -> Result<()> {
         eprintln!("Debug: operation mode: {operation_mode:?}");
     }
 
-    let file_diffs = if !is_stdin_terminal {
+    let mut piped_input = false;
+    let mut file_diffs = if let OperationMode::PatchFile { path } = &operation_mode {
+        // Patch mode: parse a saved diff, no git repository needed
+        read_patch_file(path)?
+    } else if !is_stdin_terminal {
         // Stdin mode: read piped input (backward compatibility)
         if cli.verbose {
             eprintln!("Debug: Using stdin mode");
         }
-        read_input_completely().unwrap_or_else(|_| {
-            if cli.verbose {
-                eprintln!("Debug: No stdin input, falling back to git executor");
+        match read_input_completely() {
+            Ok(file_diffs) => {
+                piped_input = true;
+                file_diffs
             }
-            get_diffs_from_git(&operation_mode).unwrap_or_default()
-        })
+            Err(_) => {
+                if cli.verbose {
+                    eprintln!("Debug: No stdin input, falling back to git executor");
+                }
+                get_diffs_from_git(&operation_mode, &config).unwrap_or_default()
+            }
+        }
     } else {
         // Interactive mode: use git executor
         if cli.verbose {
             eprintln!("Debug: Using git executor mode");
         }
-        get_diffs_from_git(&operation_mode)?
+        get_diffs_from_git(&operation_mode, &config)?
     };
 
-    if file_diffs.is_empty() {
+    // Exporting writes the raw diffs and skips the TUI
+    if let Some(path) = &cli.export {
+        let options = diff_options_from_config(&config);
+        write_patch(
+            path,
+            &load_summarized_diffs(&file_diffs, &operation_mode, &options)?,
+        )?;
+        eprintln!("Wrote {} files to {path}", file_diffs.len());
+        return Ok(());
+    }
+
+    // Machine-readable output skips the TUI entirely
+    if cli.json {
+        let summaries: Vec<FileDiffSummary> =
+            file_diffs.iter().map(FileDiffSummary::from).collect();
+        println!("{}", serde_json::to_string_pretty(&summaries)?);
+        if cli.exit_code && !file_diffs.is_empty() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Scripting mode mirrors `git diff --exit-code`
+    if cli.exit_code {
+        if file_diffs.is_empty() {
+            return Ok(());
+        }
+        std::process::exit(1);
+    }
+
+    // Watching only makes sense for diffs of the working tree, which change while editing
+    let watch_interval = cli.watch.filter(|_| {
+        let watchable = operation_mode.reads_working_tree() && !piped_input;
+        if !watchable {
+            eprintln!("--watch only applies to working tree diffs, ignoring it");
+        }
+        watchable
+    });
+
+    // In watch mode, wait for changes instead of exiting
+    if file_diffs.is_empty() && watch_interval.is_none() {
         println!("No differences found.");
         return Ok(());
     }
 
+    // Ask before building a tree for an enormous diff. It has been read and parsed already,
+    // so truncating only spares the tree and the per-file work of the session
+    let mut file_limit_note = None;
+    if let Some(max_files) = config.ui.max_files.filter(|&max| file_diffs.len() > max) {
+        let total = file_diffs.len();
+        match ask_file_limit_choice(total, max_files) {
+            FileLimitChoice::All => config.ui.max_files = None,
+            FileLimitChoice::Truncate => {
+                file_diffs.truncate(max_files);
+                file_limit_note = Some(format!(
+                    "Showing the first {max_files} of {total} files (--max-files)"
+                ));
+            }
+            FileLimitChoice::Quit => return Ok(()),
+        }
+    }
+
+    // Show how the diff tool would run for the first file, with the widths of this terminal
+    if cli.print_diff_command {
+        let app = App::new(config, file_diffs, operation_mode)?;
+        let (terminal_width, _) = crossterm::terminal::size().unwrap_or((80, 24));
+        let area_width = app.diff_pane_width(terminal_width);
+        println!("{}", app.describe_diff_command(area_width, terminal_width)?);
+        return Ok(());
+    }
+
     // Initialize TUI
     enable_raw_mode()
         .map_err(|e| anyhow::anyhow!("Failed to initialize terminal raw mode: {}", e))?;
 
+    let alt_screen = !cli.no_alt_screen;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    if alt_screen {
+        execute!(stdout, EnterAlternateScreen)?;
+    }
+    execute!(stdout, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
+    if !alt_screen {
+        // Draw over the visible screen rather than around the shell output on it
+        terminal.clear()?;
+    }
 
-    let app = App::new(config, file_diffs, operation_mode)?;
+    let suggest_conflicts = !piped_input
+        && !matches!(operation_mode, OperationMode::GitConflicts)
+        && GitExecutor::operation_in_progress()
+        && GitExecutor::new()
+            .get_conflicted_files()
+            .is_ok_and(|files| !files.is_empty());
+    let summary_only = file_diffs.iter().any(FileDiff::is_summary_only);
+    let mut app = App::new(config, file_diffs, operation_mode)?;
+    // Several notes can apply at once, so they share the status line
+    let mut notes = Vec::new();
+    if summary_only {
+        notes.push("Diff exceeds maxDiffBytes, so each file is loaded when selected".to_string());
+    }
+    if suggest_conflicts {
+        notes.push(
+            "A merge or rebase is in progress, run `ftdv conflicts` to review conflicts"
+                .to_string(),
+        );
+    }
+    notes.extend(file_limit_note);
+    if !notes.is_empty() {
+        app.status_message = Some(notes.join(" | "));
+    }
+    app.piped_input = piped_input;
+    app.alt_screen = alt_screen;
+    app.watch_interval = watch_interval.map(std::time::Duration::from_secs);
+    if cli.debug_pager {
+        // Reload so the first file's raw diff is kept too
+        app.debug_pager = true;
+        app.update_diff_content();
+    }
     let res = run_app(&mut terminal, app);
 
     // Restore terminal
     disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
+    execute!(terminal.backend_mut(), DisableMouseCapture)?;
+    if alt_screen {
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    } else {
+        // Keep the last frame on screen and start the prompt below it
+        let height = terminal.size()?.height;
+        terminal.set_cursor_position((0, height.saturating_sub(1)))?;
+        println!();
+    }
     terminal.show_cursor()?;
 
+    // Errors that ended the session, e.g. a lost terminal, with their causes on one line
     if let Err(err) = res {
-        eprintln!("{err:?}")
+        eprintln!("Error: {err:#}");
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Format a command as a shell-like line, quoting arguments that contain spaces or quotes
+fn format_command_line(cmd: &Command) -> String {
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| {
+            let arg = arg.to_string_lossy();
+            if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || c == '\'' || c == '"')
+            {
+                format!("'{}'", arg.replace('\'', "'\\''"))
+            } else {
+                arg.into_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Configure the environment of a diff tool child process
+///
+/// The parent environment is inherited unless `envPassthrough` restricts it, and the terminal
+/// overrides (TERM/COLUMNS/LINES) are only applied when `overrideTerminalEnv` is enabled.
+fn configure_child_env(cmd: &mut Command, paging: &GitPagingConfig, overrides: &[(&str, String)]) {
+    if !paging.env_passthrough.is_empty() {
+        cmd.env_clear();
+        let allowed = ESSENTIAL_ENV_VARS
+            .iter()
+            .copied()
+            .chain(paging.env_passthrough.iter().map(String::as_str));
+        for key in allowed {
+            if let Some(value) = std::env::var_os(key) {
+                cmd.env(key, value);
+            }
+        }
+    }
+
+    if paging.override_terminal_env {
+        for (key, value) in overrides {
+            cmd.env(key, value);
+        }
+    }
+}
+
+/// Get the directory per-path check states belong to: the repository, or the current directory
+fn check_states_repo_dir() -> std::path::PathBuf {
+    GitExecutor::repo_root()
+        .or_else(|_| std::env::current_dir())
+        .unwrap_or_default()
+}
+
+/// Remove the persisted check states of this repository, or of all repositories
+fn run_clear_command(config: &Config, all: bool, force: bool) -> Result<()> {
+    let repo_dir = check_states_repo_dir();
+    let persistence_manager =
+        PersistenceManager::new(config.persistence.check_scope, Some(&repo_dir))?;
+
+    // Per-blob states belong to this repository when git knows their blobs
+    let repo_blobs = if all || !GitExecutor::is_git_repo() {
+        std::collections::HashSet::new()
+    } else {
+        GitExecutor::known_objects(&persistence_manager.stored_blob_hashes()?)?
+    };
+    let files = persistence_manager.check_states_to_clear(all, &repo_blobs)?;
+    let scope = if all {
+        "all repositories".to_string()
+    } else {
+        repo_dir.display().to_string()
+    };
+
+    if files.is_empty() {
+        println!("No check states to clear for {scope}.");
+        return Ok(());
+    }
+
+    if !force {
+        eprint!("Clear {} check states for {scope}? [y/N] ", files.len());
+        io::Write::flush(&mut io::stderr())?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            println!("Nothing cleared.");
+            return Ok(());
+        }
+    }
+
+    let cleared = persistence_manager.clear_check_states(&files)?;
+    println!("Cleared {cleared} check states for {scope}.");
+    Ok(())
+}
+
+/// List the built-in themes, or print a preview of one to stdout
+fn run_themes_command(config: &Config, preview: Option<&str>) -> Result<()> {
+    let Some(name) = preview else {
+        for name in Theme::builtin_names() {
+            let marker = if *name == config.theme.name { "*" } else { " " };
+            println!("{marker} {name}");
+        }
+        if Theme::builtin(&config.theme.name).is_none() {
+            println!("* {} (from config)", config.theme.name);
+        }
+        return Ok(());
+    };
+
+    // The configured theme may customize colors, so prefer it when the name matches
+    let theme = if name == config.theme.name {
+        config.theme.clone()
+    } else {
+        Theme::builtin(name).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unknown theme '{}'. Available themes: {}",
+                name,
+                Theme::builtin_names().join(", ")
+            )
+        })?
+    };
+
+    // Render sample data into an off-screen buffer
+    let file_diffs = DiffParser::parse(THEME_PREVIEW_DIFF);
+    let backend = ratatui::backend::TestBackend::new(THEME_PREVIEW_WIDTH, THEME_PREVIEW_HEIGHT);
+    let mut terminal = Terminal::new(backend)?;
+    terminal.draw(|f| render_theme_preview(f, &theme, &file_diffs))?;
+
+    print_buffer(terminal.backend().buffer())
+}
+
+/// Print a rendered buffer to stdout with its colors
+fn print_buffer(buffer: &ratatui::buffer::Buffer) -> Result<()> {
+    use ratatui::crossterm::{
+        queue,
+        style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    };
+    use std::io::Write;
+
+    let mut stdout = io::stdout();
+    for y in 0..buffer.area().height {
+        for x in 0..buffer.area().width {
+            if let Some(cell) = buffer.cell((x, y)) {
+                queue!(
+                    stdout,
+                    SetForegroundColor(Color::from(cell.fg)),
+                    SetBackgroundColor(Color::from(cell.bg)),
+                    Print(cell.symbol())
+                )?;
+            }
+        }
+        queue!(stdout, ResetColor, Print("\n"))?;
     }
+    stdout.flush()?;
 
     Ok(())
 }
@@ -975,41 +3218,385 @@ fn generate_completions(shell: clap_complete::Shell) {
     print_completions(shell, &mut cmd);
 }
 
-fn get_diffs_from_git(mode: &OperationMode) -> Result<Vec<FileDiff>> {
-    let git_executor = GitExecutor::new();
+/// Compile `ui.hidePatterns`, or None when none are configured
+fn build_hide_patterns(patterns: &[String]) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .map_err(|e| anyhow::anyhow!("Invalid ui.hidePatterns entry '{pattern}': {e}"))?;
+        builder.add(glob);
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// Check if a path matches the hide patterns, trying its file name alone too
+///
+/// This lets patterns without a slash, like "Cargo.lock", match in any directory.
+fn is_hidden(hide_patterns: &GlobSet, path: &str) -> bool {
+    hide_patterns.is_match(path)
+        || path
+            .rsplit_once('/')
+            .is_some_and(|(_, name)| hide_patterns.is_match(name))
+}
 
-    // Get overall diff output
-    let diff_output = git_executor.get_diff(mode)?;
+/// Get the diffs to show in the tree: those not hidden by `hide_patterns`, if given
+fn visible_file_diffs<'a>(
+    file_diffs: &'a [FileDiff],
+    hide_patterns: Option<&GlobSet>,
+) -> Vec<&'a FileDiff> {
+    file_diffs
+        .iter()
+        .filter(|file_diff| {
+            hide_patterns.is_none_or(|patterns| !is_hidden(patterns, &file_diff.filename))
+        })
+        .collect()
+}
 
-    if diff_output.is_empty() {
-        return Ok(vec![]);
+/// Hash the content of fetched diffs, to tell whether a refetch changed anything
+fn diff_fingerprint(file_diffs: &[FileDiff]) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for file_diff in file_diffs {
+        file_diff.filename.hash(&mut hasher);
+        file_diff.content.hash(&mut hasher);
+        // Summaries of diffs too large to read have no content, only line counts
+        (file_diff.added_lines, file_diff.removed_lines).hash(&mut hasher);
+        file_diff
+            .status_group
+            .map(StatusGroup::label)
+            .hash(&mut hasher);
     }
+    hasher.finish()
+}
 
-    // Parse the diff output to get individual file diffs
-    Ok(DiffParser::parse(&diff_output))
+/// Build the git diff options from the effective configuration
+///
+/// These are never colored, since ANSI codes would break parsing and pager input.
+fn diff_options_from_config(config: &Config) -> DiffOptions {
+    DiffOptions {
+        context_lines: config.git.context_lines,
+        inter_hunk_context: config.git.inter_hunk_context,
+        color: None,
+        pathspec: config.git.pathspec.clone(),
+        reverse: config.git.reverse,
+        max_output_bytes: match config.git.max_diff_bytes {
+            0 => None,
+            bytes => Some(usize::try_from(bytes).unwrap_or(usize::MAX)),
+        },
+        diff_filter: config.git.diff_filter.clone(),
+    }
 }
 
-fn read_input_completely() -> Result<Vec<FileDiff>> {
-    // Read all stdin content at once
-    let mut buffer = String::new();
-    io::stdin()
-        .read_to_string(&mut buffer)
-        .map_err(|e| anyhow::anyhow!("Failed to read from stdin: {}", e))?;
+/// Check if both targets of Compare mode are refs, rather than paths diffed with --no-index
+fn compare_targets_are_refs(mode: &OperationMode) -> Result<bool> {
+    match mode {
+        OperationMode::Compare {
+            target1,
+            target2,
+            force_ref,
+        } => {
+            let executor = GitExecutor::new();
+            Ok(executor.is_git_ref(target1, *force_ref)?
+                && executor.is_git_ref(target2, *force_ref)?)
+        }
+        _ => Ok(false),
+    }
+}
 
-    if buffer.trim().is_empty() {
-        anyhow::bail!("No input received from stdin");
+/// Look up the commit or range label of a mode that compares git revisions
+///
+/// Compare mode does not require a repository, but ref ranges still have a label.
+fn revision_label_for(mode: &OperationMode, diff_options: &DiffOptions) -> Option<String> {
+    match mode {
+        OperationMode::GitDiff { .. }
+        | OperationMode::GitCachedRef { .. }
+        | OperationMode::Compare { .. }
+            if GitExecutor::is_git_repo() =>
+        {
+            GitExecutor::with_options(diff_options.clone()).revision_label(mode)
+        }
+        _ => None,
     }
+}
 
-    Ok(DiffParser::parse(&buffer))
+/// Show a single path target as its working tree changes, like `git diff -- <path>`
+///
+/// Targets are told apart the same way as for `Compare`: anything git resolves is a ref.
+fn resolve_path_target(mode: OperationMode, config: &mut Config) -> Result<OperationMode> {
+    match mode {
+        OperationMode::GitDiff {
+            target,
+            force_ref: false,
+        } if !GitExecutor::new().is_git_ref(&target, false)? => {
+            config.git.pathspec.push(target);
+            Ok(OperationMode::GitWorkingDirectory)
+        }
+        mode => Ok(mode),
+    }
+}
+
+fn get_diffs_from_git(mode: &OperationMode, config: &Config) -> Result<Vec<FileDiff>> {
+    let git_executor = GitExecutor::with_options(diff_options_from_config(config));
+
+    // Status mode fetches staged, unstaged and untracked files separately to group them
+    if matches!(mode, OperationMode::GitStatus) {
+        return git_executor.get_status_diffs(config.git.show_untracked);
+    }
+    if matches!(mode, OperationMode::GitConflicts) {
+        return git_executor.get_conflict_diffs();
+    }
+
+    // Get overall diff output, or only the file list if it is too large to hold in memory
+    let diff_output = match git_executor.get_diff(mode) {
+        Err(e) if e.is::<DiffTooLarge>() => return git_executor.get_summary_diffs(mode),
+        diff_output => diff_output?,
+    };
+
+    // Parse the diff output to get individual file diffs
+    let file_diffs = if diff_output.is_empty() {
+        vec![]
+    } else {
+        DiffParser::parse(&diff_output)
+    };
+
+    Ok(file_diffs)
+}
+
+/// Decode a diff tool's output, replacing invalid UTF-8 so one bad byte cannot blank the pane
+///
+/// Some tools pass through bytes of files in other encodings along with their ANSI colors.
+fn decode_tool_output(stdout: Vec<u8>) -> String {
+    String::from_utf8(stdout).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
+
+fn run_command_with_stdin(cmd: &mut Command, command_name: &str, input: &str) -> Result<String> {
+    use std::io::Write;
+
+    cmd.stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to spawn {}: {}", command_name, e))?;
+
+    // Write input
+    if let Some(stdin) = child.stdin.take() {
+        let mut writer = std::io::BufWriter::new(stdin);
+        writer
+            .write_all(input.as_bytes())
+            .map_err(|e| anyhow::anyhow!("Failed to write to command: {}", e))?;
+        writer
+            .flush()
+            .map_err(|e| anyhow::anyhow!("Failed to flush command input: {}", e))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| anyhow::anyhow!("Failed to read from command: {}", e))?;
+
+    if output.status.success() {
+        Ok(decode_tool_output(output.stdout))
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(anyhow::anyhow!("Command failed: {}", stderr))
+    }
+}
+
+fn run_git_external_diff(cmd: &mut Command) -> Result<String> {
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    let output = cmd
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to execute git with external diff: {}", e))?;
+
+    // git diff --no-index exits with 1 when the paths differ, which is normal
+    if matches!(output.status.code(), Some(0 | 1)) {
+        let stdout = decode_tool_output(output.stdout);
+        if stdout.is_empty() {
+            // e.g. untracked files, which git diff does not know about
+            return Err(anyhow::anyhow!("Git external diff produced no output"));
+        }
+        Ok(stdout)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(anyhow::anyhow!("Git external diff failed: {}", stderr))
+    }
+}
+
+/// Get the number of rows a line of the given display width takes when wrapped
+fn wrapped_row_count(line_width: usize, available_width: u16) -> usize {
+    if available_width == 0 {
+        return 1;
+    }
+    line_width.div_ceil(available_width as usize).max(1)
+}
+
+/// Cut content down to max_lines with a footer, or None if it already fits
+fn truncate_diff_lines(content: &str, max_lines: usize) -> Option<String> {
+    let total_lines = content.lines().count();
+    if total_lines <= max_lines {
+        return None;
+    }
+
+    let mut truncated: String = content
+        .lines()
+        .take(max_lines)
+        .flat_map(|line| [line, "\n"])
+        .collect();
+    truncated.push_str(&format!(
+        "... (truncated, {} more lines, press F to load all)",
+        total_lines - max_lines
+    ));
+    Some(truncated)
+}
+
+/// How to open a diff with more files than `maxFiles`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileLimitChoice {
+    All,
+    Truncate,
+    Quit,
+}
+
+impl FileLimitChoice {
+    /// Interpret an answer to the prompt; anything unrecognized shows the first files only
+    fn from_answer(answer: &str) -> Self {
+        match answer.trim().to_lowercase().as_str() {
+            "a" | "all" => FileLimitChoice::All,
+            "q" | "quit" => FileLimitChoice::Quit,
+            _ => FileLimitChoice::Truncate,
+        }
+    }
+}
+
+/// Ask on the terminal whether to show all files of a diff with more than `max_files`
+///
+/// The terminal is read directly, since stdin may be the piped diff. Without a terminal,
+/// only the first files are shown.
+fn ask_file_limit_choice(total: usize, max_files: usize) -> FileLimitChoice {
+    eprint!(
+        "The diff has {total} files, more than --max-files {max_files}. \
+         Show [a]ll, the [f]irst {max_files} (default), or [q]uit? "
+    );
+    let _ = io::Write::flush(&mut io::stderr());
+
+    let mut answer = String::new();
+    let read = std::fs::File::open("/dev/tty")
+        .and_then(|tty| io::BufRead::read_line(&mut io::BufReader::new(tty), &mut answer));
+    if read.is_err() {
+        eprintln!();
+    }
+    FileLimitChoice::from_answer(&answer)
+}
+
+/// Fetch the diff of a file that is only known from a summary of a diff too large to read
+fn fetch_summarized_diff(
+    mode: &OperationMode,
+    options: &DiffOptions,
+    file_diff: &FileDiff,
+) -> Result<String> {
+    // Staged files in status mode are diffed against HEAD instead of the worktree
+    let mode = match file_diff.status_group {
+        Some(StatusGroup::Staged) => &OperationMode::GitCached,
+        _ => mode,
+    };
+    let git_executor = GitExecutor::with_options(options.clone());
+    match &file_diff.rename_from {
+        Some(source) => git_executor.get_paths_diff(mode, &[source, &file_diff.filename]),
+        None => git_executor.get_file_diff(mode, &file_diff.filename),
+    }
+}
+
+/// Get file diffs with the content of summarized files filled in, so all of them can be saved
+fn load_summarized_diffs<'a>(
+    file_diffs: &'a [FileDiff],
+    mode: &OperationMode,
+    options: &DiffOptions,
+) -> Result<std::borrow::Cow<'a, [FileDiff]>> {
+    if !file_diffs.iter().any(FileDiff::is_summary_only) {
+        return Ok(std::borrow::Cow::Borrowed(file_diffs));
+    }
+    file_diffs
+        .iter()
+        .map(|file_diff| {
+            let mut file_diff = file_diff.clone();
+            if file_diff.is_summary_only() {
+                file_diff.content = fetch_summarized_diff(mode, options, &file_diff)?;
+            }
+            Ok(file_diff)
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(std::borrow::Cow::Owned)
+}
+
+/// Write file diffs to a patch file, using the raw diffs rather than diff tool output
+fn write_patch(path: &str, file_diffs: &[FileDiff]) -> Result<()> {
+    std::fs::write(path, DiffParser::to_patch(file_diffs))
+        .map_err(|e| anyhow::anyhow!("Failed to write patch file '{}': {}", path, e))
+}
+
+fn read_patch_file(path: &str) -> Result<Vec<FileDiff>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read patch file '{}': {}", path, e))?;
+
+    Ok(DiffParser::parse(&content))
+}
+
+fn read_input_completely() -> Result<Vec<FileDiff>> {
+    // Read all stdin content at once
+    let mut buffer = String::new();
+    io::stdin()
+        .read_to_string(&mut buffer)
+        .map_err(|e| anyhow::anyhow!("Failed to read from stdin: {}", e))?;
+
+    if buffer.trim().is_empty() {
+        anyhow::bail!("No input received from stdin");
+    }
+
+    // Input that is not a git diff (e.g. `jj diff`) is handled by the fallbacks
+    Ok(DiffParser::parse_input(&buffer))
 }
 
 fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result<()> {
     loop {
+        app.poll_diff_tool();
+        app.poll_watch();
         terminal.draw(|f| ui(f, &mut app))?;
 
         // Use poll to handle the case where stdin might not be available
         if event::poll(std::time::Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
+            let event = event::read()?;
+            if let Event::Mouse(mouse) = event {
+                app.handle_sidebar_drag(mouse.kind, mouse.column);
+            } else if let Event::Key(key) = event {
+                // An error dialog swallows the key press that dismisses it
+                if app.error_dialog.take().is_some() {
+                    continue;
+                }
+
+                // Status messages last until the next key press
+                app.status_message = None;
+
+                // An open prompt captures all keys
+                if app.prompt.is_some() {
+                    handle_prompt_key(&mut app, key.code);
+                    continue;
+                }
+
+                // Digits typed before a motion build a count (literal while typing a search)
+                if let KeyCode::Char(c) = key.code {
+                    if !app.search_input_mode && app.push_count_digit(c) {
+                        continue;
+                    }
+                }
+                let explicit_count = app.take_count();
+                let count = explicit_count.unwrap_or(1);
+
                 match key.code {
                     // Quit or exit search mode
                     KeyCode::Char('q') => {
@@ -1020,13 +3607,20 @@ fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, mut app: Ap
                         }
                     }
                     KeyCode::Esc => {
-                        if app.search_mode {
+                        if app.overlay.is_some() {
+                            app.close_overlay();
+                        } else if app.search_mode {
                             app.exit_search_mode();
                         } else {
                             app.should_quit = true;
                         }
                     }
 
+                    // Command line for switching comparisons
+                    KeyCode::Char(':') if !app.search_input_mode => {
+                        app.enter_prompt(Prompt::Command);
+                    }
+
                     // Search mode (use '/' key)
                     KeyCode::Char('/') if !app.search_input_mode => {
                         app.enter_search_mode();
@@ -1038,18 +3632,36 @@ fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, mut app: Ap
                     }
 
                     // Backspace in search input mode
-                    KeyCode::Backspace => {
-                        if app.search_input_mode {
-                            app.remove_search_char();
-                        }
+                    KeyCode::Backspace if app.search_input_mode => {
+                        app.remove_search_char();
                     }
 
+                    // Search history recall while typing a query
+                    KeyCode::Up if app.search_input_mode => app.recall_previous_search(),
+                    KeyCode::Down if app.search_input_mode => app.recall_next_search(),
+
                     // File navigation (disabled only when actively typing in search)
+                    // Navigation acts on the focused pane, switched with Shift-Tab
+                    KeyCode::BackTab if !app.search_input_mode => app.toggle_focus(),
                     KeyCode::Down | KeyCode::Char('j') if !app.search_input_mode => {
-                        app.select_next()
+                        app.move_down(count)
                     }
                     KeyCode::Up | KeyCode::Char('k') if !app.search_input_mode => {
-                        app.select_previous()
+                        app.move_up(count)
+                    }
+
+                    // Paging, a screenful of the focused pane at a time
+                    KeyCode::Char('d')
+                        if key.modifiers.contains(KeyModifiers::CONTROL)
+                            && !app.search_input_mode =>
+                    {
+                        app.page_down(count)
+                    }
+                    KeyCode::Char('u')
+                        if key.modifiers.contains(KeyModifiers::CONTROL)
+                            && !app.search_input_mode =>
+                    {
+                        app.page_up(count)
                     }
 
                     // Handle character input in search input mode (must be after other char handlers)
@@ -1058,7 +3670,7 @@ fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, mut app: Ap
                     }
                     KeyCode::Enter => {
                         // Toggle directory expansion/collapse or update diff view
-                        if let Some(tree_item) = app.file_tree_items.get(app.selected_index) {
+                        if let Some(tree_item) = app.get_browse_items().get(app.selected_index) {
                             if tree_item.is_directory {
                                 app.toggle_directory();
                             } else {
@@ -1068,34 +3680,85 @@ fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, mut app: Ap
                     }
 
                     // Jump navigation (disabled only when typing in search)
-                    KeyCode::Char('g') if !app.search_input_mode => app.jump_to_top(),
-                    KeyCode::Char('G') if !app.search_input_mode => app.jump_to_bottom(),
+                    // With a count, g/G jump to that item or line (like vim's line jumps)
+                    KeyCode::Char('g') if !app.search_input_mode => {
+                        app.jump_to_start(explicit_count)
+                    }
+                    KeyCode::Char('G') if !app.search_input_mode => app.jump_to_end(explicit_count),
 
                     // Vertical scrolling (disabled only when typing in search)
                     KeyCode::Char('e') | KeyCode::Char('J') if !app.search_input_mode => {
-                        app.scroll_down(1)
+                        app.scroll_down(count)
                     }
                     KeyCode::Char('y') | KeyCode::Char('K') if !app.search_input_mode => {
-                        app.scroll_up(1)
+                        app.scroll_up(count)
                     }
                     KeyCode::Char('d') | KeyCode::PageDown if !app.search_input_mode => {
-                        app.scroll_down(10)
+                        app.scroll_down(10u16.saturating_mul(count))
                     }
                     KeyCode::Char('u') | KeyCode::PageUp if !app.search_input_mode => {
-                        app.scroll_up(10)
+                        app.scroll_up(10u16.saturating_mul(count))
+                    }
+                    KeyCode::Char('f') if !app.search_input_mode => {
+                        app.scroll_down(20u16.saturating_mul(count))
+                    }
+                    KeyCode::Char('b') if !app.search_input_mode => {
+                        app.scroll_up(20u16.saturating_mul(count))
                     }
-                    KeyCode::Char('f') if !app.search_input_mode => app.scroll_down(20),
-                    KeyCode::Char('b') if !app.search_input_mode => app.scroll_up(20),
 
                     // Horizontal scrolling (disabled only when typing in search)
                     KeyCode::Char('h') | KeyCode::Left if !app.search_input_mode => {
-                        app.scroll_left(5)
+                        app.scroll_left(5u16.saturating_mul(count))
                     }
                     KeyCode::Char('l') | KeyCode::Right if !app.search_input_mode => {
-                        app.scroll_right(5)
+                        app.scroll_right(5u16.saturating_mul(count))
+                    }
+                    KeyCode::Char('H') if !app.search_input_mode => {
+                        app.scroll_left(app.horizontal_page_size().saturating_mul(count))
+                    }
+                    KeyCode::Char('L') if !app.search_input_mode => {
+                        app.scroll_right(app.horizontal_page_size().saturating_mul(count))
+                    }
+
+                    // Collapse/expand all directories (disabled only when typing in search)
+                    // Jump between files and lines that contain the search query
+                    KeyCode::Char(']') if !app.search_input_mode => {
+                        app.jump_to_search_match_file(true, count)
+                    }
+                    KeyCode::Char('[') if !app.search_input_mode => {
+                        app.jump_to_search_match_file(false, count)
+                    }
+                    KeyCode::Char(')') if !app.search_input_mode => {
+                        app.jump_to_search_match_line(true, count)
+                    }
+                    KeyCode::Char('(') if !app.search_input_mode => {
+                        app.jump_to_search_match_line(false, count)
+                    }
+
+                    KeyCode::Char('C') if !app.search_input_mode => app.collapse_all_directories(),
+                    KeyCode::Char('E') if !app.search_input_mode => app.expand_all_directories(),
+                    KeyCode::Char('z') if !app.search_input_mode => app.toggle_all_directories(),
+                    KeyCode::Char('D') if !app.search_input_mode => app.toggle_files_only(),
+                    KeyCode::Char('n') if !app.search_input_mode => app.toggle_full_paths(),
+                    KeyCode::Char('S') if !app.search_input_mode => app.toggle_sticky_scroll(),
+                    KeyCode::Char('T') if !app.search_input_mode => app.toggle_raw_diff(),
+                    KeyCode::Char('o') if !app.search_input_mode => app.toggle_changed_lines_only(),
+                    KeyCode::Char('U') if !app.search_input_mode => app.toggle_whole_file_context(),
+                    KeyCode::Char('W') if !app.search_input_mode => {
+                        app.toggle_highlight_whitespace()
+                    }
+
+                    // Sidebar resizing (disabled only when typing in search)
+                    KeyCode::Char('<') if !app.search_input_mode => app.shrink_sidebar(),
+                    KeyCode::Char('>') if !app.search_input_mode => app.grow_sidebar(),
+
+                    // Width passed to diff tools (disabled only when typing in search)
+                    KeyCode::Char('{') if !app.search_input_mode => {
+                        app.adjust_width_offset(-WIDTH_OFFSET_STEP)
+                    }
+                    KeyCode::Char('}') if !app.search_input_mode => {
+                        app.adjust_width_offset(WIDTH_OFFSET_STEP)
                     }
-                    KeyCode::Char('H') if !app.search_input_mode => app.scroll_left(20),
-                    KeyCode::Char('L') if !app.search_input_mode => app.scroll_right(20),
 
                     // Space key (disabled only when typing in search)
                     KeyCode::Char(' ') if !app.search_input_mode => {
@@ -1103,8 +3766,33 @@ fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, mut app: Ap
                         app.update_diff_content();
                     }
 
+                    // Diff the selected file across a ref range
+                    KeyCode::Char('v') if !app.search_input_mode => app.enter_prompt(Prompt::Range),
+                    KeyCode::Char('V') if !app.search_input_mode => {
+                        app.enter_prompt(Prompt::Revision)
+                    }
+                    KeyCode::Char('w') if !app.search_input_mode => {
+                        app.enter_prompt(Prompt::Export)
+                    }
+                    KeyCode::Char('s') if !app.search_input_mode => app.copy_hunk_suggestion(),
+                    KeyCode::Char('c') if !app.search_input_mode => app.copy_review_comment(),
+                    KeyCode::Char('r') if !app.search_input_mode => app.reload(),
+                    KeyCode::Char('R') if !app.search_input_mode => app.reverse_comparison(),
+                    KeyCode::Char('m') if !app.search_input_mode => app.cycle_diff_filter(),
+                    KeyCode::Char('i') if !app.search_input_mode => app.toggle_hidden_files(),
+                    KeyCode::Char('t') if !app.search_input_mode => {
+                        if let Err(e) = run_difftool(terminal, &mut app) {
+                            app.show_error(&e);
+                        }
+                    }
+                    KeyCode::Char('p') if !app.search_input_mode => app.copy_selected_path(false),
+                    KeyCode::Char('P') if !app.search_input_mode => app.copy_selected_path(true),
+                    // Load the rest of a diff truncated by maxDiffLines
+                    KeyCode::Char('F') if !app.search_input_mode => app.load_full_diff(),
+
                     // Checkbox toggle (works in both modes)
                     KeyCode::Tab => app.toggle_file_checked(),
+                    KeyCode::Char('x') if !app.search_input_mode => app.toggle_hunk_checked(),
 
                     _ => {}
                 }
@@ -1112,17 +3800,110 @@ fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, mut app: Ap
         }
 
         if app.should_quit {
+            // Check states are written in the background; make sure they all reach the disk
+            match app.persistence_manager.flush() {
+                Ok(()) => return Ok(()),
+                // Say what was lost before leaving; quitting again exits for good
+                Err(e) => {
+                    app.should_quit = false;
+                    app.show_error(&e.context("Some check marks could not be saved"));
+                }
+            }
+        }
+    }
+}
+
+/// Open the selected file in `git difftool`, suspending the TUI while it runs
+fn run_difftool<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+) -> Result<()> {
+    let mut cmd = match app.difftool_command() {
+        Ok(cmd) => cmd,
+        Err(e) => {
+            app.status_message = Some(e.to_string());
             return Ok(());
         }
+    };
+    // Terminal tools like vimdiff need the tty, but errors are captured for the status line
+    cmd.stderr(Stdio::piped());
+
+    disable_raw_mode()?;
+    execute!(io::stdout(), DisableMouseCapture)?;
+    if app.alt_screen {
+        execute!(io::stdout(), LeaveAlternateScreen)?;
+    }
+    let output = cmd.spawn().and_then(|child| child.wait_with_output());
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnableMouseCapture)?;
+    if app.alt_screen {
+        execute!(io::stdout(), EnterAlternateScreen)?;
+    }
+    terminal.clear()?;
+
+    app.status_message = match output {
+        Ok(output) if output.status.success() => None,
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Some(
+                match stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+                    Some(line) => format!("Difftool failed: {}", line.trim()),
+                    None => format!(
+                        "Difftool failed: git difftool exited with {}",
+                        output.status
+                    ),
+                },
+            )
+        }
+        Err(e) => Some(format!("Failed to run git difftool: {e}")),
+    };
+    Ok(())
+}
+
+fn handle_prompt_key(app: &mut App, code: KeyCode) {
+    match code {
+        KeyCode::Esc => app.cancel_prompt(),
+        KeyCode::Enter => app.confirm_prompt(),
+        KeyCode::Backspace => {
+            app.prompt_query.pop();
+            app.prompt_error = None;
+        }
+        KeyCode::Char(c) => {
+            app.prompt_query.push(c);
+            app.prompt_error = None;
+        }
+        _ => {}
     }
 }
 
 fn ui(f: &mut Frame, app: &mut App) {
-    // Main horizontal split: file list (30%) and diff content area (70%)
+    // Layout math below assumes room for the borders of both panes
+    let mut area = f.area();
+    if area.width < MIN_UI_WIDTH || area.height < MIN_UI_HEIGHT {
+        render_too_small(f, area, app);
+        return;
+    }
+
+    // Reserve the bottom row for the key hint footer
+    if app.config.ui.footer {
+        let outer_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+        area = outer_chunks[0];
+        render_footer(f, outer_chunks[1], app);
+    }
+
+    // Main horizontal split: file list and diff content area
     let main_chunks = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(20), Constraint::Percentage(80)])
-        .split(f.area());
+        .constraints([
+            Constraint::Percentage(app.sidebar_width),
+            Constraint::Percentage(100 - app.sidebar_width),
+        ])
+        .split(area);
+    app.layout_width = f.area().width;
+    app.sidebar_border = main_chunks[1].x;
 
     // Render search box and file list based on search mode
     if app.search_mode {
@@ -1143,17 +3924,28 @@ fn ui(f: &mut Frame, app: &mut App) {
         .constraints([Constraint::Length(3), Constraint::Min(0)])
         .split(main_chunks[1]);
 
-    render_status_line(f, right_chunks[0], app);
+    // Render the diff first so the status line sees the current viewport height
     render_diff_content(f, right_chunks[1], app);
+    if let Some(prompt) = app.prompt {
+        render_prompt(f, right_chunks[0], app, prompt);
+    } else {
+        render_status_line(f, right_chunks[0], app);
+    }
+
+    if let Some(message) = &app.error_dialog {
+        render_error_dialog(f, f.area(), app, message);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::git::test_repo::TestRepo;
     use crate::parser::FileDiff;
     use ratatui::backend::TestBackend;
     use ratatui::buffer::Buffer;
     use ratatui::layout::Rect;
+    use ratatui::style::{Color, Modifier};
 
     #[test]
     fn test_app_new() {
@@ -1179,6 +3971,250 @@ mod tests {
         assert!(buffer.area().height == 50);
     }
 
+    #[test]
+    fn test_theme_preview_renders_fixture() {
+        let theme = Theme::builtin("dark").unwrap();
+        let file_diffs = DiffParser::parse(THEME_PREVIEW_DIFF);
+        let backend = TestBackend::new(THEME_PREVIEW_WIDTH, THEME_PREVIEW_HEIGHT);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|f| render_theme_preview(f, &theme, &file_diffs))
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let row = |y: u16| -> String {
+            (0..buffer.area().width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect()
+        };
+        assert!(row(1).contains("src/main.rs | +2 -1"));
+        let cell = (0..buffer.area().width)
+            .map(|x| &buffer[(x, 10)])
+            .find(|cell| cell.symbol() == "-")
+            .unwrap();
+        assert_eq!(cell.fg, theme.colors.status_removed.0);
+    }
+
+    #[test]
+    fn test_export_patch() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("review.patch");
+        let file_diffs = DiffParser::parse(THEME_PREVIEW_DIFF);
+        let mut app = App::new(
+            Config::default(),
+            file_diffs.clone(),
+            OperationMode::GitWorkingDirectory,
+        )
+        .unwrap();
+
+        app.enter_prompt(Prompt::Export);
+        for c in path.to_string_lossy().chars() {
+            handle_prompt_key(&mut app, KeyCode::Char(c));
+        }
+        handle_prompt_key(&mut app, KeyCode::Enter);
+        assert!(app.prompt.is_none());
+        assert!(app.status_message.as_deref().unwrap().starts_with("Wrote"));
+
+        // The patch opens again with --patch
+        let reopened = read_patch_file(&path.to_string_lossy()).unwrap();
+        assert_eq!(reopened.len(), file_diffs.len());
+        assert_eq!(reopened[0].content, file_diffs[0].content);
+
+        // Esc cancels without writing
+        app.enter_prompt(Prompt::Export);
+        handle_prompt_key(&mut app, KeyCode::Esc);
+        assert!(app.prompt.is_none());
+    }
+
+    #[test]
+    fn test_export_loads_summarized_diffs() {
+        let repo = TestRepo::new();
+        repo.write("a.txt", "one\n");
+        repo.write("b.txt", "two\n");
+        repo.commit("initial");
+        repo.write("a.txt", "one\nmore\n");
+        repo.write("b.txt", "three\n");
+        repo.commit("change");
+        let _entered = repo.enter();
+
+        let mode = OperationMode::Compare {
+            target1: "HEAD~1".to_string(),
+            target2: "HEAD".to_string(),
+            force_ref: true,
+        };
+        let options = DiffOptions::default();
+        let git_executor = GitExecutor::with_options(options.clone());
+        let summaries = git_executor.get_summary_diffs(&mode).unwrap();
+        assert_eq!(summaries.len(), 2);
+        assert!(summaries.iter().all(FileDiff::is_summary_only));
+
+        // A diff too large to read whole still exports every file's changes
+        let loaded = load_summarized_diffs(&summaries, &mode, &options).unwrap();
+        let full_patch =
+            DiffParser::to_patch(&DiffParser::parse(&git_executor.get_diff(&mode).unwrap()));
+        assert_eq!(DiffParser::to_patch(&loaded), full_patch);
+    }
+
+    #[test]
+    fn test_footer_hints() {
+        let backend = TestBackend::new(100, 20);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut app = App::new(
+            Config::default(),
+            vec![],
+            OperationMode::GitWorkingDirectory,
+        )
+        .unwrap();
+
+        let last_row = |terminal: &Terminal<TestBackend>| {
+            let buffer = terminal.backend().buffer();
+            (0..buffer.area().width)
+                .map(|x| buffer[(x, buffer.area().height - 1)].symbol())
+                .collect::<String>()
+        };
+
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        assert!(last_row(&terminal).contains("q quit"));
+
+        app.enter_search_mode();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        assert!(last_row(&terminal).contains("Esc cancel"));
+
+        app.config.ui.footer = false;
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        assert!(!last_row(&terminal).contains("cancel"));
+    }
+
+    #[test]
+    fn test_tiny_terminal() {
+        let file_diffs = vec![FileDiff {
+            filename: "src/very/long/path/name.rs".to_string(),
+            content: "@@ -1 +1 @@\n-a\n+b\n".to_string(),
+            added_lines: 1,
+            removed_lines: 1,
+            ..Default::default()
+        }];
+        let mut app = App::new(
+            Config::default(),
+            file_diffs,
+            OperationMode::GitWorkingDirectory,
+        )
+        .unwrap();
+
+        let mut terminal = Terminal::new(TestBackend::new(5, 3)).unwrap();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let text = buffer_to_string(terminal.backend().buffer());
+        assert!(text.contains("Too"), "{text}");
+
+        // Sizes around the threshold render without panicking
+        for (width, height) in [(0, 0), (1, 1), (MIN_UI_WIDTH, MIN_UI_HEIGHT), (21, 9)] {
+            let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+            terminal.draw(|f| ui(f, &mut app)).unwrap();
+            app.enter_search_mode();
+            terminal.draw(|f| ui(f, &mut app)).unwrap();
+            app.exit_search_mode();
+        }
+
+        // So does an error dialog with more text than fits
+        app.show_error(&anyhow::anyhow!("{}", "long error ".repeat(50)));
+        let mut terminal = Terminal::new(TestBackend::new(MIN_UI_WIDTH, MIN_UI_HEIGHT)).unwrap();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+
+        // Pane widths are computed without overflowing on very wide terminals
+        assert_eq!(app.diff_pane_width(2000), 1600);
+        assert_eq!(
+            app.resolve_template_variables("{{diffAreaWidth}}", 2000),
+            "1596"
+        );
+    }
+
+    #[test]
+    fn test_render_status_format() {
+        let backend = TestBackend::new(60, 3);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut config = Config::default();
+        config.git.instant = true;
+        config.ui.status_format = Some("{path} [{stats}] {index} {branch} ({percent".to_string());
+        let file_diffs =
+            DiffParser::parse("diff --git a/a.rs b/a.rs\n+a\ndiff --git a/b.rs b/b.rs\n-b\n");
+        let mut app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+
+        terminal
+            .draw(|f| render_status_line(f, Rect::new(0, 0, 60, 3), &app))
+            .unwrap();
+        let content = buffer_to_string(terminal.backend().buffer());
+        // Unknown placeholders and unclosed braces are shown as written
+        assert!(content.contains("a.rs [+1 -0] 1/2 {branch} ({percent"));
+
+        // Transient messages still follow the formatted status
+        app.status_message = Some("Copied".to_string());
+        terminal
+            .draw(|f| render_status_line(f, Rect::new(0, 0, 60, 3), &app))
+            .unwrap();
+        assert!(buffer_to_string(terminal.backend().buffer()).contains("| Copied"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_debug_pager_split() {
+        let backend = TestBackend::new(60, 6);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut config = Config::default();
+        config.git.instant = true;
+        config.git.paging.pager = "tr a-z A-Z".to_string();
+        let file_diffs = DiffParser::parse("diff --git a/a.rs b/a.rs\n+added line\n");
+        let mut app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+        app.debug_pager = true;
+        app.update_diff_content();
+        assert!(app.shows_debug_split());
+
+        let mut draw = |app: &mut App| {
+            terminal
+                .draw(|f| render_diff_content(f, Rect::new(0, 0, 60, 6), app))
+                .unwrap();
+            buffer_to_string(terminal.backend().buffer())
+        };
+        draw(&mut app);
+        wait_for_diff_tool(&mut app);
+
+        // Git's diff on the left, the pager's output on the right
+        let content = draw(&mut app);
+        let row = content
+            .lines()
+            .find(|line| line.contains("+added line"))
+            .unwrap();
+        assert!(row.find("+added line") < row.find("+ADDED LINE"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resize_refetch_indicator() {
+        let backend = TestBackend::new(120, 6);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut config = Config::default();
+        config.git.instant = true;
+        config.git.paging.pager = "sleep 0.3".to_string();
+        let file_diffs = DiffParser::parse("diff --git a/a.rs b/a.rs\n+added line\n");
+        let mut app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+        app.update_diff_content();
+        wait_for_diff_tool(&mut app);
+        assert!(!app.is_resizing());
+
+        // The old output stays up while the pager re-runs at the new width
+        app.refresh_diff_with_area_width(100, 120);
+        assert!(app.is_resizing());
+        terminal
+            .draw(|f| {
+                let area = f.area();
+                render_diff_content(f, area, &mut app)
+            })
+            .unwrap();
+        assert!(buffer_to_string(terminal.backend().buffer()).contains("resizing…"));
+
+        wait_for_diff_tool(&mut app);
+        assert!(!app.is_resizing());
+    }
+
     #[test]
     fn test_render_file_list() {
         let backend = TestBackend::new(40, 20);
@@ -1187,21 +4223,15 @@ mod tests {
         let file_diffs = vec![
             FileDiff {
                 filename: "test1.rs".to_string(),
-                old_path: None,
-                new_path: None,
                 content: "test content".to_string(),
                 added_lines: 1,
-                removed_lines: 0,
-                diff_key: None,
+                ..Default::default()
             },
             FileDiff {
                 filename: "test2.rs".to_string(),
-                old_path: None,
-                new_path: None,
                 content: "test content 2".to_string(),
-                added_lines: 0,
                 removed_lines: 1,
-                diff_key: None,
+                ..Default::default()
             },
         ];
         let mut app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
@@ -1220,6 +4250,95 @@ mod tests {
         assert!(content.contains("test2.rs"));
     }
 
+    #[test]
+    fn test_hide_patterns() {
+        let file_diffs: Vec<FileDiff> = [
+            "Cargo.lock",
+            "crates/core/Cargo.lock",
+            "src/main.rs",
+            "web/app.min.js",
+        ]
+        .iter()
+        .map(|path| FileDiff {
+            filename: path.to_string(),
+            content: format!("diff of {path}"),
+            added_lines: 1,
+            ..Default::default()
+        })
+        .collect();
+        let file_paths = |app: &App| {
+            app.get_current_file_tree_items()
+                .iter()
+                .filter(|item| !item.is_directory)
+                .map(|item| item.full_path.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let mut config = Config::default();
+        config.ui.hide_patterns = vec!["Cargo.lock".to_string(), "*.min.js".to_string()];
+        let mut app = App::new(
+            config.clone(),
+            file_diffs.clone(),
+            OperationMode::GitWorkingDirectory,
+        )
+        .unwrap();
+        assert_eq!(file_paths(&app), ["src/main.rs"]);
+        assert_eq!(app.hidden_count, 3);
+        assert_eq!(app.diff_output, "diff of src/main.rs");
+
+        // Hidden files come back with the toggle, and go again with the next one
+        app.toggle_hidden_files();
+        assert_eq!(file_paths(&app).len(), 4);
+        assert_eq!(app.hidden_count, 0);
+        app.toggle_hidden_files();
+        assert_eq!(file_paths(&app), ["src/main.rs"]);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Hiding 3 files matching ui.hidePatterns")
+        );
+
+        config.ui.hide_patterns = vec!["src/[".to_string()];
+        assert!(App::new(config, file_diffs, OperationMode::GitWorkingDirectory).is_err());
+
+        let mut app = App::new(
+            Config::default(),
+            vec![],
+            OperationMode::GitWorkingDirectory,
+        )
+        .unwrap();
+        app.toggle_hidden_files();
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("No ui.hidePatterns configured")
+        );
+    }
+
+    #[test]
+    fn test_render_expanded_dir_counts() {
+        let render = |expanded_dir_counts: bool| {
+            let mut config = Config::default();
+            config.ui.expanded_dir_counts = expanded_dir_counts;
+            let file_diffs = ["src/a.rs", "src/b.rs", "README.md"]
+                .iter()
+                .map(|path| FileDiff {
+                    filename: path.to_string(),
+                    content: "content".to_string(),
+                    added_lines: 1,
+                    ..Default::default()
+                })
+                .collect();
+            let mut app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+            let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+            terminal
+                .draw(|f| render_file_list(f, Rect::new(0, 0, 40, 10), &mut app))
+                .unwrap();
+            buffer_to_string(terminal.backend().buffer())
+        };
+
+        assert!(render(true).contains("src (2)"));
+        assert!(!render(false).contains("(2)"));
+    }
+
     #[test]
     fn test_render_diff_content() {
         let backend = TestBackend::new(60, 20);
@@ -1240,15 +4359,1622 @@ mod tests {
         assert!(content.contains("No diff content available"));
     }
 
-    fn buffer_to_string(buffer: &Buffer) -> String {
-        let mut result = String::new();
-        for y in 0..buffer.area().height {
-            for x in 0..buffer.area().width {
-                let cell = buffer.cell((x, y)).unwrap();
-                result.push_str(cell.symbol());
-            }
-            result.push('\n');
-        }
-        result
+    #[test]
+    fn test_highlight_whitespace_errors() {
+        let backend = TestBackend::new(40, 8);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut config = Config::default();
+        config.ui.highlight_whitespace = true;
+        let mut app = App::new(config, vec![], OperationMode::GitWorkingDirectory).unwrap();
+        app.diff_output = "+++ b/a.rs  \n+let a = 1;  \n-let b = 2;  \n+ \t c\n".to_string();
+
+        terminal
+            .draw(|f| render_diff_content(f, Rect::new(0, 0, 40, 8), &mut app))
+            .unwrap();
+
+        // Rows start after the border; the line text starts at column 1
+        let buffer = terminal.backend().buffer();
+        let bg = |x: u16, y: u16| buffer[(x, y)].bg;
+        let removed = app.theme.colors.status_removed.0;
+        let modified = app.theme.colors.status_modified.0;
+        assert_ne!(bg(11, 1), removed); // File headers are not added lines
+        assert_eq!(bg(12, 2), removed); // Trailing whitespace on an added line
+        assert_eq!(bg(13, 2), removed);
+        assert_ne!(bg(11, 2), removed);
+        assert_ne!(bg(12, 3), removed); // Removed lines are left alone
+        assert_eq!(bg(2, 4), modified); // Tabs mixed with spaces in the indentation
+        assert_ne!(bg(5, 4), modified);
+    }
+
+    #[test]
+    fn test_highlight_conflict_sections() {
+        let backend = TestBackend::new(30, 10);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut app = App::new(Config::default(), vec![], OperationMode::GitConflicts).unwrap();
+        app.diff_output =
+            "keep\n<<<<<<< HEAD\nours\n||||||| base\nbase\n=======\ntheirs\n>>>>>>> other\n"
+                .to_string();
+
+        terminal
+            .draw(|f| render_diff_content(f, Rect::new(0, 0, 30, 10), &mut app))
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let colors = &app.theme.colors;
+        assert_ne!(buffer[(1, 1)].fg, colors.status_added.0); // Outside any conflict
+        assert!(buffer[(1, 2)].modifier.contains(Modifier::BOLD)); // <<<<<<< marker
+        assert_eq!(buffer[(1, 3)].fg, colors.status_added.0);
+        assert_eq!(buffer[(1, 5)].fg, colors.text_dim.0);
+        assert_eq!(buffer[(1, 7)].fg, colors.status_modified.0);
+        assert!(buffer[(1, 8)].modifier.contains(Modifier::BOLD)); // >>>>>>> marker
+    }
+
+    #[test]
+    fn test_diff_scrollbars() {
+        let mut app = App::new(
+            Config::default(),
+            vec![],
+            OperationMode::GitWorkingDirectory,
+        )
+        .unwrap();
+        app.diff_output = (0..100).map(|i| format!("+line {i}\n")).collect();
+
+        let right_border = |app: &mut App| {
+            let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+            terminal
+                .draw(|f| render_diff_content(f, Rect::new(0, 0, 40, 10), app))
+                .unwrap();
+            let buffer = terminal.backend().buffer().clone();
+            (1..9)
+                .map(|y| buffer[(39, y)].symbol().to_string())
+                .collect::<String>()
+        };
+
+        assert!(right_border(&mut app).contains('█'));
+
+        // Long lines wrap, so there is nothing to scroll sideways
+        app.set_diff_output(format!("+{}\n", "x".repeat(200)));
+        let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+        terminal
+            .draw(|f| render_diff_content(f, Rect::new(0, 0, 40, 10), &mut app))
+            .unwrap();
+        let bottom_border: String = (1..39)
+            .map(|x| terminal.backend().buffer()[(x, 9)].symbol().to_string())
+            .collect();
+        assert!(!bottom_border.contains('▬'));
+        app.diff_output = (0..100).map(|i| format!("+line {i}\n")).collect();
+
+        app.config.ui.scrollbars = false;
+        assert!(!right_border(&mut app).contains('█'));
+    }
+
+    #[test]
+    fn test_scroll_percentage() {
+        let config = Config::default();
+        let mut app = App::new(config, vec![], OperationMode::GitWorkingDirectory).unwrap();
+        app.diff_output = (0..30).map(|i| format!("line {i}\n")).collect();
+
+        // Everything fits on screen
+        app.clamp_scroll(42, 80);
+        assert_eq!(app.scroll_percentage(), None);
+
+        app.clamp_scroll(12, 80);
+        app.vertical_scroll = 0;
+        assert_eq!(app.scroll_percentage(), Some(0));
+        app.vertical_scroll = 10;
+        assert_eq!(app.scroll_percentage(), Some(50));
+        app.vertical_scroll = 100;
+        assert_eq!(app.scroll_percentage(), Some(100));
+    }
+
+    #[test]
+    fn test_cached_line_widths() {
+        let config = Config::default();
+        let mut app = App::new(config, vec![], OperationMode::GitWorkingDirectory).unwrap();
+        // A minified file: one huge line
+        app.set_diff_output(format!("+{}\n", "x".repeat(200_000)));
+        app.clamp_scroll(12, 82);
+        assert_eq!(app.cached_max_width, Some(u16::MAX));
+        assert_eq!(app.horizontal_page_size(), 40);
+
+        // Scrolling right stops at the end of the longest line
+        app.scroll_right(u16::MAX);
+        assert_eq!(app.horizontal_scroll, u16::MAX - 80);
+
+        // New content is measured again
+        app.set_diff_output("short\n".to_string());
+        assert_eq!(app.cached_max_width, None);
+        app.clamp_scroll(12, 82);
+        assert_eq!(app.cached_max_width, Some(5));
+        assert_eq!(app.horizontal_scroll, 0);
+    }
+
+    #[test]
+    fn test_changed_lines_only() {
+        let config = Config::default();
+        let mut app = App::new(config, vec![], OperationMode::GitWorkingDirectory).unwrap();
+        let context: String = (0..20).map(|i| format!(" context {i}\n")).collect();
+        app.set_diff_output(format!(
+            "@@ -1,22 +1,22 @@\n{context}\x1b[31m-old\x1b[m\n\x1b[32m+new\x1b[m\n"
+        ));
+
+        app.toggle_changed_lines_only();
+        app.clamp_scroll(12, 82);
+        assert_eq!(app.visible_lines.as_deref(), Some(&[0, 21, 22][..]));
+        assert_eq!(app.diff_row_count, 3);
+
+        // The rendered lines keep their colors
+        let mut terminal = Terminal::new(TestBackend::new(40, 6)).unwrap();
+        terminal
+            .draw(|f| render_diff_content(f, Rect::new(0, 0, 40, 6), &mut app))
+            .unwrap();
+        let buffer = terminal.backend().buffer().clone();
+        let row = |y: u16| {
+            (1..5)
+                .map(|x| buffer[(x, y)].symbol().to_string())
+                .collect::<String>()
+        };
+        assert_eq!(row(2), "-old");
+        assert_eq!(row(3), "+new");
+        assert_eq!(buffer[(1, 3)].fg, Color::Green);
+
+        // Positions map back to the full diff
+        app.vertical_scroll = 1;
+        assert_eq!(app.top_diff_line(), 21);
+
+        app.toggle_changed_lines_only();
+        app.clamp_scroll(12, 82);
+        assert_eq!(app.diff_row_count, 23);
+        assert_eq!(app.top_diff_line(), 0);
+    }
+
+    #[test]
+    fn test_wrapped_lines_extend_scroll() {
+        let config = Config::default();
+        let mut app = App::new(config, vec![], OperationMode::GitWorkingDirectory).unwrap();
+        // 5 short lines and one line that wraps into 3 rows of a 20 column pane
+        app.diff_output = format!("{}{}\n", "short\n".repeat(5), "x".repeat(50));
+
+        app.vertical_scroll = 100;
+        app.clamp_scroll(6, 22);
+        assert_eq!(app.diff_row_count, 8);
+        assert_eq!(app.vertical_scroll, 4);
+    }
+
+    #[test]
+    fn test_top_diff_line_skips_wrapped_rows() {
+        let config = Config::default();
+        let mut app = App::new(config, vec![], OperationMode::GitWorkingDirectory).unwrap();
+        // The first hunk's added line wraps into 3 rows of a 20 column pane
+        app.set_diff_output(format!(
+            "@@ -1 +1 @@\n+{}\n@@ -10 +10 @@\n+second\n",
+            "x".repeat(50)
+        ));
+        app.clamp_scroll(12, 22);
+        assert_eq!(app.diff_row_count, 6);
+
+        // The second hunk's header is on row 4 but is line 2 of the diff
+        app.vertical_scroll = 4;
+        assert_eq!(app.top_diff_line(), 2);
+        let plain_output = strip_ansi_escapes::strip_str(&app.diff_output);
+        assert_eq!(
+            DiffParser::hunk_header_at_line(&plain_output, app.top_diff_line()),
+            Some("@@ -10 +10 @@")
+        );
+
+        // Rows inside the wrapped line belong to it
+        app.vertical_scroll = 3;
+        assert_eq!(app.top_diff_line(), 1);
+
+        // 3g jumps to the second hunk's header, below the wrapped rows
+        app.focus = Pane::Diff;
+        app.jump_to_start(Some(3));
+        assert_eq!(app.vertical_scroll, 4);
+        app.jump_to_end(Some(4));
+        assert_eq!(app.vertical_scroll, 5);
+    }
+
+    #[test]
+    fn test_sticky_scroll() {
+        let file_diff = |name: &str, lines: usize| FileDiff {
+            filename: name.to_string(),
+            content: "+line\n".repeat(lines),
+            added_lines: lines,
+            ..Default::default()
+        };
+        let file_diffs = vec![file_diff("a.rs", 100), file_diff("b.rs", 30)];
+        let mut config = Config::default();
+        config.git.instant = true;
+
+        // By default switching files returns to the top
+        let mut app = App::new(
+            config.clone(),
+            file_diffs.clone(),
+            OperationMode::GitWorkingDirectory,
+        )
+        .unwrap();
+        app.vertical_scroll = 50;
+        app.select_next(1);
+        assert_eq!(app.vertical_scroll, 0);
+
+        // Sticky scroll keeps the offset, clamped to the new file's length
+        config.ui.sticky_scroll = true;
+        let mut app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+        app.vertical_scroll = 50;
+        app.select_next(1);
+        assert_eq!(app.vertical_scroll, 50);
+        app.clamp_scroll(12, 80);
+        assert_eq!(app.vertical_scroll, 20);
+
+        app.toggle_sticky_scroll();
+        assert!(!app.sticky_scroll);
+        assert_eq!(app.status_message.as_deref(), Some("Sticky scroll off"));
+    }
+
+    #[test]
+    fn test_toggle_hunk_checked() {
+        let file_diffs = vec![FileDiff {
+            filename: "a.rs".to_string(),
+            content: "diff --git a/a.rs b/a.rs\n@@ -1 +1 @@\n-a\n+b\n@@ -10 +10 @@\n-c\n+d\n"
+                .to_string(),
+            added_lines: 2,
+            removed_lines: 2,
+            diff_key: None, // Nothing is persisted without a key
+            ..Default::default()
+        }];
+        let mut config = Config::default();
+        config.git.instant = true;
+        let mut app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+        app.update_diff_content();
+
+        // Checking one of two hunks leaves the file partially checked
+        app.toggle_hunk_checked();
+        assert_eq!(app.status_message.as_deref(), Some("1/2 hunks checked"));
+        assert!(app.is_partially_checked("a.rs"));
+        assert!(app.is_hunk_checked("a.rs", "@@ -1 +1 @@"));
+        assert!(!app.is_hunk_checked("a.rs", "@@ -10 +10 @@"));
+
+        // Checking the last hunk checks the file
+        app.vertical_scroll = 4;
+        app.toggle_hunk_checked();
+        assert!(app.checked_files.contains("a.rs"));
+        assert!(!app.is_partially_checked("a.rs"));
+
+        // Unchecking a hunk of a checked file keeps the other hunks checked
+        app.toggle_hunk_checked();
+        assert!(!app.checked_files.contains("a.rs"));
+        assert!(app.is_hunk_checked("a.rs", "@@ -1 +1 @@"));
+        assert!(app.is_partially_checked("a.rs"));
+
+        // Toggling the whole file drops the per-hunk state
+        app.toggle_file_checked();
+        app.toggle_file_checked();
+        assert!(!app.is_partially_checked("a.rs"));
+    }
+
+    #[test]
+    fn test_check_toggle_flashes_row() {
+        let file_diffs = DiffParser::parse("diff --git a/a.rs b/a.rs\n+a\n");
+        let mut config = Config::default();
+        config.git.instant = true;
+        let mut app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+
+        app.toggle_file_checked();
+        assert!(app.checked_files.contains("a.rs"));
+        assert!(app.is_check_flashing("a.rs"));
+        assert!(!app.is_check_flashing("b.rs"));
+
+        // A zero duration turns the flash off
+        app.config.ui.check_flash_ms = 0;
+        app.toggle_file_checked();
+        assert!(!app.is_check_flashing("a.rs"));
+    }
+
+    #[test]
+    fn test_toggle_directory_checked() {
+        // No index lines, so nothing is persisted
+        let file_diffs = DiffParser::parse(
+            "diff --git a/src/a.rs b/src/a.rs\n+a\ndiff --git a/src/ui/b.rs b/src/ui/b.rs\n+b\n",
+        );
+        let mut config = Config::default();
+        config.git.instant = true;
+        let mut app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+        let index_of = |app: &App, path: &str| {
+            app.file_tree_items
+                .iter()
+                .position(|item| item.full_path == path)
+                .unwrap()
+        };
+        let mark =
+            |app: &App, path: &str| app.check_mark(&app.file_tree_items[index_of(app, path)]);
+
+        // Checking one file leaves its parent directories partially checked
+        app.selected_index = index_of(&app, "src/ui/b.rs");
+        app.toggle_file_checked();
+        assert_eq!(mark(&app, "src/ui"), CheckMark::Checked);
+        assert_eq!(mark(&app, "src"), CheckMark::Partial);
+        assert_eq!(mark(&app, "src/a.rs"), CheckMark::Unchecked);
+
+        // A partially checked directory checks everything under it
+        app.selected_index = index_of(&app, "src");
+        app.toggle_file_checked();
+        assert!(app.checked_files.contains("src/a.rs"));
+        assert!(app.checked_files.contains("src/ui/b.rs"));
+        assert_eq!(mark(&app, "src"), CheckMark::Checked);
+
+        // A fully checked directory unchecks everything under it
+        app.toggle_file_checked();
+        assert!(app.checked_files.is_empty());
+        assert_eq!(mark(&app, "src"), CheckMark::Unchecked);
+    }
+
+    #[test]
+    fn test_sidebar_resize_clamped() {
+        let config = Config::default();
+        let mut app = App::new(config, vec![], OperationMode::GitWorkingDirectory).unwrap();
+        assert_eq!(app.sidebar_width, 20);
+
+        for _ in 0..20 {
+            app.grow_sidebar();
+        }
+        assert_eq!(app.sidebar_width, MAX_SIDEBAR_WIDTH);
+
+        for _ in 0..20 {
+            app.shrink_sidebar();
+        }
+        assert_eq!(app.sidebar_width, MIN_SIDEBAR_WIDTH);
+    }
+
+    #[test]
+    fn test_width_offset() {
+        let mut config = Config::default();
+        config.git.paging.pager = "delta -w={{diffColumnWidth}} --total={{width}}".to_string();
+        let mut app = App::new(config, vec![], OperationMode::GitWorkingDirectory).unwrap();
+        let command = |app: &App| {
+            app.resolve_template_variables_with_area_width(&app.config.git.paging.pager, 62, 100)
+        };
+        assert_eq!(command(&app), "delta -w=24 --total=100");
+
+        app.last_refresh_width = 62;
+        app.adjust_width_offset(WIDTH_OFFSET_STEP);
+        assert_eq!(command(&app), "delta -w=26 --total=102");
+        // The diff tool is re-run on the next render
+        assert_eq!(app.last_refresh_width, 0);
+
+        for _ in 0..100 {
+            app.adjust_width_offset(-WIDTH_OFFSET_STEP);
+        }
+        assert_eq!(command(&app), "delta -w=1 --total=1");
+    }
+
+    #[test]
+    fn test_count_prefix() {
+        let config = Config::default();
+        let mut app = App::new(config, vec![], OperationMode::GitWorkingDirectory).unwrap();
+
+        // A leading zero is not a count
+        assert!(!app.push_count_digit('0'));
+        assert!(app.push_count_digit('1'));
+        assert!(app.push_count_digit('0'));
+        assert_eq!(app.take_count(), Some(10));
+        assert_eq!(app.take_count(), None);
+
+        for _ in 0..5 {
+            app.push_count_digit('9');
+        }
+        assert_eq!(app.take_count(), Some(MAX_COUNT_PREFIX));
+    }
+
+    #[test]
+    fn test_list_paging() {
+        let diff: String = (0..12)
+            .map(|i| format!("diff --git a/f{i:02} b/f{i:02}\n+{i}\n"))
+            .collect();
+        let mut config = Config::default();
+        config.git.instant = true;
+        let mut app = App::new(
+            config,
+            DiffParser::parse(&diff),
+            OperationMode::GitWorkingDirectory,
+        )
+        .unwrap();
+        // Before the first render, pages are a single item
+        assert_eq!(app.list_page_size(), 1);
+
+        app.file_list_viewport_height = 5;
+        app.select_next(app.list_page_size());
+        assert_eq!(app.selected_index, 5);
+        app.select_next(app.list_page_size() * 2);
+        assert_eq!(app.selected_index, 11);
+        assert_eq!(app.file_list_state.selected(), Some(11));
+        app.select_previous(app.list_page_size());
+        assert_eq!(app.selected_index, 6);
+    }
+
+    #[test]
+    fn test_focus_routes_navigation() {
+        let diff: String = (0..3)
+            .map(|i| format!("diff --git a/f{i} b/f{i}\n+{i}\n"))
+            .collect();
+        let mut config = Config::default();
+        config.git.instant = true;
+        let mut app = App::new(
+            config,
+            DiffParser::parse(&diff),
+            OperationMode::GitWorkingDirectory,
+        )
+        .unwrap();
+
+        // The file list has focus at first
+        app.move_down(2);
+        assert_eq!(app.selected_index, 2);
+        assert_eq!(app.vertical_scroll, 0);
+
+        // With the diff focused, the same keys scroll and keep the selection
+        app.toggle_focus();
+        app.diff_viewport_height = 10;
+        app.move_down(3);
+        app.page_down(1);
+        assert_eq!(app.vertical_scroll, 13);
+        app.move_up(1);
+        assert_eq!(app.vertical_scroll, 12);
+        app.jump_to_start(Some(5));
+        assert_eq!(app.vertical_scroll, 4);
+        app.jump_to_start(None);
+        assert_eq!(app.vertical_scroll, 0);
+        assert_eq!(app.selected_index, 2);
+
+        app.toggle_focus();
+        app.jump_to_start(None);
+        assert_eq!(app.selected_index, 0);
+    }
+
+    #[test]
+    fn test_auto_collapse_depth() {
+        let mut config = Config::default();
+        config.ui.auto_collapse_depth = Some(2);
+        config.ui.hide_patterns = vec!["x/**".to_string()];
+        let file_diffs = vec![
+            FileDiff {
+                filename: "a/b/c/d/file.rs".to_string(),
+                content: "test content".to_string(),
+                added_lines: 1,
+                ..Default::default()
+            },
+            FileDiff {
+                filename: "x/y/z/hidden.rs".to_string(),
+                content: "test content".to_string(),
+                ..Default::default()
+            },
+        ];
+        let mut app = App::new(
+            config,
+            file_diffs,
+            OperationMode::Compare {
+                target1: "a".to_string(),
+                target2: "b".to_string(),
+                force_ref: false,
+            },
+        )
+        .unwrap();
+
+        // The first two levels stay open, everything below starts collapsed
+        let paths: Vec<&str> = app
+            .file_tree_items
+            .iter()
+            .map(|item| item.full_path.as_str())
+            .collect();
+        assert_eq!(paths, ["a", "a/b", "a/b/c"]);
+        assert!(app.collapsed_directories.contains("a/b/c/d"));
+        // Directories of hidden files are not in the tree, so they are not collapsed either
+        assert!(!app.collapsed_directories.contains("x/y/z"));
+
+        // Collapsed directories still expand on demand
+        app.selected_index = 2;
+        app.toggle_directory();
+        assert_eq!(app.file_tree_items.len(), 4);
+    }
+
+    #[test]
+    fn test_collapse_all_selects_visible_ancestor() {
+        let config = Config::default();
+        let file_diffs = vec![FileDiff {
+            filename: "src/nested/lib.rs".to_string(),
+            content: "test content".to_string(),
+            added_lines: 1,
+            ..Default::default()
+        }];
+        let mut app = App::new(
+            config,
+            file_diffs,
+            OperationMode::Compare {
+                target1: "a".to_string(),
+                target2: "b".to_string(),
+                force_ref: false,
+            },
+        )
+        .unwrap();
+
+        // Select the file inside the nested directory
+        app.selected_index = 2;
+        app.collapse_all_directories();
+        assert_eq!(app.file_tree_items.len(), 1);
+        assert_eq!(app.file_tree_items[app.selected_index].full_path, "src");
+
+        app.expand_all_directories();
+        assert_eq!(app.file_tree_items.len(), 3);
+        assert!(app.collapsed_directories.is_empty());
+    }
+
+    #[test]
+    fn test_configure_child_env() {
+        let overrides = [("COLUMNS", "120".to_string())];
+        let mut paging = GitPagingConfig::default();
+
+        // Terminal overrides are opt-in
+        let mut cmd = Command::new("true");
+        configure_child_env(&mut cmd, &paging, &overrides);
+        assert!(!cmd.get_envs().any(|(key, _)| key == "COLUMNS"));
+
+        paging.override_terminal_env = true;
+        let mut cmd = Command::new("true");
+        configure_child_env(&mut cmd, &paging, &overrides);
+        assert!(
+            cmd.get_envs()
+                .any(|(key, value)| key == "COLUMNS" && value == Some("120".as_ref()))
+        );
+    }
+
+    #[test]
+    fn test_search_history_recall() {
+        let config = Config::default();
+        let mut app = App::new(config, vec![], OperationMode::GitWorkingDirectory).unwrap();
+        app.search_history = vec!["old".to_string(), "new".to_string()];
+        app.enter_search_mode();
+
+        app.recall_previous_search();
+        assert_eq!(app.search_query, "new");
+        app.recall_previous_search();
+        assert_eq!(app.search_query, "old");
+        app.recall_previous_search();
+        assert_eq!(app.search_query, "old");
+
+        app.recall_next_search();
+        assert_eq!(app.search_query, "new");
+        app.recall_next_search();
+        assert!(app.search_query.is_empty());
+    }
+
+    #[test]
+    fn test_fuzzy_search_ranking() {
+        let file_diffs = ["src/mirrors.rs", "src/main.rs", "README.md"]
+            .iter()
+            .map(|name| FileDiff {
+                filename: name.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        let config = Config::default();
+        let mut app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+        app.enter_search_mode();
+
+        for c in "mainrs".chars() {
+            app.add_search_char(c);
+        }
+        assert_eq!(app.filtered_file_tree_items[0].full_path, "src/main.rs");
+        assert!(
+            !app.filtered_file_tree_items
+                .iter()
+                .any(|item| item.full_path == "README.md")
+        );
+    }
+
+    #[test]
+    fn test_jump_between_search_matches() {
+        let patch = "diff --git a/src/parse.rs b/src/parse.rs\n--- a/src/parse.rs\n+++ b/src/parse.rs\n@@ -1,3 +1,3 @@\n fn parse() {\n-    old();\n+    new();\n@@ -10,2 +10,2 @@\n-parse(a)\n+parse(b)\ndiff --git a/src/parse_util.rs b/src/parse_util.rs\n--- a/src/parse_util.rs\n+++ b/src/parse_util.rs\n@@ -1 +1 @@\n-old\n+new\ndiff --git a/tests/parser.rs b/tests/parser.rs\n--- a/tests/parser.rs\n+++ b/tests/parser.rs\n@@ -1 +1 @@\n-Parse()\n+other()\n";
+        let config = Config::default();
+        let mut app = App::new(
+            config,
+            DiffParser::parse(patch),
+            OperationMode::GitWorkingDirectory,
+        )
+        .unwrap();
+        app.piped_input = true;
+        let selected_path = |app: &App| {
+            app.get_current_file_tree_items()[app.selected_index]
+                .full_path
+                .clone()
+        };
+
+        // Without a search there is nothing to jump between
+        app.jump_to_search_match_file(true, 1);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Search with / to jump between matches")
+        );
+
+        app.enter_search_mode();
+        for c in "parse".chars() {
+            app.add_search_char(c);
+        }
+        app.confirm_search();
+        assert_eq!(app.content_match_files.len(), 2);
+        app.jump_to_position(1);
+        assert_eq!(selected_path(&app), "src/parse.rs");
+        assert_eq!(
+            app.search_match_summary().as_deref(),
+            Some("file 1/2 with matches, line 1/3 in this file")
+        );
+
+        // Lines step through this file's matches
+        app.jump_to_search_match_line(true, 1);
+        app.jump_to_search_match_line(true, 1);
+        assert_eq!(app.search_match_line, Some(8));
+        assert_eq!(
+            app.search_match_summary().as_deref(),
+            Some("file 1/2 with matches, line 2/3 in this file")
+        );
+        app.jump_to_search_match_line(false, 1);
+        assert_eq!(app.search_match_line, Some(4));
+
+        // Files skip the one without a match, and stop at the last one
+        app.jump_to_search_match_file(true, 1);
+        assert_eq!(selected_path(&app), "tests/parser.rs");
+        assert_eq!(
+            app.search_match_summary().as_deref(),
+            Some("file 2/2 with matches, line 1/1 in this file")
+        );
+        app.jump_to_search_match_file(true, 1);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("No later file contains 'parse'")
+        );
+        app.jump_to_search_match_file(false, 1);
+        assert_eq!(selected_path(&app), "src/parse.rs");
+    }
+
+    #[test]
+    fn test_toggle_files_only() {
+        let file_diffs = ["src/ui/list.rs", "src/main.rs"]
+            .iter()
+            .map(|name| FileDiff {
+                filename: name.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        let config = Config::default();
+        let mut app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+
+        app.toggle_files_only();
+        let items = app.get_current_file_tree_items();
+        assert_eq!(items.len(), 2);
+        assert!(items.iter().all(|item| !item.is_directory));
+        assert!(items.iter().any(|item| item.name == "src/ui/list.rs"));
+        assert!(app.shows_full_paths());
+
+        // Basenames can be shown in the files-only list too
+        app.toggle_full_paths();
+        let items = app.get_current_file_tree_items();
+        assert!(items.iter().any(|item| item.label(false) == "list.rs"));
+        assert!(!app.shows_full_paths());
+
+        app.select_next(1);
+        let selected = app.get_current_file_tree_items()[app.selected_index]
+            .full_path
+            .clone();
+        app.toggle_files_only();
+        assert_eq!(
+            app.get_current_file_tree_items()[app.selected_index].full_path,
+            selected
+        );
+        assert!(!app.shows_full_paths());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_diff_tool_runs_async_and_drops_stale_results() {
+        let config = Config::default();
+        let mut app = App::new(config, vec![], OperationMode::GitWorkingDirectory).unwrap();
+
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "sleep 0.2; cat"]);
+        let slow_job = DiffToolJob::Stdin {
+            cmd,
+            name: "sh".to_string(),
+            input: "slow".to_string(),
+        };
+        app.spawn_diff_tool(slow_job, None);
+        assert!(app.pending_diff.is_some());
+
+        // Selecting another file supersedes the slow job
+        app.spawn_diff_tool(DiffToolJob::Ready("fast".to_string()), None);
+        assert!(app.pending_diff.is_none());
+        assert_eq!(app.diff_output, "fast");
+
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        app.poll_diff_tool();
+        assert_eq!(app.diff_output, "fast");
+    }
+
+    #[test]
+    fn test_reload_patch_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("review.patch");
+        let patch = |name: &str| {
+            format!(
+                "diff --git a/{name} b/{name}\n--- a/{name}\n+++ b/{name}\n@@ -1 +1 @@\n-a\n+b\n"
+            )
+        };
+        std::fs::write(&path, patch("a.rs") + &patch("b.rs")).unwrap();
+
+        let path = path.to_string_lossy().to_string();
+        let file_diffs = read_patch_file(&path).unwrap();
+        let config = Config::default();
+        let mut app = App::new(
+            config,
+            file_diffs,
+            OperationMode::PatchFile { path: path.clone() },
+        )
+        .unwrap();
+        app.select_next(1);
+        assert_eq!(app.file_tree_items[app.selected_index].full_path, "b.rs");
+
+        // The selected file stays selected when files are added before it
+        std::fs::write(&path, patch("0.rs") + &patch("b.rs")).unwrap();
+        app.reload();
+        assert_eq!(app.file_tree_items.len(), 2);
+        assert_eq!(app.file_tree_items[app.selected_index].full_path, "b.rs");
+
+        // An empty diff stays in the TUI
+        std::fs::write(&path, "").unwrap();
+        app.reload();
+        assert!(app.file_tree_items.is_empty());
+        assert_eq!(app.diff_output, "No differences found.");
+    }
+
+    #[test]
+    fn test_sidebar_drag() {
+        let backend = TestBackend::new(100, 20);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let config = Config::default();
+        let mut app = App::new(config, vec![], OperationMode::GitWorkingDirectory).unwrap();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        assert_eq!(app.sidebar_border, 20);
+
+        // Dragging away from the border does nothing
+        app.handle_sidebar_drag(MouseEventKind::Down(MouseButton::Left), 50);
+        app.handle_sidebar_drag(MouseEventKind::Drag(MouseButton::Left), 40);
+        assert_eq!(app.sidebar_width, 20);
+
+        app.handle_sidebar_drag(MouseEventKind::Down(MouseButton::Left), 19);
+        app.handle_sidebar_drag(MouseEventKind::Drag(MouseButton::Left), 35);
+        assert_eq!(app.sidebar_width, 35);
+        app.handle_sidebar_drag(MouseEventKind::Drag(MouseButton::Left), 95);
+        assert_eq!(app.sidebar_width, MAX_SIDEBAR_WIDTH);
+        app.handle_sidebar_drag(MouseEventKind::Up(MouseButton::Left), 95);
+        assert!(!app.dragging_sidebar);
+    }
+
+    #[test]
+    fn test_difftool_command() {
+        let file_diffs = vec![FileDiff {
+            filename: "a.rs".to_string(),
+            added_lines: 1,
+            ..Default::default()
+        }];
+        let mut config = Config::default();
+        config.git.difftool = Some("meld".to_string());
+        let app = App::new(config, file_diffs.clone(), OperationMode::GitCached).unwrap();
+
+        let cmd = app.difftool_command().unwrap();
+        let args: Vec<_> = cmd.get_args().collect();
+        assert_eq!(
+            args,
+            [
+                "difftool",
+                "--no-prompt",
+                "--tool=meld",
+                "--cached",
+                "--",
+                "a.rs"
+            ]
+        );
+
+        let app = App::new(
+            Config::default(),
+            file_diffs,
+            OperationMode::PatchFile {
+                path: "review.patch".to_string(),
+            },
+        )
+        .unwrap();
+        assert!(app.difftool_command().is_err());
+    }
+
+    #[test]
+    fn test_status_groups() {
+        let file_diff = |filename: &str, status_group| FileDiff {
+            filename: filename.to_string(),
+            added_lines: 1,
+            status_group: Some(status_group),
+            ..Default::default()
+        };
+        let file_diffs = vec![
+            file_diff("src/lib.rs", StatusGroup::Unstaged),
+            file_diff("src/lib.rs", StatusGroup::Staged),
+            file_diff("notes.txt", StatusGroup::Untracked),
+        ];
+        let mut app = App::new(Config::default(), file_diffs, OperationMode::GitStatus).unwrap();
+
+        let rows: Vec<_> = app
+            .file_tree_items
+            .iter()
+            .map(|item| (item.depth, item.name.as_str()))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (0, "Staged changes"),
+                (1, "src"),
+                (2, "lib.rs"),
+                (0, "Unstaged changes"),
+                (1, "src"),
+                (2, "lib.rs"),
+                (0, "Untracked"),
+                (1, "notes.txt"),
+            ]
+        );
+        assert_eq!(app.file_tree_items[4].repo_path(), "src");
+
+        // Collapsing keeps the unstaged copy of the file selected, not the staged one
+        app.selected_index = 5;
+        app.collapse_all_directories();
+        assert_eq!(
+            app.file_tree_items[app.selected_index].name,
+            "Unstaged changes"
+        );
+        app.expand_all_directories();
+        assert_eq!(app.file_tree_items.len(), 8);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_instant_mode_uses_stored_diff() {
+        let file_diffs = vec![FileDiff {
+            filename: "stored.rs".to_string(),
+            content: "stored diff\n".to_string(),
+            added_lines: 1,
+            ..Default::default()
+        }];
+        let mut config = Config::default();
+        config.git.instant = true;
+        config.git.paging.pager = "tr a-z A-Z".to_string();
+
+        let mut app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+        assert!(app.git_executor.is_none());
+
+        // The pager still receives the stored content
+        app.update_diff_content();
+        wait_for_diff_tool(&mut app);
+        assert_eq!(app.diff_output.trim_end(), "STORED DIFF");
+    }
+
+    #[test]
+    fn test_render_error_dialog() {
+        let backend = TestBackend::new(80, 20);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let config = Config::default();
+        let mut app = App::new(config, vec![], OperationMode::GitWorkingDirectory).unwrap();
+
+        let error = anyhow::anyhow!("disk full").context("Some check marks could not be saved");
+        app.show_error(&error);
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let content = buffer_to_string(terminal.backend().buffer());
+        assert!(content.contains(" Error "));
+        // The whole cause chain, not Debug output
+        assert!(content.contains("Some check marks could not be saved: disk full"));
+        assert!(content.contains("Press any key to continue"));
+    }
+
+    #[test]
+    fn test_render_rename_only_placeholder() {
+        let backend = TestBackend::new(60, 8);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut config = Config::default();
+        config.git.instant = true;
+        let file_diffs = DiffParser::parse(
+            "diff --git a/old.rs b/new.rs\nsimilarity index 100%\n\
+             rename from old.rs\nrename to new.rs\n",
+        );
+        let mut app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+        app.update_diff_content();
+
+        terminal
+            .draw(|f| render_diff_content(f, Rect::new(0, 0, 60, 8), &mut app))
+            .unwrap();
+        let content = buffer_to_string(terminal.backend().buffer());
+        let rows: Vec<&str> = content.lines().collect();
+        // The explanation comes first, above git's header lines
+        assert!(rows[1].contains("No textual changes (rename only)"));
+        assert!(content.contains("rename from old.rs"));
+    }
+
+    #[test]
+    fn test_compare_targets_resolved_once() {
+        let repo = TestRepo::new();
+        repo.write("old.txt", "one\n");
+        repo.write("new.txt", "two\n");
+        repo.commit("initial");
+        let _entered = repo.enter();
+
+        // Paths are checked from the working directory, not the fixture's
+        let path = |name: &str| repo.path().join(name).to_string_lossy().into_owned();
+        let mode = OperationMode::Compare {
+            target1: path("old.txt"),
+            target2: path("new.txt"),
+            force_ref: false,
+        };
+        let config = Config::default();
+        let file_diffs = get_diffs_from_git(&mode, &config).unwrap();
+        let mut app = App::new(config, file_diffs, mode).unwrap();
+        assert!(!app.compare_refs);
+        app.selected_index = app
+            .get_current_file_tree_items()
+            .iter()
+            .position(|item| !item.is_directory)
+            .unwrap();
+
+        // Switching files does not ask git about the targets again
+        std::fs::remove_file(repo.path().join("old.txt")).unwrap();
+        let Ok(DiffToolJob::GitExternal(cmd)) = app.prepare_external_diff_via_git("cat", 78, 80)
+        else {
+            panic!("expected a git external diff");
+        };
+        assert!(cmd.get_args().any(|arg| arg == "--no-index"));
+
+        // A new comparison is resolved when it is switched to
+        app.enter_prompt(Prompt::Command);
+        app.prompt_query = "diff HEAD HEAD".to_string();
+        app.confirm_prompt();
+        assert!(app.prompt.is_none());
+        assert!(app.compare_refs);
+    }
+
+    #[test]
+    fn test_range_keeps_merge_base_semantics() {
+        let repo = TestRepo::new();
+        repo.write("a.txt", "one\n");
+        repo.commit("initial");
+        repo.write("a.txt", "two\n");
+        repo.commit("change");
+        repo.write("a.txt", "three\n");
+        repo.commit("change again");
+        let _entered = repo.enter();
+
+        let config = Config::default();
+        let mode = OperationMode::Compare {
+            target1: "HEAD~2".to_string(),
+            target2: "HEAD".to_string(),
+            force_ref: true,
+        };
+        let file_diffs = get_diffs_from_git(&mode, &config).unwrap();
+        let mut app = App::new(config, file_diffs, mode).unwrap();
+        app.selected_index = app
+            .get_current_file_tree_items()
+            .iter()
+            .position(|item| !item.is_directory)
+            .unwrap();
+        let diff_range = |app: &mut App, range: &str| {
+            app.enter_prompt(Prompt::Range);
+            app.prompt_query = range.to_string();
+            app.confirm_prompt();
+            app.diff_output.clone()
+        };
+
+        // Going back from HEAD undoes the change, but HEAD~2 has nothing since the merge base
+        let reverted = diff_range(&mut app, "HEAD..HEAD~2");
+        assert!(reverted.contains("diff --git"));
+        assert!(reverted.contains("three") && reverted.contains("one"));
+        assert!(diff_range(&mut app, "HEAD...HEAD~2").starts_with("No changes to this file"));
+    }
+
+    #[test]
+    fn test_long_submodule_log_scrolls_to_end() {
+        let mut terminal = Terminal::new(TestBackend::new(60, 8)).unwrap();
+        let mut config = Config::default();
+        config.git.instant = true;
+        let file_diffs = DiffParser::parse(
+            "diff --git a/lib b/lib\nindex 1a2b3c4..5d6e7f8 160000\n--- a/lib\n+++ b/lib\n\
+             @@ -1 +1 @@\n-Subproject commit 1a2b3c4\n+Subproject commit 5d6e7f8\n",
+        );
+        let mut app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+        app.update_diff_content();
+        app.submodule_log = (0..20).map(|i| format!("abc{i:04} Commit {i}")).collect();
+
+        // The summary, the log and a blank line come before the 7 lines of the diff
+        app.vertical_scroll = u16::MAX;
+        let mut draw = |app: &mut App| {
+            terminal
+                .draw(|f| render_diff_content(f, Rect::new(0, 0, 60, 8), app))
+                .unwrap();
+            buffer_to_string(terminal.backend().buffer())
+        };
+        draw(&mut app);
+        assert_eq!(app.diff_row_count, 29);
+        assert_eq!(app.vertical_scroll, 23);
+
+        // Scrolled up to the end of the log, its last commit is on screen
+        app.vertical_scroll = 16;
+        let content = draw(&mut app);
+        assert!(content.contains("abc0019 Commit 19"));
+        assert_eq!(app.top_diff_line(), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_assume_colored_skips_pager() {
+        let colored = "\x1b[32m+colored\x1b[0m\n";
+        let file_diffs = DiffParser::parse_input(colored);
+        let mut config = Config::default();
+        config.git.paging.pager = "tr a-z A-Z".to_string();
+        config.git.paging.assume_colored = true;
+
+        let mut app = App::new(
+            config.clone(),
+            file_diffs.clone(),
+            OperationMode::GitWorkingDirectory,
+        )
+        .unwrap();
+        app.piped_input = true;
+        app.update_diff_content();
+        assert!(app.pending_diff.is_none());
+        assert_eq!(app.diff_output, colored);
+
+        // Without the option, colored input still goes through the pager
+        config.git.paging.assume_colored = false;
+        let mut app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+        app.piped_input = true;
+        app.update_diff_content();
+        wait_for_diff_tool(&mut app);
+        assert!(app.diff_output.contains("COLORED"));
+    }
+
+    #[test]
+    fn test_file_at_revision_overlay() {
+        let mut config = Config::default();
+        config.git.instant = true;
+        let file_diffs = DiffParser::parse("diff --git a/Cargo.toml b/Cargo.toml\n+x\n");
+        let mut app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+        app.update_diff_content();
+
+        app.enter_prompt(Prompt::Revision);
+        app.prompt_query = "HEAD".to_string();
+        app.confirm_prompt();
+        assert!(app.prompt.is_none());
+        assert!(app.diff_output.contains("[package]"));
+        assert!(app.shows_file_contents());
+        assert_eq!(
+            app.overlay,
+            Some(Overlay::FileAtRevision {
+                revision: "HEAD".to_string(),
+                path: "Cargo.toml".to_string(),
+            })
+        );
+
+        app.close_overlay();
+        assert!(app.overlay.is_none());
+        assert_eq!(
+            app.diff_output,
+            "diff --git a/Cargo.toml b/Cargo.toml\n+x\n"
+        );
+    }
+
+    #[test]
+    fn test_single_path_target() {
+        let mut config = Config::default();
+        let mode = OperationMode::GitDiff {
+            target: "Cargo.toml".to_string(),
+            force_ref: false,
+        };
+        let mode = resolve_path_target(mode, &mut config).unwrap();
+        assert!(matches!(mode, OperationMode::GitWorkingDirectory));
+        assert_eq!(config.git.pathspec, ["Cargo.toml"]);
+
+        let mut config = Config::default();
+        let mode = OperationMode::GitDiff {
+            target: "HEAD".to_string(),
+            force_ref: false,
+        };
+        let mode = resolve_path_target(mode, &mut config).unwrap();
+        assert!(matches!(mode, OperationMode::GitDiff { .. }));
+        assert!(config.git.pathspec.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_diff_tool_error_is_shown_in_status_line() {
+        let file_diffs = vec![FileDiff {
+            filename: "a.rs".to_string(),
+            content: "original diff\n".to_string(),
+            added_lines: 1,
+            ..Default::default()
+        }];
+        let mut config = Config::default();
+        config.git.instant = true;
+        // cat reads all of its input before failing, so the error is always its stderr
+        config.git.paging.pager = "cat /no/such/path/for/ftdv -".to_string();
+
+        let mut app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+        app.update_diff_content();
+        wait_for_diff_tool(&mut app);
+
+        assert_eq!(app.diff_output, "original diff\n");
+        let message = app.status_message.unwrap();
+        assert!(message.starts_with("⚠ cat (pager) failed: "), "{message}");
+        assert!(message.contains("/no/such/path/for/ftdv"), "{message}");
+    }
+
+    #[test]
+    fn test_describe_diff_command() {
+        let file_diffs = vec![FileDiff {
+            filename: "a.rs".to_string(),
+            content: "original diff\n".to_string(),
+            added_lines: 1,
+            ..Default::default()
+        }];
+        let mut config = Config::default();
+        config.git.instant = true;
+        config.git.paging.pager = "delta --width {{diffColumnWidth}}".to_string();
+        config.git.paging.override_terminal_env = true;
+
+        let app = App::new(
+            config,
+            file_diffs.clone(),
+            OperationMode::GitWorkingDirectory,
+        )
+        .unwrap();
+        let description = app.describe_diff_command(100, 140).unwrap();
+        assert!(description.contains("File: a.rs"), "{description}");
+        assert!(
+            description.contains("Command: delta --width 43"),
+            "{description}"
+        );
+        assert!(description.contains("  COLUMNS=140"), "{description}");
+        assert!(description.contains("on stdin"), "{description}");
+
+        // External tools run through git, with the template resolved inside diff.external
+        let mut config = Config::default();
+        config.git.paging.external_diff_command = "difft --width={{width}}".to_string();
+        let app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+        let description = app.describe_diff_command(100, 140).unwrap();
+        assert!(
+            description.contains("Command: git -c 'diff.external=difft --width=140' "),
+            "{description}"
+        );
+        assert!(description.contains("-- a.rs"), "{description}");
+        assert!(
+            description.contains("Environment: inherited"),
+            "{description}"
+        );
+    }
+
+    #[test]
+    fn test_inter_hunk_context() {
+        let mut config = Config::default();
+        config.git.context_lines = Some(1);
+        config.git.inter_hunk_context = Some(20);
+
+        // Changes ten lines apart are one hunk instead of two
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let old_lines: Vec<String> = (1..=30).map(|n| format!("line {n}")).collect();
+        let mut new_lines = old_lines.clone();
+        new_lines[4] = "changed 5".to_string();
+        new_lines[14] = "changed 15".to_string();
+        let old_path = temp_dir.path().join("old.txt");
+        let new_path = temp_dir.path().join("new.txt");
+        std::fs::write(&old_path, old_lines.join("\n")).unwrap();
+        std::fs::write(&new_path, new_lines.join("\n")).unwrap();
+        let compare = OperationMode::Compare {
+            target1: old_path.to_string_lossy().into_owned(),
+            target2: new_path.to_string_lossy().into_owned(),
+            force_ref: false,
+        };
+        let hunks = |config: &Config| get_diffs_from_git(&compare, config).unwrap()[0].hunk_count;
+        let mut separate = config.clone();
+        separate.git.inter_hunk_context = None;
+        assert_eq!(hunks(&separate), 2);
+        assert_eq!(hunks(&config), 1);
+
+        // The option also reaches the git command that runs an external diff tool
+        config.git.paging.external_diff_command = "difft".to_string();
+        let file_diffs = vec![FileDiff {
+            filename: "a.rs".to_string(),
+            content: "original diff\n".to_string(),
+            added_lines: 1,
+            hunk_count: 1,
+            ..Default::default()
+        }];
+        let app = App::new(config, file_diffs, OperationMode::GitWorkingDirectory).unwrap();
+        let description = app.describe_diff_command(100, 140).unwrap();
+        assert!(
+            description.contains("-U1 --inter-hunk-context=20"),
+            "{description}"
+        );
+    }
+
+    #[test]
+    fn test_path_base_dir() {
+        let config = Config::default();
+        let app = App::new(
+            config,
+            vec![],
+            OperationMode::PatchFile {
+                path: "review.patch".to_string(),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            app.path_base_dir().unwrap(),
+            std::env::current_dir().unwrap()
+        );
+
+        let config = Config::default();
+        let app = App::new(config, vec![], OperationMode::GitWorkingDirectory).unwrap();
+        assert_eq!(
+            app.path_base_dir().unwrap(),
+            GitExecutor::repo_root().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cycle_diff_filter() {
+        let repo = TestRepo::new();
+        repo.write("kept.txt", "one\n");
+        repo.write("removed.txt", "gone\n");
+        repo.commit("initial");
+        repo.write("kept.txt", "two\n");
+        repo.write("added.txt", "new\n");
+        std::fs::remove_file(repo.path().join("removed.txt")).unwrap();
+        repo.commit("change");
+        let _entered = repo.enter();
+
+        let mode = OperationMode::Compare {
+            target1: "HEAD~1".to_string(),
+            target2: "HEAD".to_string(),
+            force_ref: true,
+        };
+        let config = Config::default();
+        let file_diffs = get_diffs_from_git(&mode, &config).unwrap();
+        let total = file_diffs.len();
+        let mut app = App::new(config, file_diffs, mode).unwrap();
+        let filenames = |app: &App| {
+            app.original_file_diffs
+                .iter()
+                .map(|file_diff| file_diff.filename.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(total, 3);
+
+        app.cycle_diff_filter();
+        assert_eq!(app.config.git.diff_filter.as_deref(), Some("A"));
+        assert_eq!(filenames(&app), ["added.txt"]);
+        app.cycle_diff_filter();
+        assert_eq!(app.diff_options.diff_filter.as_deref(), Some("M"));
+        assert_eq!(filenames(&app), ["kept.txt"]);
+        assert_eq!(
+            app.status_message,
+            Some(format!(
+                "Showing {} modified files",
+                app.original_file_diffs.len()
+            ))
+        );
+
+        // The selected file's diff is still fetched while the filter is on
+        app.update_diff_content();
+        assert!(!app.diff_output.is_empty());
+
+        app.cycle_diff_filter();
+        app.cycle_diff_filter();
+        app.cycle_diff_filter();
+        assert_eq!(app.config.git.diff_filter, None);
+        assert_eq!(app.original_file_diffs.len(), total);
+    }
+
+    #[test]
+    fn test_reverse_comparison() {
+        let repo = TestRepo::new();
+        repo.write("a.txt", "one\ntwo\n");
+        repo.commit("initial");
+        repo.write("a.txt", "one\nthree\nfour\n");
+        repo.commit("change");
+        let _entered = repo.enter();
+
+        let mode = OperationMode::Compare {
+            target1: "HEAD~1".to_string(),
+            target2: "HEAD".to_string(),
+            force_ref: true,
+        };
+        let totals = |app: &App| {
+            app.original_file_diffs
+                .iter()
+                .fold((0, 0), |(added, removed), file_diff| {
+                    (
+                        added + file_diff.added_lines,
+                        removed + file_diff.removed_lines,
+                    )
+                })
+        };
+
+        let config = Config::default();
+        let file_diffs = get_diffs_from_git(&mode, &config).unwrap();
+        let mut app = App::new(config, file_diffs, mode).unwrap();
+        assert_eq!(totals(&app), (2, 1));
+
+        app.reverse_comparison();
+        assert!(app.reversed);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Reversed the comparison")
+        );
+        assert_eq!(totals(&app), (1, 2));
+        assert_eq!(app.revision_label.as_deref(), Some("HEAD..HEAD~1"));
+
+        // A ref diff against the working tree is reversed with -R instead
+        repo.write("a.txt", "one\n");
+        let mode = OperationMode::GitDiff {
+            target: "HEAD".to_string(),
+            force_ref: true,
+        };
+        let config = Config::default();
+        let file_diffs = get_diffs_from_git(&mode, &config).unwrap();
+        let mut app = App::new(config, file_diffs, mode).unwrap();
+        assert_eq!(totals(&app), (0, 2));
+        app.reverse_comparison();
+        assert!(app.diff_options.to_args().contains(&"-R".to_string()));
+        assert_eq!(totals(&app), (2, 0));
+
+        // Modes with a single side cannot be reversed
+        let config = Config::default();
+        let mut app = App::new(config, vec![], OperationMode::GitWorkingDirectory).unwrap();
+        app.reverse_comparison();
+        assert!(!app.reversed);
+    }
+
+    #[test]
+    fn test_command_switches_comparison() {
+        let repo = TestRepo::new();
+        repo.write("a.txt", "one\n");
+        repo.commit("initial");
+        repo.write("a.txt", "two\n");
+        repo.write("b.txt", "new\n");
+        repo.commit("change");
+        let _entered = repo.enter();
+
+        let config = Config::default();
+        let mut app = App::new(config, vec![], OperationMode::GitWorkingDirectory).unwrap();
+
+        // Invalid commands keep the prompt open and leave the comparison alone
+        app.enter_prompt(Prompt::Command);
+        for c in "frobnicate".chars() {
+            handle_prompt_key(&mut app, KeyCode::Char(c));
+        }
+        handle_prompt_key(&mut app, KeyCode::Enter);
+        assert_eq!(app.prompt, Some(Prompt::Command));
+        assert!(
+            app.prompt_error
+                .as_deref()
+                .unwrap()
+                .starts_with("Unknown command")
+        );
+        assert!(matches!(
+            app.operation_mode,
+            OperationMode::GitWorkingDirectory
+        ));
+
+        // So do refs git cannot resolve
+        app.prompt_query = "show no-such-ref-for-ftdv".to_string();
+        app.confirm_prompt();
+        assert_eq!(app.prompt, Some(Prompt::Command));
+        assert!(app.prompt_error.is_some());
+        assert!(matches!(
+            app.operation_mode,
+            OperationMode::GitWorkingDirectory
+        ));
+
+        // Typing clears the error, and a valid command switches and refetches
+        handle_prompt_key(&mut app, KeyCode::Backspace);
+        assert!(app.prompt_error.is_none());
+        app.prompt_query = "show HEAD".to_string();
+        app.confirm_prompt();
+        assert!(app.prompt.is_none());
+        assert!(app.prompt_error.is_none());
+        assert!(matches!(
+            &app.operation_mode,
+            OperationMode::GitDiff { target, .. } if target == "HEAD^!"
+        ));
+        assert!(app.git_executor.is_some());
+        assert_eq!(
+            app.original_file_diffs
+                .iter()
+                .map(|d| d.filename.as_str())
+                .collect::<Vec<_>>(),
+            ["a.txt", "b.txt"]
+        );
+    }
+
+    #[test]
+    fn test_whole_file_context() {
+        let repo = TestRepo::new();
+        let lines: Vec<String> = (1..=20).map(|i| format!("line {i}\n")).collect();
+        repo.write("a.txt", &lines.concat());
+        repo.commit("initial");
+        let mut changed = lines.clone();
+        changed[0] = "first\n".to_string();
+        changed[19] = "last\n".to_string();
+        repo.write("a.txt", &changed.concat());
+        repo.commit("change both ends");
+        let _entered = repo.enter();
+
+        let mode = OperationMode::GitDiff {
+            target: "HEAD~1".to_string(),
+            force_ref: true,
+        };
+        let config = Config::default();
+        let file_diffs = get_diffs_from_git(&mode, &config).unwrap();
+        let mut app = App::new(config, file_diffs, mode).unwrap();
+        app.selected_index = app
+            .file_tree_items
+            .iter()
+            .position(|item| !item.is_directory)
+            .unwrap();
+        app.update_diff_content();
+        let normal_output = app.diff_output.clone();
+        // The diff is colored, so count hunk markers rather than parsing headers
+        assert_eq!(normal_output.matches("@@ -").count(), 2);
+        assert!(!normal_output.contains("line 10"));
+
+        // The whole file fits in a single hunk
+        app.toggle_whole_file_context();
+        assert!(app.shows_whole_file_context());
+        assert_eq!(app.diff_output.matches("@@ -").count(), 1);
+        assert!((2..=19).all(|i| app.diff_output.contains(&format!("\n line {i}"))));
+
+        // Toggling back restores the normal context
+        app.toggle_whole_file_context();
+        assert!(!app.shows_whole_file_context());
+        assert_eq!(app.diff_output, normal_output);
+    }
+
+    #[test]
+    fn test_toggle_raw_diff() {
+        let mut config = Config::default();
+        config.git.paging.pager = "cat".to_string();
+        let mut app = App::new(config, vec![], OperationMode::GitWorkingDirectory).unwrap();
+        assert_eq!(app.diff_command_type().display_name(), "cat (pager)");
+
+        app.toggle_raw_diff();
+        assert!(matches!(
+            app.diff_command_type(),
+            DiffCommandType::GitDefault
+        ));
+        assert_eq!(app.status_message.as_deref(), Some("Showing git diff"));
+        assert!(app.diff_options.color.is_some());
+
+        app.toggle_raw_diff();
+        assert_eq!(app.diff_command_type().display_name(), "cat (pager)");
+        assert!(app.diff_options.color.is_none());
+
+        // Without a diff tool there is nothing to switch to
+        let mut app = App::new(
+            Config::default(),
+            vec![],
+            OperationMode::GitWorkingDirectory,
+        )
+        .unwrap();
+        app.toggle_raw_diff();
+        assert!(app.override_diff_type.is_none());
+    }
+
+    #[test]
+    fn test_watch_rebuilds_only_on_change() {
+        let mode = OperationMode::GitWorkingDirectory;
+        let config = Config::default();
+        let watched = FileDiff {
+            filename: "watched.rs".to_string(),
+            content: "+watched\n".to_string(),
+            added_lines: 1,
+            ..Default::default()
+        };
+        let mut app = App::new(config, vec![watched], mode.clone()).unwrap();
+        let current_diffs = get_diffs_from_git(&mode, &app.config).unwrap();
+
+        // Not watching, or waiting for the interval: nothing is refetched
+        app.poll_watch();
+        app.watch_interval = Some(std::time::Duration::from_secs(3600));
+        app.poll_watch();
+        assert_eq!(app.original_file_diffs[0].filename, "watched.rs");
+
+        // An unchanged refetch keeps everything as it is
+        app.watch_interval = Some(std::time::Duration::ZERO);
+        app.diff_fingerprint = diff_fingerprint(&current_diffs);
+        app.poll_watch();
+        assert_eq!(app.original_file_diffs[0].filename, "watched.rs");
+
+        // A changed one replaces the diffs
+        app.diff_fingerprint = 0;
+        app.poll_watch();
+        assert_eq!(app.original_file_diffs.len(), current_diffs.len());
+        assert_eq!(app.diff_fingerprint, diff_fingerprint(&current_diffs));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_tool_output_with_invalid_utf8() {
+        // A pager that colors its output but emits a Latin-1 byte
+        let mut cmd = Command::new("printf");
+        cmd.arg("\\033[32m+caf\\351\\033[0m\\n");
+        let output = run_command_with_stdin(&mut cmd, "printf", "").unwrap();
+        assert_eq!(output, "\x1b[32m+caf\u{FFFD}\x1b[0m\n");
+    }
+
+    #[test]
+    fn test_file_limit_answers() {
+        assert_eq!(FileLimitChoice::from_answer("a\n"), FileLimitChoice::All);
+        assert_eq!(FileLimitChoice::from_answer("ALL"), FileLimitChoice::All);
+        assert_eq!(FileLimitChoice::from_answer("q"), FileLimitChoice::Quit);
+        assert_eq!(FileLimitChoice::from_answer("f"), FileLimitChoice::Truncate);
+        // Pressing Enter, or having no terminal to answer on, shows the first files
+        assert_eq!(FileLimitChoice::from_answer(""), FileLimitChoice::Truncate);
+    }
+
+    #[test]
+    fn test_truncate_diff_lines() {
+        let content: String = (0..10).map(|i| format!("line {i}\n")).collect();
+
+        assert_eq!(truncate_diff_lines(&content, 10), None);
+
+        let truncated = truncate_diff_lines(&content, 3).unwrap();
+        let lines: Vec<&str> = truncated.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[2], "line 2");
+        assert!(lines[3].starts_with("... (truncated, 7 more lines"));
+    }
+
+    #[test]
+    fn test_read_patch_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("review.patch");
+        std::fs::write(
+            &path,
+            "diff --git a/a.rs b/a.rs\n--- a/a.rs\n+++ b/a.rs\n@@ -1 +1 @@\n-old\n+new\n",
+        )
+        .unwrap();
+
+        let file_diffs = read_patch_file(&path.to_string_lossy()).unwrap();
+        assert_eq!(file_diffs.len(), 1);
+        assert_eq!(file_diffs[0].filename, "a.rs");
+
+        let missing = temp_dir.path().join("missing.patch");
+        assert!(read_patch_file(&missing.to_string_lossy()).is_err());
+    }
+
+    fn buffer_to_string(buffer: &Buffer) -> String {
+        let mut result = String::new();
+        for y in 0..buffer.area().height {
+            for x in 0..buffer.area().width {
+                let cell = buffer.cell((x, y)).unwrap();
+                result.push_str(cell.symbol());
+            }
+            result.push('\n');
+        }
+        result
+    }
+
+    /// Wait for the diff tool started for the selected file to finish
+    fn wait_for_diff_tool(app: &mut App) {
+        while app.pending_diff.is_some() {
+            app.poll_diff_tool();
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
     }
 }
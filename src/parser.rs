@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct FileDiff {
     pub filename: String,
     pub old_path: Option<String>,
@@ -8,7 +8,97 @@ pub struct FileDiff {
     pub content: String,
     pub added_lines: usize,
     pub removed_lines: usize,
+    pub hunk_count: usize, // Hunk headers ("@@", or "@@@" in combined diffs)
     pub diff_key: Option<DiffFileKey>, // Add key for persistence
+    pub change_kind: ChangeKind,
+    pub is_untracked: bool, // Synthesized from an untracked file in status mode
+    pub old_mode: Option<String>, // From "old mode" when the file mode changed
+    pub new_mode: Option<String>, // From "new mode" when the file mode changed
+    pub rename_from: Option<String>, // Source path of a rename or copy
+    pub similarity: Option<u8>, // From "similarity index N%" of a rename or copy
+    pub status_group: Option<StatusGroup>, // Section of `git status` in status mode
+    pub submodule: Option<SubmoduleChange>, // Set for submodules (gitlinks, mode 160000)
+    pub load_error: Option<String>, // Why the file could not be read, shown in place of its diff
+}
+
+/// Commits a submodule pointed to before and after a change
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubmoduleChange {
+    pub old_commit: Option<String>, // None when the submodule was added
+    pub new_commit: Option<String>, // None when removed, ends with "-dirty" for local changes
+}
+
+/// Section of `git status` a file is listed under in status mode
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusGroup {
+    Staged,
+    Unstaged,
+    Untracked,
+}
+
+impl StatusGroup {
+    /// All groups, in the order they are shown
+    pub const ALL: [StatusGroup; 3] = [
+        StatusGroup::Staged,
+        StatusGroup::Unstaged,
+        StatusGroup::Untracked,
+    ];
+
+    /// Header shown for the group in the file tree
+    pub fn label(self) -> &'static str {
+        match self {
+            StatusGroup::Staged => "Staged changes",
+            StatusGroup::Unstaged => "Unstaged changes",
+            StatusGroup::Untracked => "Untracked",
+        }
+    }
+}
+
+/// Kind of change a file diff represents, taken from the extended git headers
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Deleted,
+    #[default]
+    Modified,
+    Renamed,
+    Copied,
+}
+
+/// Serializable summary of a file diff without its content
+#[derive(Debug, Serialize)]
+pub struct FileDiffSummary {
+    pub filename: String,
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub added_lines: usize,
+    pub removed_lines: usize,
+    pub change_kind: ChangeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_group: Option<StatusGroup>,
+}
+
+impl From<&FileDiff> for FileDiffSummary {
+    fn from(file_diff: &FileDiff) -> Self {
+        // Drop the a/ and b/ prefixes and treat /dev/null as a missing side
+        let strip_prefix = |path: &Option<String>, prefix: &str| {
+            path.as_deref()
+                .filter(|p| *p != "/dev/null")
+                .map(|p| p.strip_prefix(prefix).unwrap_or(p).to_string())
+        };
+
+        Self {
+            filename: file_diff.filename.clone(),
+            old_path: strip_prefix(&file_diff.old_path, "a/"),
+            new_path: strip_prefix(&file_diff.new_path, "b/"),
+            added_lines: file_diff.added_lines,
+            removed_lines: file_diff.removed_lines,
+            change_kind: file_diff.change_kind,
+            status_group: file_diff.status_group,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -19,36 +109,597 @@ pub struct DiffFileKey {
 }
 
 impl FileDiff {
-    /// Get appropriate nerd font icon based on file extension
-    pub fn get_file_icon(&self) -> char {
-        let filename = if self.filename.contains('/') {
-            self.filename
-                .split('/')
-                .next_back()
-                .unwrap_or(&self.filename)
-        } else {
-            &self.filename
-        };
+    /// Get appropriate nerd font icon based on file extension, or the shebang of scripts
+    pub fn get_file_icon(&self, overrides: &crate::icons::IconOverrides) -> char {
+        if self.is_submodule() {
+            return crate::icons::SUBMODULE_ICON;
+        }
+        overrides.get_path_icon(&self.filename, || self.first_line())
+    }
+
+    /// Check if only the file's metadata is known, so its diff must be fetched to be shown
+    ///
+    /// Parsed diffs always hold at least the file header; summaries from
+    /// `DiffParser::parse_summary` hold nothing.
+    pub fn is_summary_only(&self) -> bool {
+        self.content.is_empty() && self.load_error.is_none()
+    }
 
-        crate::icons::get_file_icon(filename)
+    /// Get the first line of the file, if the diff shows it
+    ///
+    /// Only known when the first hunk starts at the top of the file. A deleted file's first
+    /// line comes from its old contents.
+    pub fn first_line(&self) -> Option<String> {
+        let mut lines = self.content.lines().map(strip_ansi_escapes::strip_str);
+        let header = lines.find(|line| DiffParser::is_hunk_header(line))?;
+        let (old_start, new_start, _) = DiffParser::parse_hunk_range(&header)?;
+        let line = match (old_start, new_start) {
+            (_, 1) => lines.find(|line| line.starts_with('+') || line.starts_with(' ')),
+            (1, 0) => lines.find(|line| line.starts_with('-')),
+            _ => None,
+        }?;
+        Some(line[1..].to_string())
     }
 
     /// Get diff statistics as string with icons
     pub fn diff_stats(&self) -> String {
+        if self.is_mode_only() {
+            // "+0 -0" says nothing, so show which way the executable bit went instead
+            return match self.mode_change() {
+                Some((_, new_mode)) if new_mode.ends_with("755") => " mode +x".to_string(),
+                Some((old_mode, _)) if old_mode.ends_with("755") => " mode -x".to_string(),
+                _ => " mode".to_string(),
+            };
+        }
+        if self.is_submodule() {
+            // The pointer change is always "+1 -1", which reads like a one-line edit
+            return " submodule".to_string();
+        }
         format!(" +{} -{}", self.added_lines, self.removed_lines)
     }
+
+    /// Get the hunk count for display, e.g. "3 hunks", or None when the diff shows no hunks
+    pub fn hunk_summary(&self) -> Option<String> {
+        match self.hunk_count {
+            0 => None,
+            1 => Some("1 hunk".to_string()),
+            count => Some(format!("{count} hunks")),
+        }
+    }
+
+    /// Get the old and new file modes if the mode changed
+    pub fn mode_change(&self) -> Option<(&str, &str)> {
+        Some((self.old_mode.as_deref()?, self.new_mode.as_deref()?))
+    }
+
+    /// Describe where a renamed or copied file came from, e.g. "renamed from a.rs (87% similar)"
+    pub fn rename_summary(&self) -> Option<String> {
+        let verb = match self.change_kind {
+            ChangeKind::Renamed => "renamed",
+            ChangeKind::Copied => "copied",
+            _ => return None,
+        };
+        let source = self.rename_from.as_deref()?;
+        Some(match self.similarity {
+            Some(similarity) => format!("{verb} from {source} ({similarity}% similar)"),
+            None => format!("{verb} from {source}"),
+        })
+    }
+
+    /// Check if this diff moves a submodule to another commit
+    pub fn is_submodule(&self) -> bool {
+        self.submodule.is_some()
+    }
+
+    /// Describe a submodule change, e.g. "Submodule vendor/lib: 1a2b3c4 → 5d6e7f8"
+    pub fn submodule_summary(&self) -> Option<String> {
+        let name = &self.filename;
+        let submodule = self.submodule.as_ref()?;
+        Some(
+            match (
+                submodule.old_commit.as_deref(),
+                submodule.new_commit.as_deref(),
+            ) {
+                (Some(old), Some(new)) => format!(
+                    "Submodule {name}: {} → {}",
+                    short_commit(old),
+                    short_commit(new)
+                ),
+                (None, Some(new)) => format!("Submodule {name} added at {}", short_commit(new)),
+                (Some(old), None) => {
+                    format!("Submodule {name} removed (was at {})", short_commit(old))
+                }
+                (None, None) => format!("Submodule {name} changed"),
+            },
+        )
+    }
+
+    /// Check if only the file mode changed, with no content changes
+    pub fn is_mode_only(&self) -> bool {
+        self.mode_change().is_some() && self.added_lines == 0 && self.removed_lines == 0
+    }
+
+    /// Explain a diff without any text changes, such as a pure rename or an empty new file
+    ///
+    /// Returns None when the diff has hunks or is a binary file. Submodule changes do have a
+    /// hunk, but only with commit hashes, so they are described as well.
+    pub fn no_text_changes_summary(&self) -> Option<String> {
+        if let Some(summary) = self.submodule_summary() {
+            return Some(summary);
+        }
+        if self.added_lines > 0 || self.removed_lines > 0 {
+            return None;
+        }
+        let plain = strip_ansi_escapes::strip_str(&self.content);
+        let has_changes = plain.lines().any(|line| {
+            DiffParser::is_hunk_header(line)
+                || line.starts_with("Binary files ")
+                || line == "GIT binary patch"
+        });
+        if has_changes {
+            return None;
+        }
+
+        let mode = self
+            .mode_change()
+            .map(|(old_mode, new_mode)| format!("{old_mode} → {new_mode}"));
+        Some(match (self.change_kind, mode) {
+            (ChangeKind::Renamed, None) => "No textual changes (rename only)".to_string(),
+            (ChangeKind::Copied, _) => "No textual changes (copy only)".to_string(),
+            (ChangeKind::Renamed, Some(mode)) => {
+                format!("No textual changes (rename and mode change {mode})")
+            }
+            (ChangeKind::Added, _) => "Empty file added".to_string(),
+            (ChangeKind::Deleted, _) => "Empty file deleted".to_string(),
+            (_, Some(mode)) => format!("Mode change only ({mode})"),
+            (_, None) => "No textual changes".to_string(),
+        })
+    }
+}
+
+/// Shorten a commit hash to 7 characters, keeping git's "-dirty" marker
+fn short_commit(commit: &str) -> String {
+    let (hash, dirty) = match commit.strip_suffix("-dirty") {
+        Some(hash) => (hash, "-dirty"),
+        None => (commit, ""),
+    };
+    let short: String = hash.chars().take(7).collect();
+    format!("{short}{dirty}")
 }
 
 pub struct DiffParser;
 
+/// Verbs starting a file header in Jujutsu's native diff format
+const JJ_HEADER_VERBS: [(&str, ChangeKind); 5] = [
+    ("Added", ChangeKind::Added),
+    ("Removed", ChangeKind::Deleted),
+    ("Modified", ChangeKind::Modified),
+    ("Renamed", ChangeKind::Renamed),
+    ("Copied", ChangeKind::Copied),
+];
+
+/// File types named in Jujutsu's native diff headers
+const JJ_FILE_TYPES: [&str; 6] = [
+    "regular file",
+    "executable file",
+    "symlink",
+    "tree",
+    "git submodule",
+    "conflict",
+];
+
+/// Name of the single entry shown for input that is not in a known diff format
+pub const WHOLE_INPUT_FILENAME: &str = "(stdin)";
+
 impl DiffParser {
+    /// Check if a line is git's "\ No newline at end of file" marker (the text is localized)
+    pub fn is_no_newline_marker(line: &str) -> bool {
+        line.starts_with("\\ ")
+    }
+
     fn calculate_diff_stats(file_diff: &mut FileDiff, content: &str) {
+        // Combined diffs mark hunks with one more '@' per parent, so "@@@" starts with "@@" too
+        file_diff.hunk_count = content
+            .lines()
+            .filter(|line| line.starts_with("@@"))
+            .count();
+
+        if Self::is_combined_header(content) {
+            Self::calculate_combined_diff_stats(file_diff, content);
+            return;
+        }
+
+        // "--- a/x" and "+++ b/x" only name the files before the first hunk; inside one they
+        // are a removed "-- ..." or an added "++ ..." line
+        let mut in_hunk = false;
         for line in content.lines() {
-            if line.starts_with('+') && !line.starts_with("+++") {
+            if line.starts_with("@@") {
+                in_hunk = true;
+            } else if !in_hunk && (line.starts_with("--- ") || line.starts_with("+++ ")) {
+                continue;
+            }
+
+            if line.starts_with('+') {
                 file_diff.added_lines += 1;
-            } else if line.starts_with('-') && !line.starts_with("---") {
+            } else if line.starts_with('-') {
                 file_diff.removed_lines += 1;
             }
+            // A submodule's diff is one line naming the commit it points to on each side
+            if let Some(submodule) = &mut file_diff.submodule {
+                if let Some(commit) = line.strip_prefix("-Subproject commit ") {
+                    submodule.old_commit = Some(commit.trim().to_string());
+                } else if let Some(commit) = line.strip_prefix("+Subproject commit ") {
+                    submodule.new_commit = Some(commit.trim().to_string());
+                }
+            }
+        }
+    }
+
+    /// Check if a file mode is that of a submodule (a gitlink)
+    fn is_gitlink_mode(mode: &str) -> bool {
+        mode.trim() == "160000"
+    }
+
+    /// Count changes in a combined diff (git diff --cc), best effort
+    ///
+    /// Each hunk line starts with one marker column per parent, so a line is counted as
+    /// removed if any column is '-' and as added if any column is '+'.
+    fn calculate_combined_diff_stats(file_diff: &mut FileDiff, content: &str) {
+        let mut parents = 0;
+
+        for line in content.lines() {
+            if line.starts_with("@@@") {
+                // "@@@ -a -b +c @@@" has one more '@' than there are parents
+                parents = line.chars().take_while(|&c| c == '@').count() - 1;
+                continue;
+            }
+            if parents == 0 {
+                // Still in the extended header
+                continue;
+            }
+
+            let markers: String = line.chars().take(parents).collect();
+            if markers.contains('-') {
+                file_diff.removed_lines += 1;
+            } else if markers.contains('+') {
+                file_diff.added_lines += 1;
+            }
+        }
+    }
+
+    fn is_combined_header(line: &str) -> bool {
+        line.starts_with("diff --cc ") || line.starts_with("diff --combined ")
+    }
+
+    /// Extract the filename from a "diff --git" or combined "diff --cc" header
+    ///
+    /// Also reports whether the paths carry git's a/ and b/ prefixes, which are missing
+    /// when the user has `diff.noprefix` set ("diff --git file file").
+    fn parse_header_filename(line: &str) -> Option<(String, bool)> {
+        if let Some(path) = line
+            .strip_prefix("diff --cc ")
+            .or_else(|| line.strip_prefix("diff --combined "))
+        {
+            // Combined diffs only name the merged path once
+            return Some((Self::unquote_path(path), true));
+        }
+
+        let rest = line.strip_prefix("diff --git ")?;
+        let (old_side, new_side) = Self::split_git_header_paths(rest)?;
+        // A no-prefix diff of a path under a/ has the same path on both sides ("a/x a/x")
+        let prefixed = old_side.starts_with("a/") && new_side.starts_with("b/");
+        if prefixed {
+            Some((old_side[2..].to_string(), true))
+        } else {
+            Some((old_side, false))
+        }
+    }
+
+    /// Check if a line is a hunk header ("@@ -1,2 +1,3 @@")
+    pub fn is_hunk_header(line: &str) -> bool {
+        line.starts_with("@@")
+    }
+
+    /// Get the hunk header lines of a diff
+    pub fn hunk_headers(content: &str) -> Vec<&str> {
+        content
+            .lines()
+            .filter(|line| Self::is_hunk_header(line))
+            .collect()
+    }
+
+    /// Find the index of the header of the hunk containing the given line
+    ///
+    /// A line before the first hunk header (e.g. in the file header) selects the first hunk.
+    fn hunk_start(lines: &[&str], line: usize) -> Option<usize> {
+        let headers: Vec<usize> = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| Self::is_hunk_header(l))
+            .map(|(i, _)| i)
+            .collect();
+
+        headers
+            .iter()
+            .rev()
+            .find(|&&header| header <= line)
+            .or_else(|| headers.first())
+            .copied()
+    }
+
+    /// Get the header line of the hunk containing the given line
+    pub fn hunk_header_at_line(content: &str, line: usize) -> Option<&str> {
+        let lines: Vec<&str> = content.lines().collect();
+        Self::hunk_start(&lines, line).map(|start| lines[start])
+    }
+
+    /// Join file diffs back into one patch, as git would print them
+    ///
+    /// ANSI colors of piped input are stripped so the patch can be applied or reopened.
+    pub fn to_patch(file_diffs: &[FileDiff]) -> String {
+        let mut patch = String::new();
+        for file_diff in file_diffs {
+            patch.push_str(&strip_ansi_escapes::strip_str(&file_diff.content));
+            if !patch.is_empty() && !patch.ends_with('\n') {
+                patch.push('\n');
+            }
+        }
+        patch
+    }
+
+    /// Get the indices of the lines to show when only changed lines are wanted
+    ///
+    /// Keeps the file header, hunk headers (which separate the hunks) and added and removed
+    /// lines, dropping context lines and "\\ No newline at end of file" markers.
+    pub fn changed_line_indices(content: &str) -> Vec<usize> {
+        let mut in_hunk = false;
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| {
+                if Self::is_hunk_header(line) {
+                    in_hunk = true;
+                    return true;
+                }
+                if line.starts_with("diff ") {
+                    in_hunk = false;
+                }
+                !in_hunk || line.starts_with('+') || line.starts_with('-')
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Get the indices of the hunk lines that contain `query`, ignoring case
+    ///
+    /// File and hunk headers are skipped, so a query naming a file does not match its header.
+    /// Output without hunk headers, such as a diff tool's, is searched as a whole.
+    pub fn search_match_lines(content: &str, query: &str) -> Vec<usize> {
+        let query = query.to_lowercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let plain = strip_ansi_escapes::strip_str(content);
+        let has_hunks = plain.lines().any(Self::is_hunk_header);
+        let mut in_hunk = !has_hunks;
+        plain
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| {
+                if has_hunks && Self::is_hunk_header(line) {
+                    in_hunk = true;
+                    return false;
+                }
+                if has_hunks && line.starts_with("diff ") {
+                    in_hunk = false;
+                }
+                in_hunk && line.to_lowercase().contains(&query)
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Get the body lines of the hunk containing the given line
+    ///
+    /// A line before the first hunk header (e.g. in the file header) selects the first hunk.
+    pub fn hunk_at_line(content: &str, line: usize) -> Option<Vec<&str>> {
+        let lines: Vec<&str> = content.lines().collect();
+        let start = Self::hunk_start(&lines, line)?;
+        let end = lines[start + 1..]
+            .iter()
+            .position(|l| Self::is_hunk_header(l) || l.starts_with("diff "))
+            .map_or(lines.len(), |offset| start + 1 + offset);
+
+        Some(lines[start + 1..end].to_vec())
+    }
+
+    /// Turn hunk body lines into a markdown suggestion block with the resulting code
+    pub fn suggestion_block(hunk_lines: &[&str]) -> String {
+        let mut block = String::from("```suggestion\n");
+        for line in hunk_lines {
+            // Keep added and context lines, drop removed lines and "\ No newline" markers
+            if let Some(kept) = line.strip_prefix('+').or_else(|| line.strip_prefix(' ')) {
+                block.push_str(kept);
+                block.push('\n');
+            } else if line.is_empty() {
+                // Some tools strip the trailing space of empty context lines
+                block.push('\n');
+            }
+        }
+        block.push_str("```\n");
+        block
+    }
+
+    /// Get the old and new start lines and the new line count from a hunk header
+    ///
+    /// Combined diff headers ("@@@ -1,2 -1,2 +1,3 @@@") use their first old range.
+    fn parse_hunk_range(header: &str) -> Option<(usize, usize, usize)> {
+        let mut old_start = None;
+        let mut new_range = None;
+        for token in header.trim_start_matches('@').split_whitespace() {
+            if token.starts_with('@') {
+                break;
+            }
+            if let Some(range) = token.strip_prefix('-') {
+                old_start = old_start.or(Some(range));
+            } else if let Some(range) = token.strip_prefix('+') {
+                new_range = Some(range);
+            }
+        }
+
+        let start = |range: &str| range.split(',').next()?.parse::<usize>().ok();
+        let (new_start, new_count) = match new_range?.split_once(',') {
+            Some((start, count)) => (start.parse().ok()?, count.parse().ok()?),
+            None => (start(new_range?)?, 1),
+        };
+        Some((start(old_start?)?, new_start, new_count))
+    }
+
+    /// Format the diff line at the given index as a review comment quote
+    ///
+    /// A code line is prefixed with `path:NNN` (the old file's line for removed lines), and a
+    /// hunk header or file header line quotes the whole hunk prefixed with its new line range.
+    pub fn review_comment(content: &str, path: &str, line: usize) -> Option<String> {
+        let lines: Vec<&str> = content.lines().collect();
+        let start = Self::hunk_start(&lines, line)?;
+        let (mut old_line, mut new_line, new_count) = Self::parse_hunk_range(lines[start])?;
+        let end = lines[start + 1..]
+            .iter()
+            .position(|l| Self::is_hunk_header(l) || l.starts_with("diff "))
+            .map_or(lines.len(), |offset| start + 1 + offset);
+
+        if line <= start || line >= end {
+            let location = match new_count {
+                0 | 1 => format!("{path}:{new_line}"),
+                count => format!("{path}:{new_line}-{}", new_line + count - 1),
+            };
+            let body: String = lines[start + 1..end]
+                .iter()
+                .map(|l| format!("{l}\n"))
+                .collect();
+            return Some(format!("{location}\n{body}"));
+        }
+
+        for body_line in &lines[start + 1..line] {
+            match body_line.chars().next() {
+                Some('-') => old_line += 1,
+                Some('+') => new_line += 1,
+                Some('\\') => {}
+                _ => {
+                    old_line += 1;
+                    new_line += 1;
+                }
+            }
+        }
+
+        let target = lines[line];
+        let (number, code) = match target.chars().next() {
+            Some('-') => (old_line, &target[1..]),
+            Some('\\') => return None,
+            Some(marker @ ('+' | ' ')) => (new_line, &target[marker.len_utf8()..]),
+            _ => (new_line, target),
+        };
+        Some(format!("{path}:{number}\n{code}\n"))
+    }
+
+    /// Split the "a/<path> b/<path>" part of a diff --git header into its two sides
+    ///
+    /// Paths may contain spaces and may be quoted with C-style escapes by git.
+    fn split_git_header_paths(rest: &str) -> Option<(String, String)> {
+        // Quoted old side: "a/..." followed by a quoted or bare new side
+        if rest.starts_with('"') {
+            let (old_side, remainder) = Self::parse_quoted(rest)?;
+            let new_side = remainder.strip_prefix(' ')?;
+            return Some((old_side, Self::unquote_path(new_side)));
+        }
+
+        // Bare old side followed by a quoted new side
+        if rest.ends_with('"') {
+            let split = rest.rfind(" \"")?;
+            let (new_side, _) = Self::parse_quoted(&rest[split + 1..])?;
+            return Some((rest[..split].to_string(), new_side));
+        }
+
+        // Both sides bare: when the path is unchanged the line splits exactly in the middle
+        if rest.len() % 2 == 1 {
+            let mid = rest.len() / 2;
+            if rest.is_char_boundary(mid) && rest.as_bytes()[mid] == b' ' {
+                let (old_side, new_side) = (&rest[..mid], &rest[mid + 1..]);
+                if old_side.get(2..) == new_side.get(2..) {
+                    return Some((old_side.to_string(), new_side.to_string()));
+                }
+            }
+        }
+
+        // Renamed bare paths: split before the new side's prefix
+        let split = rest.find(" b/").or_else(|| rest.find(' '))?;
+        Some((rest[..split].to_string(), rest[split + 1..].to_string()))
+    }
+
+    /// Unquote a path if git quoted it, otherwise return it unchanged
+    fn unquote_path(path: &str) -> String {
+        if path.starts_with('"') {
+            if let Some((unquoted, _)) = Self::parse_quoted(path) {
+                return unquoted;
+            }
+        }
+        path.to_string()
+    }
+
+    /// Parse a leading C-style quoted string, returning it unescaped along with the remainder
+    fn parse_quoted(input: &str) -> Option<(String, &str)> {
+        let mut bytes = Vec::new();
+        let mut chars = input.strip_prefix('"')?.char_indices();
+
+        while let Some((i, ch)) = chars.next() {
+            match ch {
+                '"' => {
+                    let remainder = &input[i + 2..];
+                    return Some((String::from_utf8_lossy(&bytes).into_owned(), remainder));
+                }
+                '\\' => {
+                    let (_, escaped) = chars.next()?;
+                    match escaped {
+                        'n' => bytes.push(b'\n'),
+                        't' => bytes.push(b'\t'),
+                        'r' => bytes.push(b'\r'),
+                        'a' => bytes.push(0x07),
+                        'b' => bytes.push(0x08),
+                        'f' => bytes.push(0x0c),
+                        'v' => bytes.push(0x0b),
+                        '0'..='7' => {
+                            // Three-digit octal byte, e.g. \303\251 for "é"
+                            let mut value = escaped.to_digit(8)?;
+                            for _ in 0..2 {
+                                let (_, digit) = chars.next()?;
+                                value = value * 8 + digit.to_digit(8)?;
+                            }
+                            bytes.push(u8::try_from(value).ok()?);
+                        }
+                        other => {
+                            let mut buf = [0; 4];
+                            bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+                        }
+                    }
+                }
+                other => {
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Extract the path from a "---" or "+++" line, dropping quotes and any trailing timestamp
+    fn parse_marker_path(path: &str, prefix: Option<&str>) -> String {
+        let path = path.split('\t').next().unwrap_or(path);
+        let path = Self::unquote_path(path);
+        // Give no-prefix paths the a/ or b/ prefix that prefixed diffs carry
+        match prefix {
+            Some(prefix) if path != "/dev/null" => format!("{prefix}{path}"),
+            _ => path,
         }
     }
 
@@ -73,13 +724,251 @@ impl DiffParser {
         }
     }
 
+    /// Parse the output of `git diff --raw --numstat -z` into file diffs without content
+    ///
+    /// Used when the full diff is too large to hold in memory; each file's diff is then
+    /// fetched when it is viewed (see `FileDiff::is_summary_only`).
+    pub fn parse_summary(output: &str) -> Vec<FileDiff> {
+        let mut file_diffs: Vec<FileDiff> = Vec::new();
+        let mut tokens = output.split('\0').filter(|token| !token.is_empty());
+
+        while let Some(token) = tokens.next() {
+            if let Some(raw) = token.strip_prefix(':') {
+                // ":100644 100644 abc1234 def5678 M" followed by the path, or two for R and C
+                let fields: Vec<&str> = raw.split_whitespace().collect();
+                let [old_mode, new_mode, from_hash, to_hash, status] = fields[..] else {
+                    continue;
+                };
+                let Some(first_path) = tokens.next() else {
+                    break;
+                };
+                let (rename_from, filename) = if status.starts_with(['R', 'C']) {
+                    let Some(second_path) = tokens.next() else {
+                        break;
+                    };
+                    (Some(first_path.to_string()), second_path.to_string())
+                } else {
+                    (None, first_path.to_string())
+                };
+
+                let mut file = Self::new_file_diff(filename, rename_from);
+                file.change_kind = match status.chars().next() {
+                    Some('A') => ChangeKind::Added,
+                    Some('D') => ChangeKind::Deleted,
+                    Some('R') => ChangeKind::Renamed,
+                    Some('C') => ChangeKind::Copied,
+                    _ => ChangeKind::Modified,
+                };
+                file.similarity = status.get(1..).and_then(|score| score.parse().ok());
+                match file.change_kind {
+                    ChangeKind::Added => file.old_path = Some("/dev/null".to_string()),
+                    ChangeKind::Deleted => file.new_path = Some("/dev/null".to_string()),
+                    _ => {}
+                }
+                if old_mode != new_mode
+                    && !old_mode.starts_with("000")
+                    && !new_mode.starts_with("000")
+                {
+                    file.old_mode = Some(old_mode.to_string());
+                    file.new_mode = Some(new_mode.to_string());
+                }
+                // The object a submodule lists is the commit it points to
+                if Self::is_gitlink_mode(old_mode) || Self::is_gitlink_mode(new_mode) {
+                    let commit = |hash: &str, mode: &str| {
+                        Self::is_gitlink_mode(mode).then(|| hash.to_string())
+                    };
+                    file.submodule = Some(SubmoduleChange {
+                        old_commit: commit(from_hash, old_mode),
+                        new_commit: commit(to_hash, new_mode),
+                    });
+                }
+
+                // Working tree files are not hashed by --raw, so their side reads as zeros
+                let unhashed = |hash: &str, mode: &str| {
+                    hash.bytes().all(|b| b == b'0') && !mode.starts_with("000")
+                };
+                if !unhashed(from_hash, old_mode) && !unhashed(to_hash, new_mode) {
+                    file.diff_key = Some(DiffFileKey {
+                        from_hash: from_hash.to_string(),
+                        to_hash: to_hash.to_string(),
+                        file_path: file.filename.clone(),
+                    });
+                }
+                file_diffs.push(file);
+            } else {
+                // "added\tremoved\tpath", or "added\tremoved\t" followed by both paths of a rename
+                let mut fields = token.splitn(3, '\t');
+                let (Some(added), Some(removed), Some(path)) =
+                    (fields.next(), fields.next(), fields.next())
+                else {
+                    continue;
+                };
+                let filename = if path.is_empty() {
+                    tokens.next();
+                    tokens.next()
+                } else {
+                    Some(path)
+                };
+                // Binary files are counted as "-"
+                if let Some(file) = filename
+                    .and_then(|filename| file_diffs.iter_mut().find(|f| f.filename == filename))
+                {
+                    file.added_lines = added.parse().unwrap_or(0);
+                    file.removed_lines = removed.parse().unwrap_or(0);
+                }
+            }
+        }
+        file_diffs
+    }
+
+    /// Parse diff input of unknown format, such as stdin
+    ///
+    /// Git-format diffs are tried first, then Jujutsu's native format (`jj diff`). Anything
+    /// else is shown as a single entry holding the whole input rather than an empty tree.
+    pub fn parse_input(content: &str) -> Vec<FileDiff> {
+        let file_diffs = Self::parse(content);
+        if !file_diffs.is_empty() {
+            return file_diffs;
+        }
+
+        let file_diffs = Self::parse_jj(content);
+        if !file_diffs.is_empty() || content.trim().is_empty() {
+            return file_diffs;
+        }
+
+        // The entry is not a real file, so it has no paths
+        let mut whole_input = Self::new_file_diff(WHOLE_INPUT_FILENAME.to_string(), None);
+        whole_input.old_path = None;
+        whole_input.new_path = None;
+        whole_input.content = content.to_string();
+        Self::calculate_diff_stats(&mut whole_input, content);
+        vec![whole_input]
+    }
+
+    fn new_file_diff(filename: String, old_filename: Option<String>) -> FileDiff {
+        FileDiff {
+            old_path: Some(format!("a/{}", old_filename.as_ref().unwrap_or(&filename))),
+            new_path: Some(format!("b/{filename}")),
+            filename,
+            content: String::new(),
+            added_lines: 0,
+            removed_lines: 0,
+            hunk_count: 0,
+            diff_key: None,
+            change_kind: ChangeKind::Modified,
+            is_untracked: false,
+            old_mode: None,
+            new_mode: None,
+            rename_from: old_filename,
+            similarity: None,
+            status_group: None,
+            submodule: None,
+            load_error: None,
+        }
+    }
+
+    /// Parse a file header of `jj diff`'s native format, e.g. "Modified regular file src/a.rs:"
+    ///
+    /// Returns the change kind, the path, and the old path of a rename or copy
+    /// ("Renamed regular file old.rs => new.rs:").
+    fn parse_jj_header(line: &str) -> Option<(ChangeKind, String, Option<String>)> {
+        let rest = line.strip_suffix(':')?;
+        let (kind, rest) = JJ_HEADER_VERBS.iter().find_map(|(verb, kind)| {
+            rest.strip_prefix(verb)
+                .and_then(|rest| rest.strip_prefix(' '))
+                .map(|rest| (*kind, rest))
+        })?;
+        let path = JJ_FILE_TYPES.iter().find_map(|file_type| {
+            rest.strip_prefix(file_type)
+                .and_then(|rest| rest.strip_prefix(' '))
+        })?;
+        if path.is_empty() {
+            return None;
+        }
+
+        match path.split_once(" => ") {
+            Some((old, new)) => Some((kind, new.to_string(), Some(old.to_string()))),
+            None => Some((kind, path.to_string(), None)),
+        }
+    }
+
+    /// Count changed lines in a file of `jj diff`'s native format, best effort
+    ///
+    /// Lines start with the old and new line numbers before a colon. Removed lines only have
+    /// the old number and added lines only the new one, which sits right before the colon.
+    /// Lines changed in place have both numbers, so they count as neither.
+    fn calculate_jj_diff_stats(file_diff: &mut FileDiff, content: &str) {
+        for line in content.lines().skip(1) {
+            let Some((numbers, _)) = line.split_once(':') else {
+                continue;
+            };
+            let mut columns = numbers.split_whitespace();
+            let (Some(number), None) = (columns.next(), columns.next()) else {
+                continue;
+            };
+            if !number.bytes().all(|b| b.is_ascii_digit()) {
+                continue;
+            }
+            if numbers.ends_with(char::is_whitespace) {
+                file_diff.removed_lines += 1;
+            } else {
+                file_diff.added_lines += 1;
+            }
+        }
+    }
+
+    /// Parse the native (color-words) output of `jj diff`, possibly colored
+    pub fn parse_jj(diff_content: &str) -> Vec<FileDiff> {
+        let mut file_diffs = Vec::new();
+        let mut current_file: Option<FileDiff> = None;
+
+        for raw_line in diff_content.split_inclusive('\n') {
+            let plain = strip_ansi_escapes::strip_str(raw_line);
+            let plain = plain.trim_end_matches(['\n', '\r']);
+
+            if let Some((kind, path, old_path)) = Self::parse_jj_header(plain) {
+                if let Some(file) = current_file.take() {
+                    file_diffs.push(file);
+                }
+                let mut file = Self::new_file_diff(path, old_path);
+                file.change_kind = kind;
+                current_file = Some(file);
+            }
+
+            if let Some(ref mut file) = current_file {
+                file.content.push_str(raw_line);
+                if !raw_line.ends_with('\n') {
+                    file.content.push('\n');
+                }
+            }
+        }
+
+        if let Some(file) = current_file {
+            file_diffs.push(file);
+        }
+
+        for file in &mut file_diffs {
+            let plain = strip_ansi_escapes::strip_str(&file.content);
+            Self::calculate_jj_diff_stats(file, &plain);
+        }
+        file_diffs
+    }
+
     pub fn parse(diff_content: &str) -> Vec<FileDiff> {
         let mut file_diffs = Vec::new();
         let mut current_file: Option<FileDiff> = None;
         let mut current_content = String::new();
+        // Whether the current file's paths lack the a/ and b/ prefixes (diff.noprefix)
+        let mut no_prefix = false;
+        // Past the current file's header, where "--- " and "+++ " start changed lines
+        let mut in_hunk = false;
 
-        for line in diff_content.lines() {
-            if line.starts_with("diff --git") {
+        for raw_line in diff_content.split_inclusive('\n') {
+            // Match headers without the line ending, but keep CRLF endings in the content
+            let line = raw_line.strip_suffix('\n').unwrap_or(raw_line);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+
+            if line.starts_with("diff --git") || Self::is_combined_header(line) {
                 // Save previous file if exists
                 if let Some(mut file) = current_file.take() {
                     file.content = current_content.clone();
@@ -87,10 +976,9 @@ impl DiffParser {
                     file_diffs.push(file);
                 }
 
-                // Extract filename from diff --git a/file b/file
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 4 {
-                    let filename = parts[2].trim_start_matches("a/").to_string();
+                // Extract filename from diff --git a/file b/file or diff --cc file
+                if let Some((filename, prefixed)) = Self::parse_header_filename(line) {
+                    no_prefix = !prefixed;
                     current_file = Some(FileDiff {
                         filename: filename.clone(),
                         old_path: Some(format!("a/{filename}")),
@@ -98,10 +986,25 @@ impl DiffParser {
                         content: String::new(),
                         added_lines: 0,
                         removed_lines: 0,
+                        hunk_count: 0,
                         diff_key: None, // Will be set when we parse index line
+                        change_kind: ChangeKind::Modified,
+                        is_untracked: false,
+                        old_mode: None,
+                        new_mode: None,
+                        rename_from: None,
+                        similarity: None,
+                        status_group: None,
+                        submodule: None,
+                        load_error: None,
                     });
                 }
                 current_content.clear();
+                in_hunk = false;
+            } else if line.starts_with("@@") {
+                in_hunk = true;
+            } else if in_hunk {
+                // Changed and context lines never hold header fields
             } else if line.starts_with("index ") {
                 // Parse index line to extract commit hashes
                 let current_hashes = Self::parse_index_line(line);
@@ -114,20 +1017,75 @@ impl DiffParser {
                         file_path: file.filename.clone(),
                     });
                 }
+                // "index 1a2b3c4..5d6e7f8 160000" names the mode when it did not change
+                if let Some(ref mut file) = current_file {
+                    if line.rsplit(' ').next().is_some_and(Self::is_gitlink_mode) {
+                        file.submodule.get_or_insert_with(SubmoduleChange::default);
+                    }
+                }
+            } else if let Some(mode) = line.strip_prefix("new file mode ") {
+                if let Some(ref mut file) = current_file {
+                    file.change_kind = ChangeKind::Added;
+                    if Self::is_gitlink_mode(mode) {
+                        file.submodule.get_or_insert_with(SubmoduleChange::default);
+                    }
+                }
+            } else if let Some(mode) = line.strip_prefix("deleted file mode ") {
+                if let Some(ref mut file) = current_file {
+                    file.change_kind = ChangeKind::Deleted;
+                    if Self::is_gitlink_mode(mode) {
+                        file.submodule.get_or_insert_with(SubmoduleChange::default);
+                    }
+                }
+            } else if let Some(mode) = line.strip_prefix("old mode ") {
+                if let Some(ref mut file) = current_file {
+                    file.old_mode = Some(mode.trim().to_string());
+                }
+            } else if let Some(mode) = line.strip_prefix("new mode ") {
+                if let Some(ref mut file) = current_file {
+                    file.new_mode = Some(mode.trim().to_string());
+                }
+            } else if let Some(source) = line.strip_prefix("rename from ") {
+                if let Some(ref mut file) = current_file {
+                    file.change_kind = ChangeKind::Renamed;
+                    file.rename_from = Some(Self::unquote_path(source));
+                }
+            } else if let Some(source) = line.strip_prefix("copy from ") {
+                if let Some(ref mut file) = current_file {
+                    file.change_kind = ChangeKind::Copied;
+                    file.rename_from = Some(Self::unquote_path(source));
+                }
+            } else if let Some(target) = line
+                .strip_prefix("rename to ")
+                .or_else(|| line.strip_prefix("copy to "))
+            {
+                // The header names the file by its source, but it lives at the new path now
+                if let Some(ref mut file) = current_file {
+                    file.filename = Self::unquote_path(target);
+                    file.new_path = Some(format!("b/{}", file.filename));
+                }
+            } else if let Some(index) = line.strip_prefix("similarity index ") {
+                if let Some(ref mut file) = current_file {
+                    file.similarity = index.trim_end_matches('%').parse().ok();
+                }
             } else if let Some(stripped) = line.strip_prefix("--- ") {
                 if let Some(ref mut file) = current_file {
-                    file.old_path = Some(stripped.to_string());
+                    let prefix = no_prefix.then_some("a/");
+                    file.old_path = Some(Self::parse_marker_path(stripped, prefix));
                 }
             } else if let Some(stripped) = line.strip_prefix("+++ ") {
                 if let Some(ref mut file) = current_file {
-                    file.new_path = Some(stripped.to_string());
+                    let prefix = no_prefix.then_some("b/");
+                    file.new_path = Some(Self::parse_marker_path(stripped, prefix));
                 }
             }
 
             // Always append line to current content
             if current_file.is_some() {
-                current_content.push_str(line);
-                current_content.push('\n');
+                current_content.push_str(raw_line);
+                if !raw_line.ends_with('\n') {
+                    current_content.push('\n');
+                }
             }
         }
 
@@ -146,6 +1104,192 @@ impl DiffParser {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_crlf_diff() {
+        let diff = "diff --git a/win.txt b/win.txt\r\nindex 1111111..2222222 100644\r\n--- a/win.txt\r\n+++ b/win.txt\r\n@@ -1,2 +1,2 @@\r\n line\r\n-old\r\n+new\r\n";
+        let file_diffs = DiffParser::parse(diff);
+
+        assert_eq!(file_diffs.len(), 1);
+        assert_eq!(file_diffs[0].filename, "win.txt");
+        assert_eq!(file_diffs[0].old_path.as_deref(), Some("a/win.txt"));
+        assert_eq!(file_diffs[0].new_path.as_deref(), Some("b/win.txt"));
+        assert_eq!(file_diffs[0].added_lines, 1);
+        assert_eq!(file_diffs[0].removed_lines, 1);
+        assert_eq!(
+            file_diffs[0]
+                .diff_key
+                .as_ref()
+                .map(|key| key.to_hash.as_str()),
+            Some("2222222")
+        );
+        assert_eq!(file_diffs[0].content, diff);
+    }
+
+    #[test]
+    fn test_no_newline_at_end_of_file() {
+        let diff = "diff --git a/a.txt b/a.txt\n\
+index 1234567..abcdefg 100644\n\
+--- a/a.txt\n\
++++ b/a.txt\n\
+@@ -1 +1 @@\n\
+-old\n\
+\\ No newline at end of file\n\
++new\n\
+\\ No newline at end of file\n";
+
+        let diffs = DiffParser::parse(diff);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].added_lines, 1);
+        assert_eq!(diffs[0].removed_lines, 1);
+
+        let markers: Vec<&str> = diffs[0]
+            .content
+            .lines()
+            .filter(|line| DiffParser::is_no_newline_marker(line))
+            .collect();
+        assert_eq!(markers, ["\\ No newline at end of file"; 2]);
+        assert!(!DiffParser::is_no_newline_marker("-\\ not a marker"));
+    }
+
+    #[test]
+    fn test_changed_lines_that_look_like_file_markers() {
+        // Removing a "-- comment" and adding a "++ counter" line in a hunk
+        let diff = "diff --git a/a.sql b/a.sql\n\
+--- a/a.sql\n\
++++ b/a.sql\n\
+@@ -1,2 +1,2 @@\n\
+--- comment\n\
++++ counter\n\
+ select 1;\n";
+
+        let diffs = DiffParser::parse(diff);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].added_lines, 1);
+        assert_eq!(diffs[0].removed_lines, 1);
+        assert_eq!(diffs[0].old_path.as_deref(), Some("a/a.sql"));
+        assert_eq!(diffs[0].new_path.as_deref(), Some("b/a.sql"));
+    }
+
+    #[test]
+    fn test_mode_only_change() {
+        let diff = "diff --git a/script.sh b/script.sh\nold mode 100644\nnew mode 100755\ndiff --git a/lib.rs b/lib.rs\nold mode 100755\nnew mode 100644\nindex 1111111..2222222\n--- a/lib.rs\n+++ b/lib.rs\n@@ -1 +1 @@\n-a\n+b\n";
+        let file_diffs = DiffParser::parse(diff);
+
+        assert_eq!(file_diffs.len(), 2);
+        assert_eq!(file_diffs[0].filename, "script.sh");
+        assert_eq!(file_diffs[0].mode_change(), Some(("100644", "100755")));
+        assert!(file_diffs[0].is_mode_only());
+        assert_eq!(file_diffs[0].diff_stats(), " mode +x");
+
+        // A mode change alongside content changes keeps the line counts
+        assert_eq!(file_diffs[1].mode_change(), Some(("100755", "100644")));
+        assert!(!file_diffs[1].is_mode_only());
+        assert_eq!(file_diffs[1].diff_stats(), " +1 -1");
+    }
+
+    #[test]
+    fn test_hunk_suggestion_block() {
+        let content = "diff --git a/a.rs b/a.rs\n--- a/a.rs\n+++ b/a.rs\n@@ -1,3 +1,3 @@\n fn a() {\n-    old();\n+    new();\n }\n@@ -10,2 +10,2 @@\n-x\n+y\n";
+
+        // The file header selects the first hunk
+        let hunk = DiffParser::hunk_at_line(content, 0).unwrap();
+        assert_eq!(hunk.len(), 4);
+        assert_eq!(
+            DiffParser::suggestion_block(&hunk),
+            "```suggestion\nfn a() {\n    new();\n}\n```\n"
+        );
+
+        let hunk = DiffParser::hunk_at_line(content, 9).unwrap();
+        assert_eq!(
+            DiffParser::suggestion_block(&hunk),
+            "```suggestion\ny\n```\n"
+        );
+
+        assert!(DiffParser::hunk_at_line("no hunks here", 0).is_none());
+
+        assert_eq!(
+            DiffParser::hunk_headers(content),
+            ["@@ -1,3 +1,3 @@", "@@ -10,2 +10,2 @@"]
+        );
+        assert_eq!(
+            DiffParser::hunk_header_at_line(content, 9),
+            Some("@@ -10,2 +10,2 @@")
+        );
+    }
+
+    #[test]
+    fn test_to_patch_round_trip() {
+        let diff_content = "diff --git a/a.rs b/a.rs\n--- a/a.rs\n+++ b/a.rs\n@@ -1 +1 @@\n-x\n+y\ndiff --git a/b.rs b/b.rs\n--- a/b.rs\n+++ b/b.rs\n@@ -1 +1 @@\n-\x1b[31mold\x1b[m\n+new";
+
+        let patch = DiffParser::to_patch(&DiffParser::parse(diff_content));
+        assert!(!patch.contains('\x1b'));
+        assert!(patch.ends_with("+new\n"));
+
+        let reparsed = DiffParser::parse(&patch);
+        assert_eq!(reparsed.len(), 2);
+        assert_eq!(reparsed[1].filename, "b.rs");
+        assert_eq!(reparsed[1].removed_lines, 1);
+    }
+
+    #[test]
+    fn test_changed_line_indices() {
+        let content = "diff --git a/a.rs b/a.rs\n--- a/a.rs\n+++ b/a.rs\n@@ -1,3 +1,3 @@\n fn a() {\n-    old();\n+    new();\n }\n\\ No newline at end of file\n@@ -10,2 +10,2 @@\n ctx\n-x\n";
+
+        assert_eq!(
+            DiffParser::changed_line_indices(content),
+            [0, 1, 2, 3, 5, 6, 9, 11]
+        );
+    }
+
+    #[test]
+    fn test_search_match_lines() {
+        let content = "diff --git a/parse.rs b/parse.rs\n--- a/parse.rs\n+++ b/parse.rs\n@@ -1,3 +1,3 @@ fn parse() {\n fn parse() {\n-    old();\n+    \x1b[32mParse\x1b[m();\n }\n";
+
+        assert_eq!(DiffParser::search_match_lines(content, "parse"), [4, 6]);
+        assert!(DiffParser::search_match_lines(content, "").is_empty());
+
+        // Without hunk headers every line is searched
+        assert_eq!(
+            DiffParser::search_match_lines("parse.rs\n  1 │ parse()\n", "PARSE"),
+            [0, 1]
+        );
+    }
+
+    #[test]
+    fn test_review_comment() {
+        let content = "diff --git a/a.rs b/a.rs\n--- a/a.rs\n+++ b/a.rs\n@@ -10,4 +10,4 @@ fn a() {\n fn a() {\n-    old();\n+    new();\n }\n@@ -30 +30,0 @@\n-gone\n";
+
+        // Added and context lines use the new file's numbering, removed lines the old one
+        assert_eq!(
+            DiffParser::review_comment(content, "src/a.rs", 4).unwrap(),
+            "src/a.rs:10\nfn a() {\n"
+        );
+        assert_eq!(
+            DiffParser::review_comment(content, "src/a.rs", 5).unwrap(),
+            "src/a.rs:11\n    old();\n"
+        );
+        assert_eq!(
+            DiffParser::review_comment(content, "src/a.rs", 6).unwrap(),
+            "src/a.rs:11\n    new();\n"
+        );
+        assert_eq!(
+            DiffParser::review_comment(content, "src/a.rs", 7).unwrap(),
+            "src/a.rs:12\n}\n"
+        );
+
+        // Headers quote the whole hunk with its line range
+        assert_eq!(
+            DiffParser::review_comment(content, "src/a.rs", 3).unwrap(),
+            "src/a.rs:10-13\n fn a() {\n-    old();\n+    new();\n }\n"
+        );
+        assert_eq!(
+            DiffParser::review_comment(content, "src/a.rs", 8).unwrap(),
+            "src/a.rs:30\n-gone\n"
+        );
+
+        assert!(DiffParser::review_comment("no hunks here", "a.rs", 0).is_none());
+    }
+
     #[test]
     fn test_parse_simple_diff() {
         let diff_content = r#"diff --git a/file1.rs b/file1.rs
@@ -186,4 +1330,436 @@ diff --git a/file2.rs b/file2.rs
         assert_eq!(diffs[0].filename, "file1.rs");
         assert_eq!(diffs[1].filename, "file2.rs");
     }
+
+    #[test]
+    fn test_parse_path_with_spaces() {
+        let diff_content = r#"diff --git a/docs/my file.txt b/docs/my file.txt
+index 1234567..abcdefg 100644
+--- a/docs/my file.txt
++++ b/docs/my file.txt
+@@ -1 +1 @@
+-old
++new
+"#;
+
+        let diffs = DiffParser::parse(diff_content);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].filename, "docs/my file.txt");
+        assert_eq!(diffs[0].new_path.as_deref(), Some("b/docs/my file.txt"));
+
+        // The file must be placed under its directory, not split at the space
+        let tree = crate::tree::FileTreeBuilder::build_file_tree(&diffs);
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].full_path, "docs");
+        assert_eq!(tree[1].name, "my file.txt");
+    }
+
+    #[test]
+    fn test_parse_quoted_escaped_path() {
+        let diff_content = r#"diff --git "a/src/\303\251t\303\251.txt" "b/src/\303\251t\303\251.txt"
+index 1234567..abcdefg 100644
+--- "a/src/\303\251t\303\251.txt"
++++ "b/src/\303\251t\303\251.txt"
+@@ -1 +1 @@
+-old
++new
+"#;
+
+        let diffs = DiffParser::parse(diff_content);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].filename, "src/été.txt");
+        assert_eq!(diffs[0].old_path.as_deref(), Some("a/src/été.txt"));
+
+        let tree = crate::tree::FileTreeBuilder::build_file_tree(&diffs);
+        assert_eq!(tree[1].name, "été.txt");
+    }
+
+    #[test]
+    fn test_split_renamed_header_paths() {
+        assert_eq!(
+            DiffParser::split_git_header_paths("a/old name.rs b/new name.rs"),
+            Some(("a/old name.rs".to_string(), "b/new name.rs".to_string()))
+        );
+        assert_eq!(
+            DiffParser::split_git_header_paths(r#"a/plain.rs "b/quo\"ted.rs""#),
+            Some(("a/plain.rs".to_string(), "b/quo\"ted.rs".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_submodule_changes() {
+        let diffs = DiffParser::parse(
+            "diff --git a/vendor/lib b/vendor/lib
+index 1a2b3c4..5d6e7f8 160000
+--- a/vendor/lib
++++ b/vendor/lib
+@@ -1 +1 @@
+-Subproject commit 1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b
++Subproject commit 5d6e7f8a9b0c1d2e3f4a5b6c7d8e9f0a1b2c3d4e-dirty
+diff --git a/deps/new b/deps/new
+new file mode 160000
+index 0000000..5d6e7f8
+--- /dev/null
++++ b/deps/new
+@@ -0,0 +1 @@
++Subproject commit 5d6e7f8a9b0c1d2e3f4a5b6c7d8e9f0a1b2c3d4e
+diff --git a/notes.txt b/notes.txt
+index 1111111..2222222 100644
+--- a/notes.txt
++++ b/notes.txt
+@@ -1 +1 @@
+-Subproject commit 1a2b3c4
++Subproject commit 5d6e7f8
+",
+        );
+        assert_eq!(
+            diffs[0].submodule_summary().as_deref(),
+            Some("Submodule vendor/lib: 1a2b3c4 → 5d6e7f8-dirty")
+        );
+        assert_eq!(
+            diffs[0].no_text_changes_summary(),
+            diffs[0].submodule_summary()
+        );
+        assert_eq!(diffs[0].diff_stats(), " submodule");
+        assert_eq!(
+            diffs[1].submodule_summary().as_deref(),
+            Some("Submodule deps/new added at 5d6e7f8")
+        );
+        // A file that merely contains such a line is not a submodule
+        assert!(!diffs[2].is_submodule());
+        assert_eq!(diffs[2].no_text_changes_summary(), None);
+
+        // Summaries know submodules by their mode too
+        let summaries = DiffParser::parse_summary(concat!(
+            ":160000 160000 1a2b3c4 5d6e7f8 M\0vendor/lib\0",
+            "1\t1\tvendor/lib\0",
+        ));
+        assert_eq!(
+            summaries[0].submodule_summary().as_deref(),
+            Some("Submodule vendor/lib: 1a2b3c4 → 5d6e7f8")
+        );
+    }
+
+    #[test]
+    fn test_no_text_changes_summary() {
+        let diffs = DiffParser::parse(
+            "diff --git a/old.rs b/new.rs
+similarity index 100%
+rename from old.rs
+rename to new.rs
+diff --git a/run.sh b/run.sh
+old mode 100644
+new mode 100755
+diff --git a/empty.txt b/empty.txt
+new file mode 100644
+index 0000000..e69de29
+diff --git a/logo.png b/logo.png
+index 1111111..2222222 100644
+Binary files a/logo.png and b/logo.png differ
+diff --git a/a.rs b/a.rs
+--- a/a.rs
++++ b/a.rs
+@@ -1 +1 @@
+-a
++b
+",
+        );
+        let summaries: Vec<Option<String>> = diffs
+            .iter()
+            .map(FileDiff::no_text_changes_summary)
+            .collect();
+        assert_eq!(
+            summaries,
+            [
+                Some("No textual changes (rename only)".to_string()),
+                Some("Mode change only (100644 → 100755)".to_string()),
+                Some("Empty file added".to_string()),
+                None,
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_first_line() {
+        let diffs = DiffParser::parse(
+            "diff --git a/bin/new b/bin/new
+new file mode 100755
+--- /dev/null
++++ b/bin/new
+@@ -0,0 +1,2 @@
++#!/bin/bash
++echo hi
+diff --git a/bin/old b/bin/old
+deleted file mode 100755
+--- a/bin/old
++++ /dev/null
+@@ -1 +0,0 @@
+-#!/usr/bin/env ruby
+diff --git a/bin/edited b/bin/edited
+--- a/bin/edited
++++ b/bin/edited
+@@ -1,2 +1,2 @@
+-#!/bin/sh
++#!/bin/zsh
+ set -e
+diff --git a/bin/tail b/bin/tail
+--- a/bin/tail
++++ b/bin/tail
+@@ -10 +10 @@
+-a
++b
+",
+        );
+        let first_lines: Vec<Option<String>> = diffs.iter().map(FileDiff::first_line).collect();
+        assert_eq!(
+            first_lines,
+            [
+                Some("#!/bin/bash".to_string()),
+                Some("#!/usr/bin/env ruby".to_string()),
+                Some("#!/bin/zsh".to_string()),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_no_prefix_diff() {
+        // git diff with diff.noprefix=true, including a path that starts with "a/"
+        let diff_content = r#"diff --git src/main.rs src/main.rs
+index 1111111..2222222 100644
+--- src/main.rs
++++ src/main.rs
+@@ -1 +1 @@
+-old
++new
+diff --git a/b.rs a/b.rs
+index 3333333..4444444 100644
+--- a/b.rs
++++ a/b.rs
+@@ -1 +1,2 @@
+ keep
++added
+"#;
+
+        let diffs = DiffParser::parse(diff_content);
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].filename, "src/main.rs");
+        assert_eq!(diffs[0].old_path.as_deref(), Some("a/src/main.rs"));
+        assert_eq!(diffs[0].new_path.as_deref(), Some("b/src/main.rs"));
+        assert_eq!((diffs[0].added_lines, diffs[0].removed_lines), (1, 1));
+        assert_eq!(diffs[1].filename, "a/b.rs");
+
+        let summary = FileDiffSummary::from(&diffs[1]);
+        assert_eq!(summary.old_path.as_deref(), Some("a/b.rs"));
+        assert_eq!(summary.new_path.as_deref(), Some("a/b.rs"));
+    }
+
+    #[test]
+    fn test_parse_combined_diff() {
+        let diff_content = r#"diff --cc src/conflict.rs
+index 1111111,2222222..3333333
+--- a/src/conflict.rs
++++ b/src/conflict.rs
+@@@ -1,3 -1,3 +1,4 @@@
+  fn main() {
+- println!("ours");
+ -println!("theirs");
+++println!("merged");
++ println!("extra");
+  }
+diff --git a/other.rs b/other.rs
+--- a/other.rs
++++ b/other.rs
+@@ -1 +1 @@
+-old
++new
+"#;
+
+        let diffs = DiffParser::parse(diff_content);
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].filename, "src/conflict.rs");
+        assert!(diffs[0].content.contains("merged"));
+        assert_eq!(diffs[0].added_lines, 2);
+        assert_eq!(diffs[0].removed_lines, 2);
+        assert_eq!(diffs[0].hunk_count, 1);
+        assert_eq!(diffs[1].filename, "other.rs");
+        assert_eq!(diffs[1].hunk_count, 1);
+    }
+
+    #[test]
+    fn test_parse_hunk_count() {
+        let diff_content = r#"diff --git a/src/lib.rs b/src/lib.rs
+index 1234567..abcdefg 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,2 +1,2 @@
+-use std::fs;
++use std::io;
+ 
+@@ -10,3 +10,4 @@ fn read() {
+     let a = 1;
++    let b = 2;
+ }
+@@ -40 +41 @@ fn write() {
+-    old();
++    new();
+diff --git a/empty.txt b/empty.txt
+new file mode 100644
+index 0000000..e69de29
+"#;
+
+        let diffs = DiffParser::parse(diff_content);
+        assert_eq!(diffs[0].hunk_count, 3);
+        assert_eq!(diffs[0].hunk_summary().as_deref(), Some("3 hunks"));
+        assert_eq!(diffs[1].hunk_count, 0);
+        assert_eq!(diffs[1].hunk_summary(), None);
+    }
+
+    #[test]
+    fn test_parse_jj_native_diff() {
+        let diff_content = "Modified regular file src/main.rs:\n   1    1: fn main() {\n   2     :     old();\n        2:     new();\n        3:     more();\n   3    4: }\nAdded regular file notes.txt:\n        1: hello\nRenamed regular file a.rs => b.rs:\n   1    1: same\n";
+
+        // Git-format parsing finds nothing, so the native format is used
+        assert!(DiffParser::parse(diff_content).is_empty());
+        let diffs = DiffParser::parse_input(diff_content);
+        assert_eq!(diffs.len(), 3);
+
+        assert_eq!(diffs[0].filename, "src/main.rs");
+        assert_eq!(diffs[0].change_kind, ChangeKind::Modified);
+        assert_eq!((diffs[0].added_lines, diffs[0].removed_lines), (2, 1));
+        assert!(
+            diffs[0]
+                .content
+                .starts_with("Modified regular file src/main.rs:\n")
+        );
+        assert!(diffs[0].content.ends_with("   3    4: }\n"));
+
+        assert_eq!(diffs[1].filename, "notes.txt");
+        assert_eq!(diffs[1].change_kind, ChangeKind::Added);
+        assert_eq!(diffs[1].added_lines, 1);
+
+        assert_eq!(diffs[2].filename, "b.rs");
+        assert_eq!(diffs[2].old_path.as_deref(), Some("a/a.rs"));
+        assert_eq!(diffs[2].change_kind, ChangeKind::Renamed);
+
+        // Colored output is recognized too, and the colors are kept for display
+        let colored = "\x1b[1mModified regular file x.rs:\x1b[0m\n\x1b[31m   1\x1b[0m     : gone\n";
+        let diffs = DiffParser::parse_input(colored);
+        assert_eq!(diffs[0].filename, "x.rs");
+        assert_eq!(diffs[0].removed_lines, 1);
+        assert!(diffs[0].content.contains("\x1b[31m"));
+    }
+
+    #[test]
+    fn test_parse_unknown_input_as_whole_diff() {
+        let diffs = DiffParser::parse_input("--- a\n+++ b\n@@ -1 +1 @@\n-x\n+y\n");
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].filename, WHOLE_INPUT_FILENAME);
+        assert_eq!((diffs[0].added_lines, diffs[0].removed_lines), (1, 1));
+
+        assert!(DiffParser::parse_input("\n").is_empty());
+    }
+
+    #[test]
+    fn test_parse_change_kind_and_summary() {
+        let diff_content = r#"diff --git a/new.rs b/new.rs
+new file mode 100644
+index 0000000..abcdefg
+--- /dev/null
++++ b/new.rs
+@@ -0,0 +1 @@
++fn main() {}
+diff --git a/old.rs b/old.rs
+deleted file mode 100644
+index 1234567..0000000
+--- a/old.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-fn main() {}
+"#;
+
+        let diffs = DiffParser::parse(diff_content);
+        assert_eq!(diffs[0].change_kind, ChangeKind::Added);
+        assert_eq!(diffs[1].change_kind, ChangeKind::Deleted);
+
+        let summary = FileDiffSummary::from(&diffs[0]);
+        assert_eq!(summary.old_path, None);
+        assert_eq!(summary.new_path.as_deref(), Some("new.rs"));
+        assert_eq!(summary.added_lines, 1);
+
+        let json = serde_json::to_string(&summary).unwrap();
+        assert!(json.contains(r#""change_kind":"added""#));
+    }
+    #[test]
+    fn test_parse_rename_similarity() {
+        let diff_content = r#"diff --git a/src/old.rs b/src/new.rs
+similarity index 87%
+rename from src/old.rs
+rename to src/new.rs
+index 1234567..abcdefg 100644
+--- a/src/old.rs
++++ b/src/new.rs
+@@ -1 +1 @@
+-fn old() {}
++fn new() {}
+diff --git a/a.rs b/a.rs
+index 1234567..abcdefg 100644
+--- a/a.rs
++++ b/a.rs
+@@ -1 +1 @@
+-x
++y
+"#;
+
+        let diffs = DiffParser::parse(diff_content);
+        assert_eq!(diffs[0].change_kind, ChangeKind::Renamed);
+        assert_eq!(diffs[0].filename, "src/new.rs");
+        assert_eq!(diffs[0].diff_key.as_ref().unwrap().file_path, "src/new.rs");
+        assert_eq!(diffs[0].rename_from.as_deref(), Some("src/old.rs"));
+        assert_eq!(diffs[0].similarity, Some(87));
+        assert_eq!(
+            diffs[0].rename_summary().as_deref(),
+            Some("renamed from src/old.rs (87% similar)")
+        );
+        assert_eq!(diffs[1].rename_summary(), None);
+    }
+
+    #[test]
+    fn test_parse_summary() {
+        let output = concat!(
+            ":100644 100644 1234567 abcdef0 M\0src/main.rs\0",
+            ":000000 100644 0000000 0000000 A\0new.txt\0",
+            ":100644 100644 1111111 2222222 R087\0old.rs\0renamed.rs\0",
+            ":100644 000000 3333333 0000000 D\0gone.bin\0",
+            "3\t1\tsrc/main.rs\0",
+            "2\t0\tnew.txt\0",
+            "1\t1\t\0old.rs\0renamed.rs\0",
+            "-\t-\tgone.bin\0",
+        );
+
+        let diffs = DiffParser::parse_summary(output);
+        assert_eq!(diffs.len(), 4);
+        assert!(diffs.iter().all(FileDiff::is_summary_only));
+
+        assert_eq!(diffs[0].filename, "src/main.rs");
+        assert_eq!((diffs[0].added_lines, diffs[0].removed_lines), (3, 1));
+        assert_eq!(diffs[0].diff_key.as_ref().unwrap().to_hash, "abcdef0");
+
+        // The working tree side of a new file is not hashed, so it has no persistence key
+        assert_eq!(diffs[1].change_kind, ChangeKind::Added);
+        assert_eq!(diffs[1].old_path.as_deref(), Some("/dev/null"));
+        assert!(diffs[1].diff_key.is_none());
+
+        assert_eq!(diffs[2].change_kind, ChangeKind::Renamed);
+        assert_eq!(diffs[2].filename, "renamed.rs");
+        assert_eq!(diffs[2].rename_from.as_deref(), Some("old.rs"));
+        assert_eq!(diffs[2].similarity, Some(87));
+        assert_eq!((diffs[2].added_lines, diffs[2].removed_lines), (1, 1));
+
+        assert_eq!(diffs[3].change_kind, ChangeKind::Deleted);
+        assert_eq!(diffs[3].new_path.as_deref(), Some("/dev/null"));
+        assert_eq!((diffs[3].added_lines, diffs[3].removed_lines), (0, 0));
+        assert!(diffs[3].diff_key.is_some());
+    }
 }
@@ -1,58 +1,247 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
 
+use crate::config::{CheckScope, xdg_base_dir};
 use crate::parser::DiffFileKey;
 
+/// Maximum number of search queries kept in the history
+pub const MAX_SEARCH_HISTORY: usize = 20;
+
+/// Longest readable path part of a check state file name, in bytes
+const MAX_READABLE_PATH_LEN: usize = 80;
+
 #[derive(Debug, Serialize, Deserialize)]
 struct CheckState {
     checked_files: HashSet<String>,
+    /// Hunks reviewed in a file that is not checked as a whole, by `hunk_id`
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    checked_hunks: HashSet<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SearchHistory {
+    queries: Vec<String>,
 }
 
 pub struct PersistenceManager {
     base_dir: PathBuf,
+    history_path: PathBuf,
+    check_scope: CheckScope,
+    repo_id: Option<String>, // Identifies the repository for per-path check states
+    writer: BackgroundWriter, // Writes check states without blocking the UI
+}
+
+enum WriteRequest {
+    Write(PathBuf, String),
+    Remove(PathBuf),
+    Flush(mpsc::Sender<Option<String>>), // Answered with the first failure since the last flush
+}
+
+/// Writes files on a background thread, so toggling check marks never waits for the disk
+///
+/// Requests that queue up while the thread is writing are handled as one batch, in which only
+/// the latest content of each file is written (or the file removed, if that came last).
+struct BackgroundWriter {
+    sender: mpsc::Sender<WriteRequest>,
+}
+
+impl BackgroundWriter {
+    fn spawn() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || Self::run(receiver));
+        Self { sender }
+    }
+
+    fn run(receiver: mpsc::Receiver<WriteRequest>) {
+        let mut failure: Option<String> = None;
+        while let Ok(first) = receiver.recv() {
+            let mut batch = HashMap::new();
+            let mut flushes = Vec::new();
+            for request in std::iter::once(first).chain(receiver.try_iter()) {
+                match request {
+                    WriteRequest::Write(path, content) => {
+                        batch.insert(path, Some(content));
+                    }
+                    WriteRequest::Remove(path) => {
+                        batch.insert(path, None);
+                    }
+                    WriteRequest::Flush(reply) => flushes.push(reply),
+                }
+            }
+
+            for (path, content) in batch {
+                let result = match content {
+                    Some(content) => fs::write(&path, content),
+                    None => match fs::remove_file(&path) {
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                        result => result,
+                    },
+                };
+                if let Err(e) = result {
+                    failure.get_or_insert_with(|| format!("Failed to write check state: {e}"));
+                }
+            }
+            for reply in flushes {
+                let _ = reply.send(failure.take());
+            }
+        }
+    }
+
+    fn write(&self, path: PathBuf, content: String) -> Result<()> {
+        self.sender
+            .send(WriteRequest::Write(path, content))
+            .map_err(|_| anyhow::anyhow!("Check state writer has stopped"))
+    }
+
+    fn remove(&self, path: PathBuf) -> Result<()> {
+        self.sender
+            .send(WriteRequest::Remove(path))
+            .map_err(|_| anyhow::anyhow!("Check state writer has stopped"))
+    }
+
+    /// Wait until every queued write is on disk
+    fn flush(&self) -> Result<()> {
+        let (reply, done) = mpsc::channel();
+        self.sender
+            .send(WriteRequest::Flush(reply))
+            .map_err(|_| anyhow::anyhow!("Check state writer has stopped"))?;
+        match done.recv() {
+            Ok(Some(failure)) => Err(anyhow::anyhow!(failure)),
+            Ok(None) => Ok(()),
+            Err(_) => Err(anyhow::anyhow!("Check state writer has stopped")),
+        }
+    }
+}
+
+impl Drop for BackgroundWriter {
+    fn drop(&mut self) {
+        // Don't lose checks made right before quitting
+        let _ = self.flush();
+    }
 }
 
 impl PersistenceManager {
-    pub fn new() -> Result<Self> {
-        let base_dir = Self::get_base_directory()?;
+    /// Create a manager whose check states are scoped to the given repository directory
+    ///
+    /// The directory is only needed for per-path check states and for clearing those of one
+    /// repository, so other callers may leave it out and skip resolving it.
+    pub fn new(check_scope: CheckScope, repo_dir: Option<&Path>) -> Result<Self> {
+        if check_scope == CheckScope::PerPath && repo_dir.is_none() {
+            anyhow::bail!("Per-path check states need the repository directory");
+        }
+        let data_dir = Self::get_data_directory()?;
+        let base_dir = data_dir.join("checks");
         fs::create_dir_all(&base_dir)?;
 
-        Ok(Self { base_dir })
+        Ok(Self {
+            base_dir,
+            history_path: data_dir.join("search_history.json"),
+            check_scope,
+            repo_id: repo_dir.map(repo_id),
+            writer: BackgroundWriter::spawn(),
+        })
+    }
+
+    /// Wait for check states saved so far to be written to disk
+    pub fn flush(&self) -> Result<()> {
+        self.writer.flush()
     }
 
-    fn get_base_directory() -> Result<PathBuf> {
-        let home_dir =
-            dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Cannot find home directory"))?;
+    fn get_data_directory() -> Result<PathBuf> {
+        Ok(xdg_base_dir("XDG_DATA_HOME", ".local/share")?.join("ftdv"))
+    }
+
+    /// Load recent search queries, oldest first
+    pub fn load_search_history(&self) -> Result<Vec<String>> {
+        if !self.history_path.exists() {
+            return Ok(Vec::new());
+        }
 
-        Ok(home_dir.join(".local/share/ftdv/checks"))
+        let content = fs::read_to_string(&self.history_path)?;
+        let history: SearchHistory = serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse search history: {}", e))?;
+
+        Ok(history.queries)
     }
 
+    /// Save recent search queries, oldest first
+    pub fn save_search_history(&self, queries: &[String]) -> Result<()> {
+        let history = SearchHistory {
+            queries: queries.to_vec(),
+        };
+        let content = serde_json::to_string_pretty(&history)?;
+
+        fs::write(&self.history_path, content)
+            .map_err(|e| anyhow::anyhow!("Failed to write search history: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Get the file a check state is stored in
+    ///
+    /// The name keeps a readable, sanitized form of the path, followed by a hash of the exact
+    /// path, so long paths, characters that are invalid in file names, and paths that sanitize
+    /// to the same text all get a valid file of their own.
     fn get_check_file_path(&self, key: &DiffFileKey) -> PathBuf {
-        // Create a safe filename from the key
-        let safe_filename = format!(
-            "{}_{}_{}",
-            key.from_hash,
-            key.to_hash,
-            key.file_path.replace(['/', '\\'], "_")
+        let safe_path = format!(
+            "{}_{}",
+            readable_path(&key.file_path),
+            stable_hash(&key.file_path)
         );
+        self.base_dir
+            .join(format!("{}.json", self.check_file_stem(key, &safe_path)))
+    }
 
-        self.base_dir.join(format!("{safe_filename}.json"))
+    /// Get the file check states were stored in before names carried a hash of the path
+    fn legacy_check_file_path(&self, key: &DiffFileKey) -> PathBuf {
+        let safe_path = key.file_path.replace(['/', '\\'], "_");
+        self.base_dir
+            .join(format!("{}.json", self.check_file_stem(key, &safe_path)))
+    }
+
+    fn check_file_stem(&self, key: &DiffFileKey, safe_path: &str) -> String {
+        match self.check_scope {
+            CheckScope::PerPath => {
+                let repo_id = self.repo_id.as_deref().unwrap_or_default();
+                format!("path_{repo_id}_{safe_path}")
+            }
+            CheckScope::PerBlob | CheckScope::Off => {
+                format!("{}_{}_{}", key.from_hash, key.to_hash, safe_path)
+            }
+        }
+    }
+
+    fn load_check_state(&self, key: &DiffFileKey) -> Result<Option<CheckState>> {
+        if self.check_scope == CheckScope::Off {
+            return Ok(None);
+        }
+        // Fall back to the legacy name, so checks made by older versions are kept
+        let Some(file_path) = [
+            self.get_check_file_path(key),
+            self.legacy_check_file_path(key),
+        ]
+        .into_iter()
+        .find(|path| path.exists()) else {
+            return Ok(None);
+        };
+
+        let content = fs::read_to_string(&file_path)?;
+        let check_state = serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse check state: {}", e))?;
+        Ok(Some(check_state))
     }
 
     pub fn load_checked_files(&self, keys: &[DiffFileKey]) -> Result<HashSet<String>> {
+        // Read what was saved last, not what was on disk before
+        self.flush()?;
         let mut all_checked = HashSet::new();
 
         for key in keys {
-            let file_path = self.get_check_file_path(key);
-
-            if file_path.exists() {
-                let content = fs::read_to_string(&file_path)?;
-                let check_state: CheckState = serde_json::from_str(&content)
-                    .map_err(|e| anyhow::anyhow!("Failed to parse check state: {}", e))?;
-
+            if let Some(check_state) = self.load_check_state(key)? {
                 // Add the file path to checked set if it was checked
                 if check_state.checked_files.contains(&key.file_path) {
                     all_checked.insert(key.file_path.clone());
@@ -63,33 +252,141 @@ impl PersistenceManager {
         Ok(all_checked)
     }
 
-    pub fn save_check_state(&self, key: &DiffFileKey, is_checked: bool) -> Result<()> {
-        let file_path = self.get_check_file_path(key);
+    /// Load the checked hunks of files that are only partially reviewed, by file path
+    pub fn load_checked_hunks(
+        &self,
+        keys: &[DiffFileKey],
+    ) -> Result<HashMap<String, HashSet<String>>> {
+        self.flush()?;
+        let mut all_hunks = HashMap::new();
 
-        let mut checked_files = HashSet::new();
-        if is_checked {
-            checked_files.insert(key.file_path.clone());
+        for key in keys {
+            if let Some(check_state) = self.load_check_state(key)? {
+                if !check_state.checked_hunks.is_empty() {
+                    all_hunks.insert(key.file_path.clone(), check_state.checked_hunks);
+                }
+            }
         }
 
-        let check_state = CheckState { checked_files };
-        let content = serde_json::to_string_pretty(&check_state)?;
+        Ok(all_hunks)
+    }
 
-        fs::write(&file_path, content)
-            .map_err(|e| anyhow::anyhow!("Failed to write check state: {}", e))?;
+    /// Save whether a whole file is checked, dropping any per-hunk state
+    ///
+    /// An unchecked file has nothing left to remember, so its state is removed.
+    pub fn save_check_state(&self, key: &DiffFileKey, is_checked: bool) -> Result<()> {
+        if !is_checked {
+            return self.remove_check_state(key);
+        }
 
-        Ok(())
+        self.write_check_state(
+            key,
+            CheckState {
+                checked_files: HashSet::from([key.file_path.clone()]),
+                checked_hunks: HashSet::new(),
+            },
+        )
     }
 
-    #[allow(dead_code)]
-    pub fn remove_check_state(&self, key: &DiffFileKey) -> Result<()> {
+    /// Save the checked hunks of a file that is not checked as a whole
+    pub fn save_checked_hunks(&self, key: &DiffFileKey, hunks: &HashSet<String>) -> Result<()> {
+        self.write_check_state(
+            key,
+            CheckState {
+                checked_files: HashSet::new(),
+                checked_hunks: hunks.clone(),
+            },
+        )
+    }
+
+    /// Queue a check state to be written in the background
+    fn write_check_state(&self, key: &DiffFileKey, check_state: CheckState) -> Result<()> {
+        if self.check_scope == CheckScope::Off {
+            return Ok(());
+        }
         let file_path = self.get_check_file_path(key);
 
-        if file_path.exists() {
-            fs::remove_file(&file_path)
-                .map_err(|e| anyhow::anyhow!("Failed to remove check state: {}", e))?;
+        let content = serde_json::to_string_pretty(&check_state)?;
+        self.writer.write(file_path, content)
+    }
+
+    /// Queue the check state of a file to be removed, under its current and legacy names
+    pub fn remove_check_state(&self, key: &DiffFileKey) -> Result<()> {
+        if self.check_scope == CheckScope::Off {
+            return Ok(());
         }
+        self.writer.remove(self.get_check_file_path(key))?;
+        self.writer.remove(self.legacy_check_file_path(key))
+    }
 
-        Ok(())
+    /// Get the file names (without extension) of all stored check states, by path
+    fn check_state_files(&self) -> Result<Vec<(PathBuf, String)>> {
+        self.flush()?;
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.base_dir)? {
+            let path = entry?.path();
+            if path != self.history_path
+                && path
+                    .extension()
+                    .is_some_and(|extension| extension == "json")
+            {
+                if let Some(stem) = path.file_stem() {
+                    let stem = stem.to_string_lossy().into_owned();
+                    files.push((path, stem));
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    /// Get the blob hashes that per-blob check states were saved for
+    pub fn stored_blob_hashes(&self) -> Result<Vec<String>> {
+        let mut hashes: Vec<String> = self
+            .check_state_files()?
+            .iter()
+            .filter_map(|(_, stem)| blob_hashes(stem))
+            .flat_map(|(from_hash, to_hash)| [from_hash.to_string(), to_hash.to_string()])
+            .collect();
+        hashes.sort();
+        hashes.dedup();
+        Ok(hashes)
+    }
+
+    /// Get the check state files to clear: all of them, or only those of this repository
+    ///
+    /// Per-blob states don't record their repository, so they count as this repository's
+    /// when one of their blobs is in `repo_blobs`.
+    pub fn check_states_to_clear(
+        &self,
+        all: bool,
+        repo_blobs: &HashSet<String>,
+    ) -> Result<Vec<PathBuf>> {
+        let repo_prefix = self.repo_id.as_ref().map(|id| format!("path_{id}_"));
+        Ok(self
+            .check_state_files()?
+            .into_iter()
+            .filter(|(_, stem)| {
+                all || repo_prefix
+                    .as_ref()
+                    .is_some_and(|prefix| stem.starts_with(prefix))
+                    || blob_hashes(stem).is_some_and(|(from_hash, to_hash)| {
+                        repo_blobs.contains(from_hash) || repo_blobs.contains(to_hash)
+                    })
+            })
+            .map(|(path, _)| path)
+            .collect())
+    }
+
+    /// Remove check state files found by `check_states_to_clear`, returning how many were removed
+    ///
+    /// Like `remove_check_state`, the removals go through the background writer, which is
+    /// flushed before returning.
+    pub fn clear_check_states(&self, files: &[PathBuf]) -> Result<usize> {
+        for file_path in files {
+            self.writer.remove(file_path.clone())?;
+        }
+        self.flush()?;
+        Ok(files.len())
     }
 
     // Optional: cleanup old check files
@@ -115,19 +412,100 @@ impl PersistenceManager {
     }
 }
 
+/// Hash text into a stable identifier, usable in file names
+///
+/// Uses FNV-1a rather than the std hasher, whose output may change between Rust releases.
+fn stable_hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{hash:016x}")
+}
+
+/// Make a path readable in a file name: only ASCII letters, digits, `.`, `-` and `_` are kept,
+/// and long paths keep their end, where the file name is
+fn readable_path(path: &str) -> String {
+    let readable: String = path
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    // Every character is ASCII now, so any byte offset is a character boundary
+    readable[readable.len().saturating_sub(MAX_READABLE_PATH_LEN)..].to_string()
+}
+
+/// Get the two blob hashes a per-blob check state file is named after
+fn blob_hashes(stem: &str) -> Option<(&str, &str)> {
+    if stem.starts_with("path_") {
+        return None;
+    }
+    let (from_hash, rest) = stem.split_once('_')?;
+    let (to_hash, _) = rest.split_once('_')?;
+    Some((from_hash, to_hash))
+}
+
+/// Get a stable identifier for a repository directory
+fn repo_id(repo_dir: &Path) -> String {
+    stable_hash(&repo_dir.to_string_lossy())
+}
+
+/// Get the identifier a hunk's check state is stored under, from its "@@ ... @@" header
+pub fn hunk_id(header: &str) -> String {
+    stable_hash(header.trim_end())
+}
+
+/// Record a query as the most recent history entry, dropping duplicates and old entries
+pub fn push_search_history(history: &mut Vec<String>, query: &str) {
+    if query.is_empty() {
+        return;
+    }
+
+    history.retain(|entry| entry != query);
+    history.push(query.to_string());
+
+    if history.len() > MAX_SEARCH_HISTORY {
+        let excess = history.len() - MAX_SEARCH_HISTORY;
+        history.drain(..excess);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::TempDir;
 
     fn create_test_manager() -> (PersistenceManager, TempDir) {
+        create_scoped_test_manager(CheckScope::PerBlob, Path::new("/repo"))
+    }
+
+    fn create_scoped_test_manager(
+        check_scope: CheckScope,
+        repo_dir: &Path,
+    ) -> (PersistenceManager, TempDir) {
         let temp_dir = TempDir::new().unwrap();
         let manager = PersistenceManager {
             base_dir: temp_dir.path().to_path_buf(),
+            history_path: temp_dir.path().join("search_history.json"),
+            check_scope,
+            repo_id: Some(repo_id(repo_dir)),
+            writer: BackgroundWriter::spawn(),
         };
         (manager, temp_dir)
     }
 
+    fn key(from_hash: &str, to_hash: &str) -> DiffFileKey {
+        DiffFileKey {
+            from_hash: from_hash.to_string(),
+            to_hash: to_hash.to_string(),
+            file_path: "src/main.rs".to_string(),
+        }
+    }
+
     #[test]
     fn test_save_and_load_check_state() {
         let (manager, _temp_dir) = create_test_manager();
@@ -142,7 +520,9 @@ mod tests {
         manager.save_check_state(&key, true).unwrap();
 
         // Load and verify
-        let checked = manager.load_checked_files(&[key.clone()]).unwrap();
+        let checked = manager
+            .load_checked_files(std::slice::from_ref(&key))
+            .unwrap();
         assert!(checked.contains("src/main.rs"));
 
         // Save unchecked state
@@ -153,6 +533,203 @@ mod tests {
         assert!(!checked.contains("src/main.rs"));
     }
 
+    #[test]
+    fn test_save_and_load_checked_hunks() {
+        let (manager, _temp_dir) = create_test_manager();
+        let hunks: HashSet<String> = [hunk_id("@@ -1,3 +1,3 @@ fn main()")].into();
+
+        manager
+            .save_checked_hunks(&key("abc123", "def456"), &hunks)
+            .unwrap();
+        let keys = [key("abc123", "def456")];
+        assert!(manager.load_checked_files(&keys).unwrap().is_empty());
+        assert_eq!(
+            manager.load_checked_hunks(&keys).unwrap()["src/main.rs"],
+            hunks
+        );
+
+        // Checking the whole file drops the per-hunk state
+        manager.save_check_state(&keys[0], true).unwrap();
+        assert!(
+            manager
+                .load_checked_files(&keys)
+                .unwrap()
+                .contains("src/main.rs")
+        );
+        assert!(manager.load_checked_hunks(&keys).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_background_writes_are_batched_and_flushed() {
+        let (manager, temp_dir) = create_test_manager();
+        for i in 0..50 {
+            manager
+                .save_check_state(&key("abc123", "def456"), i % 2 == 0)
+                .unwrap();
+        }
+        manager.flush().unwrap();
+        // The last of the queued states wins
+        let keys = [key("abc123", "def456")];
+        assert!(manager.load_checked_files(&keys).unwrap().is_empty());
+
+        // Failures surface when flushing, since writes no longer report them directly
+        drop(temp_dir);
+        manager.save_check_state(&keys[0], true).unwrap();
+        assert!(manager.flush().is_err());
+        assert!(manager.flush().is_ok());
+    }
+
+    #[test]
+    fn test_per_blob_scope_drops_checks_when_content_changes() {
+        let (manager, _temp_dir) = create_test_manager();
+        manager
+            .save_check_state(&key("abc123", "def456"), true)
+            .unwrap();
+
+        let checked = manager
+            .load_checked_files(&[key("abc123", "fed654")])
+            .unwrap();
+        assert!(checked.is_empty());
+    }
+
+    #[test]
+    fn test_per_path_scope_survives_content_changes() {
+        let (manager, temp_dir) = create_scoped_test_manager(CheckScope::PerPath, Path::new("/a"));
+        manager
+            .save_check_state(&key("abc123", "def456"), true)
+            .unwrap();
+
+        let checked = manager
+            .load_checked_files(&[key("abc123", "fed654")])
+            .unwrap();
+        assert!(checked.contains("src/main.rs"));
+
+        // The same path in another repository is a different file
+        let other_repo = PersistenceManager {
+            base_dir: temp_dir.path().to_path_buf(),
+            history_path: temp_dir.path().join("search_history.json"),
+            check_scope: CheckScope::PerPath,
+            repo_id: Some(repo_id(Path::new("/b"))),
+            writer: BackgroundWriter::spawn(),
+        };
+        let checked = other_repo
+            .load_checked_files(&[key("abc123", "def456")])
+            .unwrap();
+        assert!(checked.is_empty());
+
+        manager
+            .save_check_state(&key("000000", "111111"), false)
+            .unwrap();
+        let checked = manager
+            .load_checked_files(&[key("abc123", "def456")])
+            .unwrap();
+        assert!(checked.is_empty());
+    }
+
+    #[test]
+    fn test_per_path_scope_needs_repo_dir() {
+        assert!(PersistenceManager::new(CheckScope::PerPath, None).is_err());
+    }
+
+    #[test]
+    fn test_off_scope_persists_nothing() {
+        let (manager, temp_dir) = create_scoped_test_manager(CheckScope::Off, Path::new("/a"));
+        manager
+            .save_check_state(&key("abc123", "def456"), true)
+            .unwrap();
+
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+        let checked = manager
+            .load_checked_files(&[key("abc123", "def456")])
+            .unwrap();
+        assert!(checked.is_empty());
+    }
+
+    #[test]
+    fn test_clear_check_states() {
+        let (manager, temp_dir) = create_scoped_test_manager(CheckScope::PerPath, Path::new("/a"));
+        manager
+            .save_check_state(&key("abc123", "def456"), true)
+            .unwrap();
+        let other_repo = PersistenceManager {
+            base_dir: temp_dir.path().to_path_buf(),
+            history_path: temp_dir.path().join("search_history.json"),
+            check_scope: CheckScope::PerBlob,
+            repo_id: Some(repo_id(Path::new("/b"))),
+            writer: BackgroundWriter::spawn(),
+        };
+        other_repo
+            .save_check_state(&key("abc123", "def456"), true)
+            .unwrap();
+        other_repo
+            .save_check_state(&key("0000000", "fed654"), true)
+            .unwrap();
+        other_repo.flush().unwrap();
+        manager.save_search_history(&["main".to_string()]).unwrap();
+
+        assert_eq!(
+            manager.stored_blob_hashes().unwrap(),
+            ["0000000", "abc123", "def456", "fed654"]
+        );
+
+        // Per-path states of this repository, and per-blob states of its blobs
+        let repo_blobs = HashSet::from(["fed654".to_string()]);
+        let files = manager.check_states_to_clear(false, &repo_blobs).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(manager.clear_check_states(&files).unwrap(), 2);
+        assert!(
+            other_repo
+                .load_checked_files(&[key("abc123", "def456")])
+                .unwrap()
+                .contains("src/main.rs")
+        );
+
+        // Everything else, leaving the search history alone
+        let files = manager
+            .check_states_to_clear(true, &HashSet::new())
+            .unwrap();
+        assert_eq!(manager.clear_check_states(&files).unwrap(), 1);
+        assert_eq!(manager.load_search_history().unwrap(), ["main"]);
+    }
+
+    #[test]
+    fn test_data_directory_is_under_xdg_data_home() {
+        // Resolving XDG_DATA_HOME itself is covered by the config tests, without touching the
+        // environment other tests read
+        assert_eq!(
+            PersistenceManager::get_data_directory().unwrap(),
+            xdg_base_dir("XDG_DATA_HOME", ".local/share")
+                .unwrap()
+                .join("ftdv")
+        );
+    }
+
+    #[test]
+    fn test_search_history_round_trip() {
+        let (manager, _temp_dir) = create_test_manager();
+        assert!(manager.load_search_history().unwrap().is_empty());
+
+        let mut history = Vec::new();
+        push_search_history(&mut history, "main");
+        push_search_history(&mut history, "tree");
+        push_search_history(&mut history, "main");
+        push_search_history(&mut history, "");
+        assert_eq!(history, vec!["tree".to_string(), "main".to_string()]);
+
+        manager.save_search_history(&history).unwrap();
+        assert_eq!(manager.load_search_history().unwrap(), history);
+    }
+
+    #[test]
+    fn test_search_history_is_capped() {
+        let mut history = Vec::new();
+        for i in 0..MAX_SEARCH_HISTORY + 5 {
+            push_search_history(&mut history, &format!("query{i}"));
+        }
+        assert_eq!(history.len(), MAX_SEARCH_HISTORY);
+        assert_eq!(history[0], "query5");
+    }
+
     #[test]
     fn test_file_path_safety() {
         let (manager, _temp_dir) = create_test_manager();
@@ -169,5 +746,57 @@ mod tests {
         // Should not contain slashes in filename
         assert!(!filename.contains('/'));
         assert!(filename.contains("deep_path_with_slashes.rs"));
+
+        // Paths that sanitize to the same text still get distinct files
+        let other_key = DiffFileKey {
+            file_path: "deep_path/with:slashes.rs".to_string(),
+            ..key.clone()
+        };
+        assert_ne!(file_path, manager.get_check_file_path(&other_key));
+        manager.save_check_state(&key, true).unwrap();
+        assert!(manager.load_checked_files(&[other_key]).unwrap().is_empty());
+
+        // Long and non-ASCII paths get short, portable file names
+        let long_key = DiffFileKey {
+            file_path: format!("{}/日本語: ファイル?.rs", "nested/".repeat(50)),
+            ..key.clone()
+        };
+        let long_path = manager.get_check_file_path(&long_key);
+        let filename = long_path.file_name().unwrap().to_str().unwrap();
+        assert!(filename.len() < 120, "{filename}");
+        assert!(filename.is_ascii());
+        assert!(filename.contains(".rs_"));
+        manager.save_check_state(&long_key, true).unwrap();
+        assert_eq!(manager.load_checked_files(&[long_key]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_legacy_check_file_names_are_read() {
+        let (manager, _temp_dir) = create_test_manager();
+        let key = key("abc123", "def456");
+
+        fs::write(
+            manager.legacy_check_file_path(&key),
+            r#"{"checked_files":["src/main.rs"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            manager
+                .load_checked_files(std::slice::from_ref(&key))
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // Unchecking removes the state under both names
+        manager.save_check_state(&key, false).unwrap();
+        assert!(
+            manager
+                .load_checked_files(std::slice::from_ref(&key))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(!manager.legacy_check_file_path(&key).exists());
+        assert!(!manager.get_check_file_path(&key).exists());
     }
 }
@@ -1,12 +1,22 @@
-use crate::App;
+use crate::parser::{ChangeKind, DiffParser, FileDiff};
+use crate::theme::Theme;
+use crate::tree::{FileTreeBuilder, FileTreeItem};
+use crate::{App, CheckMark, Overlay, Pane, Prompt};
 use ansi_to_tui::IntoText;
 use ratatui::{
     Frame,
-    layout::Rect,
+    layout::{Constraint, Direction, Layout, Margin, Rect},
     style::Style,
     text::{Line, Span, Text},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    widgets::{
+        Block, Borders, Clear, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation,
+        ScrollbarState, Wrap,
+    },
 };
+use std::time::{Duration, Instant};
+
+/// Shown in place of git's "\ No newline at end of file" marker
+const NO_NEWLINE_ANNOTATION: &str = "⏎ no newline at EOF";
 
 pub fn render_file_list(f: &mut Frame, area: Rect, app: &mut App) {
     let available_width = area.width.saturating_sub(4) as usize; // Account for borders and padding
@@ -19,11 +29,15 @@ pub fn render_file_list(f: &mut Frame, area: Rect, app: &mut App) {
         .enumerate()
         .map(|(i, tree_item)| {
             let is_selected = i == app.selected_index;
-            let bg_style = if is_selected {
+            let mut bg_style = if is_selected {
                 Style::default().bg(app.theme.colors.tree_selected_bg.0)
             } else {
                 Style::default()
             };
+            // Confirm a check toggle with a brief flash of the row
+            if app.is_check_flashing(&tree_item.full_path) {
+                bg_style = bg_style.add_modifier(ratatui::style::Modifier::REVERSED);
+            }
 
             // Build tree structure with styled spans
             let mut spans = Vec::new();
@@ -65,17 +79,18 @@ pub fn render_file_list(f: &mut Frame, area: Rect, app: &mut App) {
                 ));
             }
 
-            // Add checkbox for files (not directories)
-            if !tree_item.is_directory {
-                let is_checked = app.checked_files.contains(&tree_item.full_path);
-                let checkbox_char = if is_checked { '☑' } else { '☐' };
-                let checkbox_style = if is_selected {
-                    Style::default().fg(app.theme.colors.tree_selected_fg.0)
-                } else {
-                    Style::default().fg(app.theme.colors.text_primary.0)
-                };
-                spans.push(Span::styled(format!("{checkbox_char} "), checkbox_style));
-            }
+            // Add checkbox, for directories the aggregate state of the files under them
+            let checkbox_char = match app.check_mark(tree_item) {
+                CheckMark::Checked => '☑',
+                CheckMark::Partial => '▣', // Some hunks or files are checked
+                CheckMark::Unchecked => '☐',
+            };
+            let checkbox_style = if is_selected {
+                Style::default().fg(app.theme.colors.tree_selected_fg.0)
+            } else {
+                Style::default().fg(app.theme.colors.text_primary.0)
+            };
+            spans.push(Span::styled(format!("{checkbox_char} "), checkbox_style));
 
             // Get icon based on item type
             let icon = if tree_item.is_directory {
@@ -85,7 +100,7 @@ pub fn render_file_list(f: &mut Frame, area: Rect, app: &mut App) {
                 tree_item
                     .file_diff
                     .as_ref()
-                    .map(|fd| fd.get_file_icon())
+                    .map(|fd| fd.get_file_icon(&app.icon_overrides))
                     .unwrap_or(crate::icons::get_file_icon(""))
             };
 
@@ -102,10 +117,17 @@ pub fn render_file_list(f: &mut Frame, area: Rect, app: &mut App) {
             }
 
             // Add file/directory name with appropriate color
+            let is_untracked = tree_item
+                .file_diff
+                .as_ref()
+                .is_some_and(|file_diff| file_diff.is_untracked);
             let name_style = if is_selected {
                 Style::default().fg(app.theme.colors.tree_selected_fg.0)
             } else if tree_item.is_directory {
                 Style::default().fg(app.theme.colors.tree_directory.0)
+            } else if is_untracked && !app.checked_files.contains(&tree_item.full_path) {
+                // Untracked files stand out in the added color
+                Style::default().fg(app.theme.colors.status_added.0)
             } else {
                 // Check if file is checked to dim the color
                 let is_checked = app.checked_files.contains(&tree_item.full_path);
@@ -119,26 +141,56 @@ pub fn render_file_list(f: &mut Frame, area: Rect, app: &mut App) {
                 }
             };
 
+            // Expanded directories optionally keep a count of the files below them
+            let count_badge = (app.config.ui.expanded_dir_counts
+                && tree_item.is_directory
+                && tree_item.is_expanded
+                && tree_item.dir_file_count > 0)
+                .then(|| format!(" ({})", tree_item.dir_file_count));
+
             // Calculate available space for the name
             let tree_prefix_width = tree_prefix.chars().count();
-            let checkbox_width = if !tree_item.is_directory { 2 } else { 0 }; // Checkbox + space for files only
+            let checkbox_width = 2; // Checkbox + space
             let icon_width = 2; // Icon + space
-            let stats_width = if tree_item.file_diff.is_some() { 10 } else { 0 }; // Rough estimate for stats
-            let used_width = tree_prefix_width + checkbox_width + icon_width + stats_width;
+            let stats_width = match &tree_item.file_diff {
+                // Rough estimate for stats, and for "N hunks" when shown
+                Some(_) if app.config.ui.hunk_counts => 19,
+                Some(_) => 10,
+                None => 0,
+            };
+            let badge_width = count_badge
+                .as_ref()
+                .map_or(0, |badge| badge.chars().count());
+            let used_width =
+                tree_prefix_width + checkbox_width + icon_width + stats_width + badge_width;
             let available_name_width = available_width.saturating_sub(used_width);
 
-            // Truncate name if too long
-            let display_name = if tree_item.name.chars().count() > available_name_width
-                && available_name_width > 3
-            {
+            // Truncate name if too long, keeping the end of full paths where the file name is
+            let show_full_path = app.shows_full_paths();
+            let name = tree_item.label(show_full_path);
+            let name_width = name.chars().count();
+            let display_name = if name_width > available_name_width && available_name_width > 3 {
                 let truncated_width = available_name_width.saturating_sub(3);
-                let truncated: String = tree_item.name.chars().take(truncated_width).collect();
-                format!("{truncated}...")
+                if show_full_path {
+                    let tail: String = name.chars().skip(name_width - truncated_width).collect();
+                    format!("...{tail}")
+                } else {
+                    let truncated: String = name.chars().take(truncated_width).collect();
+                    format!("{truncated}...")
+                }
             } else {
-                tree_item.name.clone()
+                name.to_string()
             };
 
             spans.push(Span::styled(display_name.clone(), name_style));
+            if let Some(badge) = count_badge {
+                spans.push(Span::styled(
+                    badge,
+                    Style::default()
+                        .fg(app.theme.colors.text_dim.0)
+                        .add_modifier(ratatui::style::Modifier::DIM),
+                ));
+            }
 
             // Add stats for files or collapsed directories
             let stats_to_show =
@@ -155,7 +207,12 @@ pub fn render_file_list(f: &mut Frame, area: Rect, app: &mut App) {
                     tree_item
                         .file_diff
                         .as_ref()
-                        .map(|file_diff| file_diff.diff_stats())
+                        .map(|file_diff| match file_diff.hunk_summary() {
+                            Some(hunks) if app.config.ui.hunk_counts => {
+                                format!(" {hunks}{}", file_diff.diff_stats())
+                            }
+                            _ => file_diff.diff_stats(),
+                        })
                 };
 
             if let Some(stats) = stats_to_show {
@@ -209,7 +266,17 @@ pub fn render_file_list(f: &mut Frame, area: Rect, app: &mut App) {
             )
         }
     } else {
-        format!(" Files & Directories ({} items)", current_items.len())
+        let mut details = vec![format!("{} items", current_items.len())];
+        if let Some(filter) = &app.config.git.diff_filter {
+            details.push(format!(
+                "filter: {}",
+                crate::git::describe_diff_filter(filter)
+            ));
+        }
+        if app.hidden_count > 0 {
+            details.push(format!("{} hidden", app.hidden_count));
+        }
+        format!(" Files & Directories ({})", details.join(", "))
     };
 
     let file_list = List::new(items)
@@ -217,23 +284,61 @@ pub fn render_file_list(f: &mut Frame, area: Rect, app: &mut App) {
             Block::default()
                 .borders(Borders::ALL)
                 .title(title)
-                .style(Style::default().fg(app.theme.colors.border.0)),
+                .style(pane_border_style(app, Pane::FileList)),
         )
         .style(Style::default().fg(app.theme.colors.text_primary.0));
 
+    // Remember the list height (inside the borders) for paging through it
+    app.file_list_viewport_height = area.height.saturating_sub(2);
     f.render_stateful_widget(file_list, area, &mut app.file_list_state);
 }
 
+/// Render the diff that was passed to the diff tool, scrolled along with its output
+fn render_raw_diff(f: &mut Frame, area: Rect, app: &App) {
+    let text = if app.contains_ansi_codes(&app.raw_diff_output) {
+        app.raw_diff_output
+            .into_text()
+            .unwrap_or_else(|_| Text::from(app.raw_diff_output.as_str()))
+    } else {
+        Text::from(app.raw_diff_output.as_str())
+    };
+
+    let raw_diff = Paragraph::new(text)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Raw git diff (--debug-pager)")
+                .style(Style::default().fg(app.theme.colors.border.0)),
+        )
+        .scroll((app.vertical_scroll, app.horizontal_scroll))
+        .wrap(Wrap { trim: false });
+
+    f.render_widget(raw_diff, area);
+}
+
 pub fn render_diff_content(f: &mut Frame, area: Rect, app: &mut App) {
+    // With --debug-pager, git's raw diff takes the left half and the diff tool the right
+    let area = if app.shows_debug_split() {
+        let halves = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(area);
+        render_raw_diff(f, halves[0], app);
+        halves[1]
+    } else {
+        area
+    };
+
     // Clamp scroll values before rendering
     app.clamp_scroll(area.height, area.width);
 
     // Check if we need to refresh diff with current width for side-by-side display
     // Use actual diff area width for maximum utilization
     if !matches!(
-        app.config.get_diff_command_type(),
+        app.diff_command_type(),
         crate::config::DiffCommandType::GitDefault
-    ) && should_refresh_diff_width(app, area.width)
+    ) && app.overlay.is_none()
+        && should_refresh_diff_width(app, area.width)
     {
         // Pass both terminal width and actual area width for flexible template calculation
         if let Ok((terminal_width, _)) = crossterm::terminal::size() {
@@ -244,7 +349,7 @@ pub fn render_diff_content(f: &mut Frame, area: Rect, app: &mut App) {
     }
 
     // Convert ANSI sequences to ratatui Text if they exist, otherwise use plain text
-    let text_content = if app.contains_ansi_codes(&app.diff_output) {
+    let mut text_content = if app.contains_ansi_codes(&app.diff_output) {
         // Parse ANSI codes using ansi-to-tui
         match app.diff_output.into_text() {
             Ok(text) => text,
@@ -257,91 +362,525 @@ pub fn render_diff_content(f: &mut Frame, area: Rect, app: &mut App) {
         // Plain text without ANSI codes
         Text::from(app.diff_output.as_str())
     };
+    // Only keep the lines measured by clamp_scroll when context lines are hidden
+    if let Some(visible) = app
+        .visible_lines
+        .as_ref()
+        .filter(|_| app.filters_changed_lines())
+    {
+        let mut lines = std::mem::take(&mut text_content.lines);
+        text_content.lines = visible
+            .iter()
+            .filter_map(|&i| lines.get_mut(i).map(std::mem::take))
+            .collect();
+    }
+
+    annotate_no_newline_markers(&mut text_content, app);
+    if app.shows_file_contents() {
+        highlight_conflict_sections(&mut text_content, app);
+    }
+    // Diff tools reformat their output, so only git's own diff lines can be analyzed
+    if app.highlight_whitespace
+        && matches!(
+            app.diff_command_type(),
+            crate::config::DiffCommandType::GitDefault
+        )
+    {
+        highlight_whitespace_errors(&mut text_content, app);
+    }
+    if app.overlay.is_none() {
+        annotate_hunk_checks(&mut text_content, app);
+    }
+    // Renames, mode changes and submodules print little or nothing, so say what changed.
+    // clamp_scroll has already counted these lines.
+    let prefix_lines = app.diff_prefix_lines();
+    if !prefix_lines.is_empty() {
+        let dim = Style::default().fg(app.theme.colors.text_dim.0);
+        let placeholder = prefix_lines.into_iter().enumerate().map(|(i, line)| {
+            let style = if i == 0 {
+                dim.add_modifier(ratatui::style::Modifier::ITALIC)
+            } else {
+                dim
+            };
+            Line::from(Span::styled(line, style))
+        });
+        if app.diff_output_is_blank() {
+            text_content.lines = placeholder.collect();
+        } else {
+            text_content.lines.splice(0..0, placeholder);
+        }
+    }
+
+    let title = match &app.overlay {
+        Some(Overlay::Range(range)) => {
+            format!("Diff Content ({range}) - [Esc: back to current diff]")
+        }
+        Some(Overlay::FileAtRevision { revision, path }) => {
+            format!("Viewing {path} at {revision} - [Esc: back to current diff]")
+        }
+        None => {
+            let revision = app
+                .revision_label
+                .as_ref()
+                .map(|label| format!("{label} - "))
+                .unwrap_or_default();
+            let context = if app.shows_whole_file_context() {
+                "whole file, "
+            } else {
+                ""
+            };
+            let navigation = match app.focus {
+                Pane::FileList => "files",
+                Pane::Diff => "scroll",
+            };
+            let resizing = if app.is_resizing() {
+                " resizing…"
+            } else {
+                ""
+            };
+            format!(
+                "Diff Content {revision}({context}using {}){resizing} - \
+                 [h/l: scroll, j/k: {navigation}, g/G: jump]",
+                app.diff_command_type().display_name()
+            )
+        }
+    };
 
     let diff_content = Paragraph::new(text_content)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(format!(
-                    "Diff Content (using {}) - [h/l: scroll, j/k: files, g/G: jump]",
-                    app.config.get_diff_display_name()
-                ))
-                .style(Style::default().fg(app.theme.colors.border.0)),
+                .title(title)
+                .style(pane_border_style(app, Pane::Diff)),
         )
         .scroll((app.vertical_scroll, app.horizontal_scroll))
         .wrap(Wrap { trim: false });
 
     f.render_widget(diff_content, area);
+
+    if app.config.ui.scrollbars {
+        render_diff_scrollbars(f, area, app);
+    }
+}
+
+/// Draw a scrollbar over the diff pane's right border for content that does not fit
+///
+/// Uses the content and viewport sizes measured by `App::clamp_scroll`.
+fn render_diff_scrollbars(f: &mut Frame, area: Rect, app: &App) {
+    let thumb_style = Style::default().fg(app.theme.colors.border_focused.0);
+    let track_style = Style::default().fg(app.theme.colors.border.0);
+
+    let max_vertical_scroll = app
+        .diff_row_count
+        .saturating_sub(app.diff_viewport_height as usize);
+    if max_vertical_scroll > 0 {
+        let mut state = ScrollbarState::new(max_vertical_scroll)
+            .viewport_content_length(app.diff_viewport_height as usize)
+            .position(app.vertical_scroll as usize);
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None)
+            .thumb_style(thumb_style)
+            .track_style(track_style);
+        let track = area.inner(Margin {
+            vertical: 1,
+            horizontal: 0,
+        });
+        f.render_stateful_widget(scrollbar, track, &mut state);
+    }
+    // The diff wraps long lines rather than scrolling sideways, so it needs no horizontal bar
+}
+
+/// Replace git's "\ No newline at end of file" markers with a dimmed annotation
+fn annotate_no_newline_markers(text: &mut Text, app: &App) {
+    for line in &mut text.lines {
+        let content: String = line
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+        if DiffParser::is_no_newline_marker(&content) {
+            *line = Line::from(Span::styled(
+                NO_NEWLINE_ANNOTATION,
+                Style::default()
+                    .fg(app.theme.colors.text_dim.0)
+                    .add_modifier(ratatui::style::Modifier::ITALIC),
+            ));
+        }
+    }
+}
+
+/// Color the sides of each conflict between `<<<<<<<` and `>>>>>>>` markers
+///
+/// Our side, the merge base (diff3 style, after `|||||||`) and their side each get their own
+/// color, and the marker lines are bold.
+fn highlight_conflict_sections(text: &mut Text, app: &App) {
+    #[derive(Clone, Copy)]
+    enum Section {
+        Ours,
+        Base,
+        Theirs,
+    }
+
+    let colors = &app.theme.colors;
+    let marker_style = Style::default()
+        .fg(colors.title.0)
+        .add_modifier(ratatui::style::Modifier::BOLD);
+    let mut section = None;
+    for line in &mut text.lines {
+        let content: String = line
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+
+        let marker = match (section, content.get(..7)) {
+            (_, Some("<<<<<<<")) => Some(Some(Section::Ours)),
+            (Some(Section::Ours), Some("|||||||")) => Some(Some(Section::Base)),
+            (Some(Section::Ours | Section::Base), Some("=======")) => Some(Some(Section::Theirs)),
+            (Some(Section::Theirs), Some(">>>>>>>")) => Some(None),
+            _ => None,
+        };
+        if let Some(next) = marker {
+            section = next;
+            line.style = line.style.patch(marker_style);
+            continue;
+        }
+
+        let color = match section {
+            Some(Section::Ours) => colors.status_added.0,
+            Some(Section::Base) => colors.text_dim.0,
+            Some(Section::Theirs) => colors.status_modified.0,
+            None => continue,
+        };
+        line.style = line.style.patch(Style::default().fg(color));
+    }
+}
+
+/// Highlight trailing whitespace and indentation mixing tabs and spaces on added lines
+fn highlight_whitespace_errors(text: &mut Text, app: &App) {
+    // "+++ b/x" names the file only before a file's first hunk
+    let mut in_hunk = false;
+    for line in &mut text.lines {
+        let content: String = line
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+        if content.starts_with("@@") {
+            in_hunk = true;
+        } else if content.starts_with("diff ") {
+            in_hunk = false;
+        }
+        if !in_hunk && content.starts_with("+++ ") {
+            continue;
+        }
+        let Some(body) = content.strip_prefix('+') else {
+            continue;
+        };
+        // A CRLF line ending is not trailing whitespace
+        let body = body.strip_suffix('\r').unwrap_or(body);
+        let body_end = 1 + body.chars().count();
+
+        let indent: Vec<char> = body
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect();
+        if indent.contains(&' ') && indent.contains(&'\t') {
+            patch_char_range(
+                line,
+                1..1 + indent.len(),
+                Style::default().bg(app.theme.colors.status_modified.0),
+            );
+        }
+
+        let trailing = body.chars().rev().take_while(|c| c.is_whitespace()).count();
+        if trailing > 0 {
+            patch_char_range(
+                line,
+                body_end - trailing..body_end,
+                Style::default().bg(app.theme.colors.status_removed.0),
+            );
+        }
+    }
+}
+
+/// Apply a style on top of the given character range of a line, splitting spans as needed
+fn patch_char_range(line: &mut Line, range: std::ops::Range<usize>, style: Style) {
+    let mut spans = Vec::with_capacity(line.spans.len() + 2);
+    let mut offset = 0;
+    for span in line.spans.drain(..) {
+        let len = span.content.chars().count();
+        let start = range.start.clamp(offset, offset + len) - offset;
+        let end = range.end.clamp(offset, offset + len) - offset;
+        offset += len;
+        if start == end {
+            spans.push(span);
+            continue;
+        }
+
+        let chars: Vec<char> = span.content.chars().collect();
+        for (part, patched) in [(0..start, false), (start..end, true), (end..len, false)] {
+            if part.is_empty() {
+                continue;
+            }
+            let part_style = if patched {
+                span.style.patch(style)
+            } else {
+                span.style
+            };
+            spans.push(Span::styled(
+                chars[part].iter().collect::<String>(),
+                part_style,
+            ));
+        }
+    }
+    line.spans = spans;
+}
+
+/// Put a checkbox before each hunk header of the selected file
+fn annotate_hunk_checks(text: &mut Text, app: &App) {
+    let Some(tree_item) = app
+        .get_current_file_tree_items()
+        .get(app.selected_index)
+        .filter(|tree_item| !tree_item.is_directory)
+    else {
+        return;
+    };
+
+    for line in &mut text.lines {
+        let content: String = line
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+        if DiffParser::is_hunk_header(&content) {
+            let (checkbox, color) = if app.is_hunk_checked(&tree_item.full_path, &content) {
+                ("☑ ", app.theme.colors.status_added.0)
+            } else {
+                ("☐ ", app.theme.colors.text_secondary.0)
+            };
+            line.spans
+                .insert(0, Span::styled(checkbox, Style::default().fg(color)));
+        }
+    }
 }
 
 /// Check if we should refresh the diff with new width
-fn should_refresh_diff_width(_app: &App, current_width: u16) -> bool {
+///
+/// Width changes are debounced so that dragging the window coalesces into a single refresh
+/// once the width has been stable for `ui.resizeDebounceMs`.
+fn should_refresh_diff_width(app: &mut App, current_width: u16) -> bool {
+    // Always refresh on the first render
+    if app.last_refresh_width == 0 {
+        app.last_refresh_width = current_width;
+        return true;
+    }
+
     // Only refresh if width has changed significantly (by more than 5 characters)
     // to avoid constant re-rendering
-    static mut LAST_WIDTH: u16 = 0;
-    unsafe {
-        if LAST_WIDTH == 0 || (current_width as i16 - LAST_WIDTH as i16).abs() > 5 {
-            LAST_WIDTH = current_width;
-            true
-        } else {
-            false
+    if current_width.abs_diff(app.last_refresh_width) <= 5 {
+        app.pending_resize = None;
+        return false;
+    }
+
+    let debounce = Duration::from_millis(app.config.ui.resize_debounce_ms);
+    let now = Instant::now();
+    let stable_since = match app.pending_resize {
+        Some((width, since)) if width == current_width => since,
+        _ => {
+            app.pending_resize = Some((current_width, now));
+            now
         }
+    };
+
+    if now.duration_since(stable_since) >= debounce {
+        app.last_refresh_width = current_width;
+        app.pending_resize = None;
+        true
+    } else {
+        false
     }
 }
 
-pub fn render_status_line(f: &mut Frame, area: Rect, app: &App) {
-    let current_items = app.get_current_file_tree_items();
-    let status_spans = if let Some(tree_item) = current_items.get(app.selected_index) {
-        let mut spans = Vec::new();
-
-        if tree_item.is_directory {
-            spans.push(Span::raw(" : "));
+/// Get the diff stats of a file as spans, with added and removed counts colored
+fn stats_spans(app: &App, file_diff: &FileDiff) -> Vec<Span<'static>> {
+    let stats_string = file_diff.diff_stats();
+    let stats_parts: Vec<&str> = stats_string.split_whitespace().collect();
+    let mut spans = Vec::new();
+    for (i, part) in stats_parts.iter().enumerate() {
+        if part.starts_with('+') {
             spans.push(Span::styled(
-                tree_item.full_path.clone(),
-                Style::default().fg(app.theme.colors.tree_directory.0),
+                part.to_string(),
+                Style::default().fg(app.theme.colors.status_added.0),
             ));
-            spans.push(Span::raw(" | Directory | "));
-        } else if let Some(file_diff) = &tree_item.file_diff {
-            spans.push(Span::raw(format!(" {}: ", file_diff.get_file_icon())));
+        } else if part.starts_with('-') {
             spans.push(Span::styled(
-                tree_item.full_path.clone(),
-                Style::default().fg(app.theme.colors.tree_file.0),
+                part.to_string(),
+                Style::default().fg(app.theme.colors.status_removed.0),
             ));
-            spans.push(Span::raw(" | "));
+        } else {
+            spans.push(Span::raw(part.to_string()));
+        }
+        if i < stats_parts.len() - 1 {
+            spans.push(Span::raw(" "));
+        }
+    }
+    spans
+}
 
-            // Add colored diff stats
-            let stats_string = file_diff.diff_stats();
-            let stats_parts: Vec<&str> = stats_string.split_whitespace().collect();
-            for (i, part) in stats_parts.iter().enumerate() {
-                if part.starts_with('+') {
-                    spans.push(Span::styled(
-                        part.to_string(),
-                        Style::default().fg(app.theme.colors.status_added.0),
-                    ));
-                } else if part.starts_with('-') {
-                    spans.push(Span::styled(
-                        part.to_string(),
-                        Style::default().fg(app.theme.colors.status_removed.0),
-                    ));
+/// Fill in the `ui.statusFormat` template for the selected item
+///
+/// Placeholders are {path}, {stats}, {scroll}, {index}, {percent} and {mode}; anything
+/// else in braces is shown literally.
+fn format_status_line(app: &App, tree_item: &FileTreeItem, template: &str) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+        if start > 0 {
+            spans.push(Span::raw(rest[..start].to_string()));
+        }
+
+        match &rest[start + 1..end] {
+            "path" => {
+                let color = if tree_item.is_directory {
+                    app.theme.colors.tree_directory.0
                 } else {
-                    spans.push(Span::raw(part.to_string()));
+                    app.theme.colors.tree_file.0
+                };
+                spans.push(Span::styled(
+                    tree_item.full_path.clone(),
+                    Style::default().fg(color),
+                ));
+            }
+            "stats" => {
+                if let Some(file_diff) = &tree_item.file_diff {
+                    spans.extend(stats_spans(app, file_diff));
                 }
-                if i < stats_parts.len() - 1 {
-                    spans.push(Span::raw(" "));
+            }
+            "scroll" => spans.push(Span::raw(format!(
+                "{},{}",
+                app.vertical_scroll, app.horizontal_scroll
+            ))),
+            "index" => {
+                if let Some((position, total)) = app.selected_file_position() {
+                    spans.push(Span::raw(format!("{position}/{total}")));
                 }
             }
+            "percent" => spans.push(Span::raw(match app.scroll_percentage() {
+                Some(percentage) => format!("{percentage}%"),
+                None => "ALL".to_string(),
+            })),
+            "mode" => spans.push(Span::raw(app.operation_mode.description())),
+            _ => spans.push(Span::raw(rest[start..=end].to_string())),
+        }
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+        spans.push(Span::raw(rest.to_string()));
+    }
+    spans
+}
+
+/// Build the default status line for the selected item
+fn default_status_spans(app: &App, tree_item: &FileTreeItem) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+
+    if tree_item.is_directory {
+        spans.push(Span::raw(" : "));
+        spans.push(Span::styled(
+            tree_item.full_path.clone(),
+            Style::default().fg(app.theme.colors.tree_directory.0),
+        ));
+        spans.push(Span::raw(" | Directory | "));
+    } else if let Some(file_diff) = &tree_item.file_diff {
+        spans.push(Span::raw(format!(
+            " {}: ",
+            file_diff.get_file_icon(&app.icon_overrides)
+        )));
+        spans.push(Span::styled(
+            tree_item.full_path.clone(),
+            Style::default().fg(app.theme.colors.tree_file.0),
+        ));
+        spans.push(Span::raw(" | "));
+        if file_diff.is_untracked {
+            spans.push(Span::styled(
+                "Untracked",
+                Style::default().fg(app.theme.colors.status_added.0),
+            ));
             spans.push(Span::raw(" | "));
-        } else {
-            spans.push(Span::raw(format!(
-                " : {} | No diff | ",
-                tree_item.full_path
-            )));
         }
 
+        spans.extend(stats_spans(app, file_diff));
+        spans.push(Span::raw(" | "));
+        if let Some(hunks) = file_diff
+            .hunk_summary()
+            .filter(|_| app.config.ui.hunk_counts)
+        {
+            spans.push(Span::raw(format!("{hunks} | ")));
+        }
+        if let Some((old_mode, new_mode)) = file_diff.mode_change() {
+            spans.push(Span::raw(format!("mode {old_mode} → {new_mode} | ")));
+        }
+        if let Some(summary) = file_diff.rename_summary() {
+            spans.push(Span::raw(format!("{summary} | ")));
+        }
+    } else {
         spans.push(Span::raw(format!(
-            "Scroll: {},{}",
-            app.vertical_scroll, app.horizontal_scroll
+            " : {} | No diff | ",
+            tree_item.full_path
         )));
+    }
+
+    if app.reversed {
+        spans.push(Span::styled(
+            "⇄ Reversed",
+            Style::default().fg(app.theme.colors.status_removed.0),
+        ));
+        spans.push(Span::raw(" | "));
+    }
+
+    if let Some((position, total)) = app.selected_file_position() {
+        spans.push(Span::raw(format!("{position}/{total} | ")));
+    }
+
+    let progress = match app.scroll_percentage() {
+        Some(percentage) => format!("{percentage}%"),
+        None => "ALL".to_string(),
+    };
+    spans.push(Span::raw(format!(
+        "Scroll: {},{} ({progress})",
+        app.vertical_scroll, app.horizontal_scroll
+    )));
+    spans
+}
+
+pub fn render_status_line(f: &mut Frame, area: Rect, app: &App) {
+    let current_items = app.get_current_file_tree_items();
+    let status_spans = if let Some(tree_item) = current_items.get(app.selected_index) {
+        let mut spans = match &app.config.ui.status_format {
+            Some(template) => format_status_line(app, tree_item, template),
+            None => default_status_spans(app, tree_item),
+        };
+        if app.width_offset != 0 {
+            spans.push(Span::raw(format!(" | Width: {:+}", app.width_offset)));
+        }
+        if let Some(count) = app.pending_count {
+            spans.push(Span::raw(format!(" | Count: {count}")));
+        }
+        if let Some(summary) = app.search_match_summary() {
+            spans.push(Span::raw(format!(" | {summary}")));
+        }
+        if let Some(message) = &app.status_message {
+            spans.push(Span::raw(" | "));
+            spans.push(Span::styled(
+                message.clone(),
+                Style::default().fg(app.theme.colors.status_added.0),
+            ));
+        }
         spans
     } else {
         vec![Span::raw(" No item selected")]
@@ -404,3 +943,325 @@ pub fn render_search_box(f: &mut Frame, area: Rect, app: &App) {
 
     f.render_widget(search_box, area);
 }
+
+/// Draw an error from the session in a dialog over the middle of the screen
+pub fn render_error_dialog(f: &mut Frame, area: Rect, app: &App, message: &str) {
+    let width = ((u32::from(area.width) * 3 / 5) as u16).clamp(20.min(area.width), area.width);
+    let inner_width = width.saturating_sub(2).max(1) as usize;
+    // Room for the wrapped message, a blank line and the hint, inside the borders
+    let message_rows: usize = message
+        .lines()
+        .map(|line| line.chars().count().div_ceil(inner_width).max(1))
+        .sum();
+    let height = message_rows.saturating_add(4).min(area.height as usize) as u16;
+    let dialog = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    let mut lines: Vec<Line> = message.lines().map(Line::from).collect();
+    lines.push(Line::default());
+    lines.push(Line::from(Span::styled(
+        "Press any key to continue",
+        Style::default().fg(app.theme.colors.text_secondary.0),
+    )));
+    let error_color = app.theme.colors.status_removed.0;
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Error ")
+                .border_style(Style::default().fg(error_color)),
+        )
+        .style(Style::default().fg(app.theme.colors.text_primary.0))
+        .wrap(Wrap { trim: false });
+
+    f.render_widget(Clear, dialog);
+    f.render_widget(paragraph, dialog);
+}
+
+/// Show a notice instead of the panes when the terminal is too small for them
+pub fn render_too_small(f: &mut Frame, area: Rect, app: &App) {
+    let notice = Paragraph::new("Too small - enlarge the window")
+        .style(Style::default().fg(app.theme.colors.text_secondary.0))
+        .wrap(Wrap { trim: true });
+    f.render_widget(notice, area);
+}
+
+/// Get the border style of a pane, highlighted when navigation keys act on it
+fn pane_border_style(app: &App, pane: Pane) -> Style {
+    if app.focus == pane {
+        Style::default().fg(app.theme.colors.border_focused.0)
+    } else {
+        Style::default().fg(app.theme.colors.border.0)
+    }
+}
+
+/// Get the key hints for the footer, as (key, action) pairs for the current input mode
+fn footer_hints(app: &App) -> &'static [(&'static str, &'static str)] {
+    if app.error_dialog.is_some() {
+        &[("any key", "dismiss")]
+    } else if app.search_input_mode {
+        &[("Enter", "confirm"), ("Esc", "cancel"), ("↑/↓", "history")]
+    } else if let Some(prompt) = app.prompt {
+        match prompt {
+            Prompt::Range => &[("Enter", "show range"), ("Esc", "cancel")],
+            Prompt::Export => &[("Enter", "write patch"), ("Esc", "cancel")],
+            Prompt::Revision => &[("Enter", "show file"), ("Esc", "cancel")],
+            Prompt::Command => &[("Enter", "run"), ("Esc", "cancel")],
+        }
+    } else if app.overlay.is_some() {
+        &[("Esc", "back to diff"), ("j/k", "move"), ("q", "quit")]
+    } else if app.focus == Pane::Diff {
+        &[
+            ("j/k", "scroll"),
+            ("S-Tab", "files"),
+            ("Enter", "fold"),
+            ("Tab", "check"),
+            ("/", "search"),
+            ("r", "reload"),
+            ("q", "quit"),
+        ]
+    } else {
+        &[
+            ("j/k", "move"),
+            ("d/u", "scroll"),
+            ("S-Tab", "diff"),
+            ("Enter", "fold"),
+            ("Tab", "check"),
+            ("/", "search"),
+            ("r", "reload"),
+            ("q", "quit"),
+        ]
+    }
+}
+
+pub fn render_footer(f: &mut Frame, area: Rect, app: &App) {
+    let key_style = Style::default().fg(app.theme.colors.title.0);
+    let action_style = Style::default().fg(app.theme.colors.text_secondary.0);
+
+    let mut spans = Vec::new();
+    for (key, action) in footer_hints(app) {
+        spans.push(Span::styled(format!(" {key}"), key_style));
+        spans.push(Span::styled(format!(" {action} "), action_style));
+    }
+
+    f.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+/// Render the open prompt, showing its placeholder while nothing has been typed
+pub fn render_prompt(f: &mut Frame, area: Rect, app: &App, prompt: Prompt) {
+    let (placeholder, title) = match prompt {
+        Prompt::Range => (
+            "e.g. HEAD~5..HEAD or main",
+            " Ref range for this file (Enter: show, ESC: cancel)",
+        ),
+        Prompt::Export => (
+            "e.g. review.patch",
+            " Write all diffs to a patch file (Enter: write, ESC: cancel)",
+        ),
+        Prompt::Revision => (
+            "e.g. HEAD~3 or main",
+            " Show this file at revision (Enter: show, ESC: cancel)",
+        ),
+        Prompt::Command => (
+            "e.g. diff main..feature, show HEAD~3, cached, head or status",
+            " Command: switch comparison (Enter: run, ESC: cancel)",
+        ),
+    };
+    let title = match &app.prompt_error {
+        Some(error) => format!(" Error: {error} (ESC: cancel)"),
+        None => title.to_string(),
+    };
+
+    let value = &app.prompt_query;
+    let (text, style) = if value.is_empty() {
+        (
+            placeholder.to_string(),
+            Style::default()
+                .fg(app.theme.colors.text_primary.0)
+                .add_modifier(ratatui::style::Modifier::DIM),
+        )
+    } else {
+        (
+            value.to_string(),
+            Style::default().fg(app.theme.colors.text_primary.0),
+        )
+    };
+
+    let input = Paragraph::new(text)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .style(Style::default().fg(app.theme.colors.border_focused.0)),
+        )
+        .style(style);
+
+    f.render_widget(input, area);
+}
+
+/// Render a sample of the UI in the theme's colors for `ftdv themes --preview`
+///
+/// Only the theme and the given diffs are drawn from, so previewing needs no git repository.
+pub fn render_theme_preview(f: &mut Frame, theme: &Theme, file_diffs: &[FileDiff]) {
+    let colors = &theme.colors;
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(f.area());
+    let main_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(30), Constraint::Min(0)])
+        .split(layout[0]);
+    let right_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(main_chunks[1]);
+
+    // File tree, with a modified file selected to show every kind of diff line
+    let tree_items = FileTreeBuilder::build_file_tree(file_diffs);
+    let selected = tree_items
+        .iter()
+        .position(|item| {
+            item.file_diff
+                .as_ref()
+                .is_some_and(|file_diff| file_diff.change_kind == ChangeKind::Modified)
+        })
+        .or_else(|| tree_items.iter().position(|item| !item.is_directory));
+    let items: Vec<ListItem> = tree_items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let mut spans = vec![Span::raw("  ".repeat(item.depth))];
+            match &item.file_diff {
+                Some(file_diff) => {
+                    let name_color = if Some(i) == selected {
+                        colors.tree_selected_fg.0
+                    } else {
+                        colors.tree_file.0
+                    };
+                    spans.push(Span::styled(
+                        item.name.clone(),
+                        Style::default().fg(name_color),
+                    ));
+                    spans.push(Span::styled(
+                        format!(" +{}", file_diff.added_lines),
+                        Style::default().fg(colors.status_added.0),
+                    ));
+                    spans.push(Span::styled(
+                        format!(" -{}", file_diff.removed_lines),
+                        Style::default().fg(colors.status_removed.0),
+                    ));
+                }
+                None => spans.push(Span::styled(
+                    format!("▼ {}", item.name),
+                    Style::default().fg(colors.tree_directory.0),
+                )),
+            }
+            let style = if Some(i) == selected {
+                Style::default().bg(colors.tree_selected_bg.0)
+            } else {
+                Style::default()
+            };
+            ListItem::new(Line::from(spans)).style(style)
+        })
+        .collect();
+    f.render_widget(
+        List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" Files ({} items)", tree_items.len()))
+                .style(Style::default().fg(colors.border.0)),
+        ),
+        main_chunks[0],
+    );
+
+    // Status line for the selected file
+    let selected_diff = selected.and_then(|i| tree_items[i].file_diff.as_ref());
+    let status_spans = match selected_diff {
+        Some(file_diff) => vec![
+            Span::styled(
+                format!(" {}", file_diff.filename),
+                Style::default().fg(colors.tree_file.0),
+            ),
+            Span::raw(" | "),
+            Span::styled(
+                format!("+{}", file_diff.added_lines),
+                Style::default().fg(colors.status_added.0),
+            ),
+            Span::raw(" "),
+            Span::styled(
+                format!("-{}", file_diff.removed_lines),
+                Style::default().fg(colors.status_removed.0),
+            ),
+            Span::raw(" | "),
+            Span::styled("Modified", Style::default().fg(colors.status_modified.0)),
+        ],
+        None => vec![Span::raw(" No item selected")],
+    };
+    f.render_widget(
+        Paragraph::new(Line::from(status_spans))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Status")
+                    .style(Style::default().fg(colors.border_focused.0)),
+            )
+            .style(Style::default().fg(colors.status_bar_fg.0)),
+        right_chunks[0],
+    );
+
+    // The selected file's diff, colored by line kind
+    let lines: Vec<Line> = selected_diff
+        .map(|file_diff| file_diff.content.lines())
+        .into_iter()
+        .flatten()
+        .map(|line| {
+            let color = if line.starts_with("@@") {
+                colors.title.0
+            } else if line.starts_with("+++") || line.starts_with("---") {
+                colors.text_dim.0
+            } else if line.starts_with('+') {
+                colors.status_added.0
+            } else if line.starts_with('-') {
+                colors.status_removed.0
+            } else if line.starts_with(' ') {
+                colors.text_primary.0
+            } else {
+                colors.text_dim.0
+            };
+            Line::styled(line.to_string(), Style::default().fg(color))
+        })
+        .collect();
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Diff")
+                .style(Style::default().fg(colors.border_focused.0)),
+        ),
+        right_chunks[1],
+    );
+
+    // Footer with a few key hints
+    let mut spans = Vec::new();
+    for (key, action) in [
+        ("j/k", "move"),
+        ("Tab", "check"),
+        ("/", "search"),
+        ("q", "quit"),
+    ] {
+        spans.push(Span::styled(
+            format!(" {key}"),
+            Style::default().fg(colors.title.0),
+        ));
+        spans.push(Span::styled(
+            format!(" {action} "),
+            Style::default().fg(colors.text_secondary.0),
+        ));
+    }
+    f.render_widget(Paragraph::new(Line::from(spans)), layout[1]);
+}
@@ -42,7 +42,7 @@ impl Visitor<'_> for ThemeColorVisitor {
     type Value = ThemeColor;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("a color name or hex code")
+        formatter.write_str("a color name, hex code or rgb(r, g, b)")
     }
 
     fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
@@ -77,12 +77,33 @@ impl Visitor<'_> for ThemeColorVisitor {
                 let b = u8::from_str_radix(&s[5..7], 16).map_err(de::Error::custom)?;
                 Color::Rgb(r, g, b)
             }
+            s if s.starts_with("rgb(") && s.ends_with(')') => {
+                parse_rgb_components(&s[4..s.len() - 1]).map_err(de::Error::custom)?
+            }
             _ => return Err(de::Error::custom(format!("unknown color: {value}"))),
         };
         Ok(ThemeColor(color))
     }
 }
 
+/// Parse the "r, g, b" part of an rgb(r, g, b) color, each component in 0-255
+fn parse_rgb_components(components: &str) -> Result<Color, String> {
+    let values: Vec<&str> = components.split(',').map(str::trim).collect();
+    let [r, g, b] = values.as_slice() else {
+        return Err(format!(
+            "rgb() takes three components, got {}: rgb({components})",
+            values.len()
+        ));
+    };
+
+    let component = |value: &str| {
+        value
+            .parse::<u8>()
+            .map_err(|_| format!("rgb() components must be integers from 0 to 255, got {value:?}"))
+    };
+    Ok(Color::Rgb(component(r)?, component(g)?, component(b)?))
+}
+
 impl<'de> Deserialize<'de> for ThemeColor {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -172,6 +193,38 @@ impl ColorScheme {
             background: ThemeColor(Color::Black),
         }
     }
+
+    /// Light theme for light terminal backgrounds
+    pub fn light_theme() -> Self {
+        Self {
+            // File tree colors
+            tree_line: ThemeColor(Color::Gray),
+            tree_selected_bg: ThemeColor(Color::Rgb(230, 230, 250)),
+            tree_selected_fg: ThemeColor(Color::Black),
+            tree_directory: ThemeColor(Color::Blue),
+            tree_file: ThemeColor(Color::Black),
+
+            // File status colors
+            status_added: ThemeColor(Color::Green),
+            status_removed: ThemeColor(Color::Red),
+            status_modified: ThemeColor(Color::Rgb(175, 135, 0)),
+
+            // UI chrome colors
+            border: ThemeColor(Color::Gray),
+            border_focused: ThemeColor(Color::Blue),
+            title: ThemeColor(Color::Blue),
+            status_bar_bg: ThemeColor(Color::Gray),
+            status_bar_fg: ThemeColor(Color::Black),
+
+            // Text colors
+            text_primary: ThemeColor(Color::Black),
+            text_secondary: ThemeColor(Color::DarkGray),
+            text_dim: ThemeColor(Color::Gray),
+
+            // Background colors
+            background: ThemeColor(Color::White),
+        }
+    }
 }
 
 /// Theme configuration
@@ -189,3 +242,69 @@ impl Default for Theme {
         }
     }
 }
+
+impl Theme {
+    /// Names of the built-in themes
+    pub fn builtin_names() -> &'static [&'static str] {
+        &["dark", "light"]
+    }
+
+    /// Get a built-in theme by name
+    pub fn builtin(name: &str) -> Option<Self> {
+        let colors = match name {
+            "dark" => ColorScheme::dark_theme(),
+            "light" => ColorScheme::light_theme(),
+            _ => return None,
+        };
+        Some(Self {
+            name: name.to_string(),
+            colors,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_themes() {
+        for name in Theme::builtin_names() {
+            let theme = Theme::builtin(name).unwrap();
+            assert_eq!(theme.name, *name);
+        }
+        assert!(Theme::builtin("solarized").is_none());
+    }
+
+    #[test]
+    fn test_theme_color_round_trip() {
+        let color = ThemeColor(Color::Rgb(230, 230, 250));
+        let yaml = serde_yaml::to_string(&color).unwrap();
+        let parsed: ThemeColor = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(parsed, color);
+    }
+
+    #[test]
+    fn test_theme_color_rgb_syntax() {
+        let parse = |value: &str| serde_yaml::from_str::<ThemeColor>(&format!("\"{value}\""));
+
+        assert_eq!(
+            parse("rgb(12,34,56)").unwrap(),
+            ThemeColor(Color::Rgb(12, 34, 56))
+        );
+        assert_eq!(
+            parse("RGB( 0 , 128, 255 )").unwrap(),
+            ThemeColor(Color::Rgb(0, 128, 255))
+        );
+
+        // Serialized back as hex so configs stay stable across a round trip
+        let yaml = serde_yaml::to_string(&parse("rgb(12, 34, 56)").unwrap()).unwrap();
+        assert_eq!(yaml.trim(), "'#0c2238'");
+
+        let error = parse("rgb(256, 0, 0)").unwrap_err().to_string();
+        assert!(error.contains("0 to 255"), "{error}");
+        assert!(parse("rgb(-1, 0, 0)").is_err());
+        let error = parse("rgb(1, 2)").unwrap_err().to_string();
+        assert!(error.contains("three components"), "{error}");
+    }
+}
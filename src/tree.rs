@@ -1,4 +1,5 @@
-use crate::parser::FileDiff;
+use crate::config::TreeSort;
+use crate::parser::{FileDiff, StatusGroup};
 use std::collections::HashSet;
 
 #[derive(Clone)]
@@ -15,6 +16,45 @@ pub struct FileTreeItem {
     pub dir_file_count: usize,  // Total files in this directory (recursive)
     pub dir_added_lines: usize, // Total added lines in this directory (recursive)
     pub dir_removed_lines: usize, // Total removed lines in this directory (recursive)
+    pub status_group: Option<StatusGroup>, // Group header this item is listed under
+}
+
+impl FileTreeItem {
+    /// Path that identifies this item in the tree
+    ///
+    /// In status mode a file can be listed under several groups, so file paths are qualified
+    /// with their group header, like the directory paths under it.
+    pub fn tree_path(&self) -> String {
+        match self.status_group {
+            Some(group) if !self.is_directory => format!("{}/{}", group.label(), self.full_path),
+            _ => self.full_path.clone(),
+        }
+    }
+
+    /// Label for this item: its full path, or just its basename
+    pub fn label(&self, full_path: bool) -> &str {
+        if full_path {
+            match self.repo_path() {
+                // Status group headers have no path of their own
+                "" => &self.name,
+                path => path,
+            }
+        } else {
+            self.name.rsplit('/').next().unwrap_or(&self.name)
+        }
+    }
+
+    /// Repository-relative path of this item, without its status group header
+    pub fn repo_path(&self) -> &str {
+        match self.status_group {
+            Some(group) if self.is_directory => self
+                .full_path
+                .strip_prefix(group.label())
+                .map(|path| path.trim_start_matches('/'))
+                .unwrap_or(&self.full_path),
+            _ => &self.full_path,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -30,6 +70,113 @@ struct TreeNode {
     removed_lines: usize,
 }
 
+/// A sorted file tree with directory statistics, built once per set of file diffs
+///
+/// Building sorts the files and sums up the directory statistics, while flattening only walks
+/// the tree, so collapsing or expanding a directory just flattens the same tree again.
+#[derive(Clone)]
+pub struct FileTree {
+    root: TreeNode,
+    grouped: bool, // Top-level nodes are status group headers
+}
+
+impl FileTree {
+    pub fn build(file_diffs: &[FileDiff]) -> Self {
+        Self::build_sorted(file_diffs, TreeSort::default())
+    }
+
+    /// Build the tree, ordering the entries of each directory as given
+    pub fn build_sorted<'a>(
+        file_diffs: impl IntoIterator<Item = &'a FileDiff>,
+        sort: TreeSort,
+    ) -> Self {
+        let file_diffs: Vec<&FileDiff> = file_diffs.into_iter().collect();
+        let grouped = file_diffs.iter().any(|fd| fd.status_group.is_some());
+
+        // Build a true tree structure like diffnav does
+        let root = if grouped {
+            FileTreeBuilder::build_grouped_tree_structure(&file_diffs, sort)
+        } else {
+            FileTreeBuilder::build_tree_structure(&file_diffs, sort)
+        };
+
+        Self { root, grouped }
+    }
+
+    /// Flatten the tree into display order, hiding the contents of collapsed directories
+    pub fn flatten(&self, collapsed_dirs: &HashSet<String>) -> Vec<FileTreeItem> {
+        let mut result = Vec::new();
+        FileTreeBuilder::flatten_tree_with_collapsed(
+            &self.root,
+            0,
+            &mut Vec::new(),
+            &mut result,
+            collapsed_dirs,
+        );
+
+        if self.grouped {
+            for item in &mut result {
+                item.status_group = match &item.file_diff {
+                    Some(file_diff) => file_diff.status_group,
+                    None => StatusGroup::ALL.into_iter().find(|group| {
+                        item.full_path == group.label()
+                            || item.full_path.starts_with(&format!("{}/", group.label()))
+                    }),
+                };
+            }
+        }
+
+        result
+    }
+
+    /// Get the files in a directory's subtree in tree order, as (full path, diff) pairs
+    pub fn files_under(&self, directory: &str) -> Vec<(&str, &FileDiff)> {
+        let mut files = Vec::new();
+        if let Some(node) = Self::find_directory(&self.root, directory) {
+            Self::collect_files(node, &mut files);
+        }
+        files
+    }
+
+    fn find_directory<'a>(node: &'a TreeNode, path: &str) -> Option<&'a TreeNode> {
+        node.children
+            .iter()
+            .filter(|child| child.is_directory)
+            .find_map(|child| {
+                if child.full_path == path {
+                    Some(child)
+                } else {
+                    Self::find_directory(child, path)
+                }
+            })
+    }
+
+    fn collect_files<'a>(node: &'a TreeNode, files: &mut Vec<(&'a str, &'a FileDiff)>) {
+        for child in &node.children {
+            if child.is_directory {
+                Self::collect_files(child, files);
+            } else if let Some(file_diff) = &child.file_diff {
+                files.push((child.full_path.as_str(), file_diff));
+            }
+        }
+    }
+
+    /// Get a flat list of the file items in tree order, labelled with their full paths
+    pub fn file_list(&self) -> Vec<FileTreeItem> {
+        self.flatten(&HashSet::new())
+            .into_iter()
+            .filter(|item| !item.is_directory)
+            .map(|item| FileTreeItem {
+                name: item.full_path.clone(),
+                depth: 0,
+                is_last_child: false,
+                parent_is_last: Vec::new(),
+                ..item
+            })
+            .collect()
+    }
+}
+
 pub struct FileTreeBuilder;
 
 impl FileTreeBuilder {
@@ -41,17 +188,85 @@ impl FileTreeBuilder {
         file_diffs: &[FileDiff],
         collapsed_dirs: &HashSet<String>,
     ) -> Vec<FileTreeItem> {
-        // First, build a true tree structure like diffnav does
-        let root = Self::build_tree_structure(file_diffs);
+        FileTree::build(file_diffs).flatten(collapsed_dirs)
+    }
 
-        // Then flatten it into display order while preserving hierarchy
-        let mut result = Vec::new();
-        Self::flatten_tree_with_collapsed(&root, 0, &mut Vec::new(), &mut result, collapsed_dirs);
+    /// Build a flat list of file items in tree order, labelled with their full paths
+    pub fn build_file_list(file_diffs: &[FileDiff]) -> Vec<FileTreeItem> {
+        FileTree::build(file_diffs).file_list()
+    }
 
-        result
+    /// Get every directory path that appears in the given file diffs
+    ///
+    /// Status group headers count as directories, and the paths under them are qualified with
+    /// the header like in the tree.
+    pub fn directory_paths<'a>(
+        file_diffs: impl IntoIterator<Item = &'a FileDiff>,
+    ) -> HashSet<String> {
+        let mut directories = HashSet::new();
+        for file_diff in file_diffs {
+            let group = file_diff.status_group.map(StatusGroup::label);
+            if let Some(label) = group {
+                directories.insert(label.to_string());
+            }
+
+            let parts: Vec<&str> = file_diff.filename.split('/').collect();
+            for i in 0..parts.len().saturating_sub(1) {
+                let path = parts[..=i].join("/");
+                directories.insert(match group {
+                    Some(label) => format!("{label}/{path}"),
+                    None => path,
+                });
+            }
+        }
+        directories
+    }
+
+    /// Build a tree with one top-level header per status group, each holding its own file tree
+    fn build_grouped_tree_structure(file_diffs: &[&FileDiff], sort: TreeSort) -> TreeNode {
+        let mut root = TreeNode {
+            name: "".to_string(),
+            full_path: "".to_string(),
+            is_directory: true,
+            file_diff: None,
+            children: Vec::new(),
+            file_count: 0,
+            added_lines: 0,
+            removed_lines: 0,
+        };
+
+        for group in StatusGroup::ALL {
+            let group_diffs: Vec<&FileDiff> = file_diffs
+                .iter()
+                .filter(|fd| fd.status_group == Some(group))
+                .copied()
+                .collect();
+            if group_diffs.is_empty() {
+                continue;
+            }
+
+            // The same directory can appear under several groups, so qualify its path
+            // with the header to keep their collapsed state apart
+            let mut group_node = Self::build_tree_structure(&group_diffs, sort);
+            Self::prefix_directory_paths(&mut group_node, group.label());
+            group_node.name = group.label().to_string();
+            group_node.full_path = group.label().to_string();
+            root.children.push(group_node);
+        }
+
+        root
+    }
+
+    fn prefix_directory_paths(node: &mut TreeNode, prefix: &str) {
+        for child in &mut node.children {
+            if child.is_directory {
+                child.full_path = format!("{prefix}/{}", child.full_path);
+                Self::prefix_directory_paths(child, prefix);
+            }
+        }
     }
 
-    fn build_tree_structure(file_diffs: &[FileDiff]) -> TreeNode {
+    fn build_tree_structure(file_diffs: &[&FileDiff], sort: TreeSort) -> TreeNode {
         let mut root = TreeNode {
             name: "".to_string(),
             full_path: "".to_string(),
@@ -110,12 +325,11 @@ impl FileTreeBuilder {
 
         // Build tree incrementally like diffnav
         for file_diff in sorted_diffs {
-            let filename = file_diff.filename.clone();
-            Self::add_file_to_tree(&mut root, &filename, Some(file_diff));
+            Self::add_file_to_tree(&mut root, &file_diff.filename, Some(file_diff.clone()));
         }
 
         // Sort all children recursively
-        Self::sort_tree_children(&mut root);
+        Self::sort_tree_children(&mut root, sort);
 
         // Calculate directory statistics
         Self::calculate_directory_stats(&mut root);
@@ -176,17 +390,24 @@ impl FileTreeBuilder {
         }
     }
 
-    fn sort_tree_children(node: &mut TreeNode) {
-        node.children
-            .sort_by(|a, b| match (a.is_directory, b.is_directory) {
-                (true, false) => std::cmp::Ordering::Less,
-                (false, true) => std::cmp::Ordering::Greater,
-                _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-            });
+    fn sort_tree_children(node: &mut TreeNode, sort: TreeSort) {
+        node.children.sort_by(|a, b| {
+            let by_name = || a.name.to_lowercase().cmp(&b.name.to_lowercase());
+            match (sort, a.is_directory, b.is_directory) {
+                (TreeSort::Interleaved, _, _) => by_name(),
+                (TreeSort::DirsFirst, true, false) | (TreeSort::FilesFirst, false, true) => {
+                    std::cmp::Ordering::Less
+                }
+                (TreeSort::DirsFirst, false, true) | (TreeSort::FilesFirst, true, false) => {
+                    std::cmp::Ordering::Greater
+                }
+                _ => by_name(),
+            }
+        });
 
         // Recursively sort children
         for child in &mut node.children {
-            Self::sort_tree_children(child);
+            Self::sort_tree_children(child, sort);
         }
     }
 
@@ -240,6 +461,7 @@ impl FileTreeBuilder {
                 dir_file_count: node.file_count,
                 dir_added_lines: node.added_lines,
                 dir_removed_lines: node.removed_lines,
+                status_group: None,
             });
         }
 
@@ -251,11 +473,10 @@ impl FileTreeBuilder {
                 let is_last = i == node.children.len() - 1;
 
                 if depth > 0 {
-                    if parent_is_last.len() <= depth {
-                        parent_is_last.push(is_last);
-                    } else {
-                        parent_is_last[depth] = is_last;
-                    }
+                    // Slot `depth` belongs to this level; the root's slot stays true since
+                    // top-level items draw no connector
+                    parent_is_last.resize(depth + 1, true);
+                    parent_is_last[depth] = is_last;
                 }
 
                 Self::flatten_tree_with_collapsed(
@@ -274,3 +495,146 @@ impl FileTreeBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::DiffParser;
+
+    #[test]
+    fn test_flatten_built_tree_with_collapsed_directories() {
+        let diffs = DiffParser::parse(
+            "diff --git a/src/b.rs b/src/b.rs\n+b\ndiff --git a/README.md b/README.md\n+r\ndiff --git a/src/a.rs b/src/a.rs\n+a\n",
+        );
+        let tree = FileTree::build(&diffs);
+        let names = |items: &[FileTreeItem]| -> Vec<String> {
+            items.iter().map(|item| item.name.clone()).collect()
+        };
+
+        let expanded = tree.flatten(&HashSet::new());
+        assert_eq!(names(&expanded), ["src", "a.rs", "b.rs", "README.md"]);
+
+        // Flattening the same tree again honors the collapsed set
+        let collapsed = tree.flatten(&HashSet::from(["src".to_string()]));
+        assert_eq!(names(&collapsed), ["src", "README.md"]);
+        assert!(!collapsed[0].is_expanded);
+        assert_eq!(collapsed[0].dir_file_count, 2);
+        assert_eq!(
+            names(&collapsed),
+            names(&FileTreeBuilder::build_file_tree_with_collapsed(
+                &diffs,
+                &HashSet::from(["src".to_string()])
+            ))
+        );
+
+        assert_eq!(
+            names(&tree.file_list()),
+            ["src/a.rs", "src/b.rs", "README.md"]
+        );
+    }
+
+    #[test]
+    fn test_cross_directory_rename_is_listed_once() {
+        let diffs = DiffParser::parse(
+            "diff --git a/src/a/foo.rs b/src/b/foo.rs\nsimilarity index 100%\nrename from src/a/foo.rs\nrename to src/b/foo.rs\n",
+        );
+        let tree = FileTree::build(&diffs);
+
+        let files = tree.file_list();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].full_path, "src/b/foo.rs");
+        assert!(tree.files_under("src/a").is_empty());
+    }
+
+    #[test]
+    fn test_files_under_directory() {
+        let diffs = DiffParser::parse(
+            "diff --git a/src/ui/b.rs b/src/ui/b.rs\n+b\ndiff --git a/README.md b/README.md\n+r\ndiff --git a/src/a.rs b/src/a.rs\n+a\n",
+        );
+        let tree = FileTree::build(&diffs);
+        let paths = |directory: &str| -> Vec<String> {
+            tree.files_under(directory)
+                .into_iter()
+                .map(|(path, _)| path.to_string())
+                .collect()
+        };
+
+        assert_eq!(paths("src"), ["src/ui/b.rs", "src/a.rs"]);
+        assert_eq!(paths("src/ui"), ["src/ui/b.rs"]);
+        assert!(paths("docs").is_empty());
+    }
+
+    #[test]
+    fn test_tree_sort_modes() {
+        let diffs = DiffParser::parse(
+            "diff --git a/b.rs b/b.rs\n+b\ndiff --git a/c/d.rs b/c/d.rs\n+d\ndiff --git a/a/e.rs b/a/e.rs\n+e\ndiff --git a/z.rs b/z.rs\n+z\n",
+        );
+        let names = |sort: TreeSort| -> Vec<String> {
+            FileTree::build_sorted(&diffs, sort)
+                .flatten(&HashSet::new())
+                .into_iter()
+                .map(|item| item.name)
+                .collect()
+        };
+
+        assert_eq!(
+            names(TreeSort::DirsFirst),
+            ["a", "e.rs", "c", "d.rs", "b.rs", "z.rs"]
+        );
+        assert_eq!(
+            names(TreeSort::FilesFirst),
+            ["b.rs", "z.rs", "a", "e.rs", "c", "d.rs"]
+        );
+        assert_eq!(
+            names(TreeSort::Interleaved),
+            ["a", "e.rs", "b.rs", "c", "d.rs", "z.rs"]
+        );
+
+        // Connectors follow the sorted order: only the final sibling gets the last branch
+        let nested = DiffParser::parse(
+            "diff --git a/a/x.rs b/a/x.rs\n+x\ndiff --git a/a/y/z.rs b/a/y/z.rs\n+z\ndiff --git a/a/w.rs b/a/w.rs\n+w\n",
+        );
+        let connectors = |sort: TreeSort| -> Vec<(String, bool)> {
+            FileTree::build_sorted(&nested, sort)
+                .flatten(&HashSet::new())
+                .into_iter()
+                .filter(|item| item.depth > 0)
+                .map(|item| (item.name, item.is_last_child))
+                .collect()
+        };
+        let expected = |items: &[(&str, bool)]| -> Vec<(String, bool)> {
+            items
+                .iter()
+                .map(|&(name, is_last)| (name.to_string(), is_last))
+                .collect()
+        };
+
+        assert_eq!(
+            connectors(TreeSort::DirsFirst),
+            expected(&[
+                ("y", false),
+                ("z.rs", true),
+                ("w.rs", false),
+                ("x.rs", true)
+            ])
+        );
+        assert_eq!(
+            connectors(TreeSort::FilesFirst),
+            expected(&[
+                ("w.rs", false),
+                ("x.rs", false),
+                ("y", true),
+                ("z.rs", true)
+            ])
+        );
+        assert_eq!(
+            connectors(TreeSort::Interleaved),
+            expected(&[
+                ("w.rs", false),
+                ("x.rs", false),
+                ("y", true),
+                ("z.rs", true)
+            ])
+        );
+    }
+}